@@ -0,0 +1,170 @@
+//! Animated emotes, Discord rejects them above 256KB so they get shrunk step by step.
+use crate::{
+    resize::{self, Aspect, Filter, Window},
+    trim,
+};
+use color_quant::NeuQuant;
+use gif::{DisposalMethod, Encoder, Repeat};
+use image::{gif::GifDecoder, AnimationDecoder, ImageError, RgbaImage};
+use std::{borrow::Cow, fmt, io::Cursor};
+
+/// Discord rejects animated emotes above 256KB.
+pub const MAX_BYTES: usize = 256_000;
+
+/// Color counts that are tried in order, the last slot is reserved for transparency.
+const COLORS: [usize; 5] = [256, 128, 64, 32, 16];
+/// Only every n-th frame is kept, tried in order after the colors ran out.
+const FRAME_STEPS: [usize; 4] = [1, 2, 3, 4];
+
+#[derive(Debug)]
+pub enum Error {
+    Decode(ImageError),
+    Encode(gif::EncodingError),
+    TooLarge(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Decode(why) => write!(f, "decoding gif: {}", why),
+            Error::Encode(why) => write!(f, "encoding gif: {}", why),
+            Error::TooLarge(size) => write!(f, "smallest result was {} bytes", size),
+        }
+    }
+}
+
+struct Frame {
+    image: RgbaImage,
+    /// delay in 10ms units, like gif stores it
+    delay: u16,
+}
+
+/// Resizes every frame of the gif to `size`x`size` and shrinks the result until it's below
+/// `MAX_BYTES` by dropping duplicate frames, reducing the colors and finally the frame rate.
+/// With `trim` the borders shared by all frames are cut off first.
+pub fn optimize(
+    data: &[u8],
+    size: u32,
+    trim: bool,
+    filter: Filter,
+    aspect: Aspect,
+    window: Window,
+) -> Result<Vec<u8>, Error> {
+    let decoder = GifDecoder::new(Cursor::new(data)).map_err(Error::Decode)?;
+    let frames = decoder
+        .into_frames()
+        .collect_frames()
+        .map_err(Error::Decode)?;
+
+    // one box for all frames, otherwise the subject would jump around
+    let bounds = match trim {
+        true => frames
+            .iter()
+            .filter_map(|f| trim::bounds(f.buffer()))
+            .fold(None, |acc, b| {
+                Some(acc.map_or(b, |acc| trim::union(acc, b)))
+            }),
+        false => None,
+    };
+
+    let frames = frames
+        .into_iter()
+        .map(|f| {
+            let (num, denom) = f.delay().numer_denom_ms();
+            let image = match bounds {
+                Some(bounds) => trim::crop(f.buffer(), bounds),
+                None => f.into_buffer(),
+            };
+
+            Frame {
+                image: resize::square_rgba(&image, size, filter, aspect, window),
+                delay: (num / denom.max(1) / 10).max(1) as u16,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let frames = dedup(frames);
+
+    let mut smallest = usize::MAX;
+    for step in FRAME_STEPS.iter() {
+        let frames = drop_frames(&frames, *step);
+        for colors in COLORS.iter() {
+            let buf = encode(&frames, size, *colors)?;
+            if buf.len() <= MAX_BYTES {
+                return Ok(buf);
+            }
+            smallest = smallest.min(buf.len());
+        }
+    }
+
+    Err(Error::TooLarge(smallest))
+}
+
+/// Merges identical consecutive frames into one with the combined delay.
+fn dedup(frames: Vec<Frame>) -> Vec<Frame> {
+    let mut out: Vec<Frame> = Vec::with_capacity(frames.len());
+    for frame in frames {
+        match out.last_mut() {
+            Some(last) if last.image == frame.image => {
+                last.delay = last.delay.saturating_add(frame.delay)
+            }
+            _ => out.push(frame),
+        }
+    }
+
+    out
+}
+
+/// Keeps every `step`-th frame, the dropped delays are added to the kept frame.
+fn drop_frames(frames: &[Frame], step: usize) -> Vec<Frame> {
+    frames
+        .chunks(step)
+        .map(|chunk| Frame {
+            image: chunk[0].image.clone(),
+            delay: chunk
+                .iter()
+                .fold(0u16, |acc, f| acc.saturating_add(f.delay)),
+        })
+        .collect()
+}
+
+fn encode(frames: &[Frame], size: u32, colors: usize) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    {
+        let mut encoder =
+            Encoder::new(&mut buf, size as u16, size as u16, &[]).map_err(Error::Encode)?;
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(Error::Encode)?;
+
+        for frame in frames {
+            let pixels = frame.image.as_raw();
+            let quant = NeuQuant::new(10, colors - 1, pixels);
+            let transparent = (colors - 1) as u8;
+            let indices = pixels
+                .chunks(4)
+                .map(|p| match p[3] < 128 {
+                    true => transparent,
+                    false => quant.index_of(p) as u8,
+                })
+                .collect::<Vec<_>>();
+
+            let mut palette = quant.color_map_rgb();
+            palette.resize(colors * 3, 0);
+
+            let frame = gif::Frame {
+                width: size as u16,
+                height: size as u16,
+                delay: frame.delay,
+                dispose: DisposalMethod::Background,
+                transparent: Some(transparent),
+                palette: Some(palette),
+                buffer: Cow::Owned(indices),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(Error::Encode)?;
+        }
+    }
+
+    Ok(buf)
+}
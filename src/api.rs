@@ -0,0 +1,259 @@
+//! The Discord calls of the submission pipeline behind a trait, so `submit` and `publish` can run
+//! against `mock::Mock` (in the tests or with the `mock` feature) instead of a live bot. `Http`
+//! sends them through `rest`.
+use crate::rest;
+use serenity::{
+    http::Http,
+    model::{
+        channel::{Message, ReactionType},
+        guild::Emoji,
+        id::{ChannelId, EmojiId, GuildId, MessageId},
+        user::User,
+    },
+};
+use std::sync::Arc;
+
+pub trait Discord {
+    /// Sends `content` with `files` below it, the bot adds `reactions` afterwards.
+    fn post(
+        &self,
+        channel: ChannelId,
+        content: &str,
+        files: Vec<(&[u8], &str)>,
+        reactions: Vec<ReactionType>,
+    ) -> serenity::Result<Message>;
+    fn remove(&self, channel: ChannelId, message: MessageId) -> serenity::Result<()>;
+    fn fetch(&self, channel: ChannelId, message: MessageId) -> serenity::Result<Message>;
+    /// `image` is a data URI like `data:image/png;base64,...`.
+    fn upload_emoji(&self, guild: GuildId, name: &str, image: &str) -> serenity::Result<Emoji>;
+    fn remove_emoji(&self, guild: GuildId, emoji: EmojiId) -> serenity::Result<()>;
+    /// Fails when `user` takes no DMs from the members of the guild.
+    fn dm(&self, user: &User, content: &str) -> serenity::Result<()>;
+}
+
+impl Discord for Http {
+    fn post(
+        &self,
+        channel: ChannelId,
+        content: &str,
+        files: Vec<(&[u8], &str)>,
+        reactions: Vec<ReactionType>,
+    ) -> serenity::Result<Message> {
+        rest::post("Sending message", || {
+            channel.send_message(self, |m| {
+                m.content(content);
+                m.add_files(files.clone());
+                m.reactions(reactions.clone())
+            })
+        })
+    }
+
+    fn remove(&self, channel: ChannelId, message: MessageId) -> serenity::Result<()> {
+        rest::call("Deleting message", || channel.delete_message(self, message))
+    }
+
+    fn fetch(&self, channel: ChannelId, message: MessageId) -> serenity::Result<Message> {
+        rest::call("Fetching message", || channel.message(self, message))
+    }
+
+    fn upload_emoji(&self, guild: GuildId, name: &str, image: &str) -> serenity::Result<Emoji> {
+        rest::post("Creating emoji", || guild.create_emoji(self, name, image))
+    }
+
+    fn remove_emoji(&self, guild: GuildId, emoji: EmojiId) -> serenity::Result<()> {
+        rest::call("Deleting emoji", || guild.delete_emoji(self, emoji))
+    }
+
+    fn dm(&self, user: &User, content: &str) -> serenity::Result<()> {
+        rest::post("Sending DM", || user.dm(self, |m| m.content(content))).map(|_| ())
+    }
+}
+
+/// The bot keeps its `Http` in an `Arc`.
+impl<T: Discord + ?Sized> Discord for Arc<T> {
+    fn post(
+        &self,
+        channel: ChannelId,
+        content: &str,
+        files: Vec<(&[u8], &str)>,
+        reactions: Vec<ReactionType>,
+    ) -> serenity::Result<Message> {
+        (**self).post(channel, content, files, reactions)
+    }
+
+    fn remove(&self, channel: ChannelId, message: MessageId) -> serenity::Result<()> {
+        (**self).remove(channel, message)
+    }
+
+    fn fetch(&self, channel: ChannelId, message: MessageId) -> serenity::Result<Message> {
+        (**self).fetch(channel, message)
+    }
+
+    fn upload_emoji(&self, guild: GuildId, name: &str, image: &str) -> serenity::Result<Emoji> {
+        (**self).upload_emoji(guild, name, image)
+    }
+
+    fn remove_emoji(&self, guild: GuildId, emoji: EmojiId) -> serenity::Result<()> {
+        (**self).remove_emoji(guild, emoji)
+    }
+
+    fn dm(&self, user: &User, content: &str) -> serenity::Result<()> {
+        (**self).dm(user, content)
+    }
+}
+
+/// Records every call and answers like Discord would, for tests of the pipeline.
+#[cfg(any(test, feature = "mock"))]
+#[cfg_attr(not(test), allow(dead_code))]
+pub mod mock {
+    use super::Discord;
+    use serde_json::json;
+    use serenity::{
+        model::{
+            channel::{Message, ReactionType},
+            guild::Emoji,
+            id::{ChannelId, EmojiId, GuildId, MessageId, UserId},
+            user::User,
+        },
+        Error,
+    };
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Mutex,
+        },
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Call {
+        Post {
+            channel: ChannelId,
+            content: String,
+            files: Vec<String>,
+            reactions: Vec<String>,
+        },
+        Remove(ChannelId, MessageId),
+        Fetch(ChannelId, MessageId),
+        UploadEmoji(GuildId, String),
+        RemoveEmoji(GuildId, EmojiId),
+        Dm(UserId, String),
+    }
+
+    #[derive(Default)]
+    pub struct Mock {
+        pub calls: Mutex<Vec<Call>>,
+        /// the sent messages that are not deleted yet
+        pub messages: Mutex<HashMap<MessageId, Message>>,
+        /// makes the next call fail like a lost connection
+        pub fail: Mutex<bool>,
+        next_id: AtomicU64,
+    }
+
+    impl Mock {
+        pub fn new() -> Self {
+            Mock::default()
+        }
+
+        pub fn calls(&self) -> Vec<Call> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        fn record(&self, call: Call) -> serenity::Result<u64> {
+            self.calls.lock().unwrap().push(call);
+            if std::mem::take(&mut *self.fail.lock().unwrap()) {
+                return Err(Error::Other("mock failure"));
+            }
+            Ok(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+    }
+
+    impl Discord for Mock {
+        fn post(
+            &self,
+            channel: ChannelId,
+            content: &str,
+            files: Vec<(&[u8], &str)>,
+            reactions: Vec<ReactionType>,
+        ) -> serenity::Result<Message> {
+            let id = self.record(Call::Post {
+                channel,
+                content: content.to_string(),
+                files: files.iter().map(|(_, name)| name.to_string()).collect(),
+                reactions: reactions.iter().map(|r| r.as_data()).collect(),
+            })?;
+
+            let attachments = files
+                .iter()
+                .enumerate()
+                .map(|(n, (file, name))| {
+                    json!({
+                        "id": (id * 10 + n as u64).to_string(),
+                        "filename": name,
+                        "size": file.len(),
+                        "url": format!("https://cdn.discordapp.com/attachments/{}/{}/{}", channel, id, name),
+                        "proxy_url": format!("https://media.discordapp.net/attachments/{}/{}/{}", channel, id, name),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let message = serde_json::from_value::<Message>(json!({
+                "id": id.to_string(),
+                "channel_id": channel.to_string(),
+                "author": {"id": "1", "username": "mock", "discriminator": "0000", "bot": true},
+                "content": content,
+                "attachments": attachments,
+                "embeds": [],
+                "type": 0,
+                "mention_everyone": false,
+                "mention_roles": [],
+                "mentions": [],
+                "pinned": false,
+                "timestamp": "2020-01-01T00:00:00+00:00",
+                "tts": false,
+            }))?;
+            self.messages
+                .lock()
+                .unwrap()
+                .insert(message.id, message.clone());
+            Ok(message)
+        }
+
+        fn remove(&self, channel: ChannelId, message: MessageId) -> serenity::Result<()> {
+            self.record(Call::Remove(channel, message))?;
+            self.messages.lock().unwrap().remove(&message);
+            Ok(())
+        }
+
+        fn fetch(&self, channel: ChannelId, message: MessageId) -> serenity::Result<Message> {
+            self.record(Call::Fetch(channel, message))?;
+            self.messages
+                .lock()
+                .unwrap()
+                .get(&message)
+                .cloned()
+                .ok_or(Error::Other("unknown message"))
+        }
+
+        fn upload_emoji(&self, guild: GuildId, name: &str, image: &str) -> serenity::Result<Emoji> {
+            let id = self.record(Call::UploadEmoji(guild, name.to_string()))?;
+            Ok(serde_json::from_value(json!({
+                "id": id.to_string(),
+                "name": name,
+                "animated": image.starts_with("data:image/gif"),
+                "managed": false,
+                "require_colons": true,
+                "roles": [],
+            }))?)
+        }
+
+        fn remove_emoji(&self, guild: GuildId, emoji: EmojiId) -> serenity::Result<()> {
+            self.record(Call::RemoveEmoji(guild, emoji))?;
+            Ok(())
+        }
+
+        fn dm(&self, user: &User, content: &str) -> serenity::Result<()> {
+            self.record(Call::Dm(user.id, content.to_string()))?;
+            Ok(())
+        }
+    }
+}
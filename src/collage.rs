@@ -0,0 +1,268 @@
+//! Results graphic of a closed round, a grid of the suggestions with name and score below each,
+//! and the leaderboard card of `export card` with a bar for every score. Text is drawn with a
+//! built-in 5x7 pixel font, names show up in capitals.
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+
+/// Size the suggestions are shown at.
+const IMAGE: u32 = 128;
+const PADDING: u32 = 8;
+/// Every font pixel becomes a square of this size.
+const SCALE: u32 = 2;
+const LINE: u32 = 7 * SCALE + 4;
+const CELL_WIDTH: u32 = IMAGE + 2 * PADDING;
+const CELL_HEIGHT: u32 = IMAGE + 2 * LINE + 2 * PADDING;
+/// Characters that fit below a suggestion.
+const CHARS: usize = (CELL_WIDTH / (6 * SCALE)) as usize;
+const MAX_COLUMNS: usize = 5;
+/// Dark theme chat background (#36393f).
+const BACKGROUND: Rgba<u8> = Rgba([54, 57, 63, 255]);
+const TEXT: Rgba<u8> = Rgba([255, 255, 255, 255]);
+/// Blurple (#5865f2) of the score bars, and the darker square of entries without an image.
+const BAR: Rgba<u8> = Rgba([88, 101, 242, 255]);
+const EMPTY: Rgba<u8> = Rgba([47, 49, 54, 255]);
+
+const CARD_WIDTH: u32 = 720;
+const THUMBNAIL: u32 = 64;
+const CARD_ROW: u32 = THUMBNAIL + PADDING;
+/// Left edge of the names and bars, after the rank and the thumbnail.
+const CARD_TEXT: u32 = PADDING + 4 * 6 * SCALE + THUMBNAIL + PADDING;
+/// Room right of the longest bar for the votes.
+const CARD_VOTES: u32 = 12 * 6 * SCALE;
+const TITLE_SCALE: u32 = 3;
+
+pub struct Entry {
+    pub image: DynamicImage,
+    pub name: String,
+    pub score: String,
+}
+
+/// Rows of a character from top to bottom, the lowest 5 bits are the pixels from left to right.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        ' ' => [0x00; 7],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// `text` cut to `max` characters, the last one becomes a dot.
+fn cut(text: &str, max: usize) -> Vec<char> {
+    let mut chars = text.chars().collect::<Vec<_>>();
+    if chars.len() > max {
+        chars.truncate(max.saturating_sub(1));
+        chars.push('.');
+    }
+    chars
+}
+
+/// Draws `chars` from `(left, y)` on, every font pixel `scale` pixels big. What doesn't fit on
+/// the canvas is left out.
+fn draw_chars(canvas: &mut RgbaImage, left: u32, y: u32, chars: &[char], scale: u32) {
+    for (n, c) in chars.iter().enumerate() {
+        let left = left + n as u32 * 6 * scale;
+        for (row, bits) in glyph(*c).iter().enumerate() {
+            for column in 0..5 {
+                if bits & (0x10 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (px, py) = (left + column * scale + dx, y + row as u32 * scale + dy);
+                        if px < canvas.width() && py < canvas.height() {
+                            canvas.put_pixel(px, py, TEXT);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draws `text` centered in the cell row starting at `(x, y)`, cut to `CHARS` characters.
+fn draw_text(canvas: &mut RgbaImage, x: u32, y: u32, text: &str) {
+    let chars = cut(text, CHARS);
+    let width = (chars.len() as u32 * 6 * SCALE).saturating_sub(SCALE);
+    draw_chars(
+        canvas,
+        x + CELL_WIDTH.saturating_sub(width) / 2,
+        y,
+        &chars,
+        SCALE,
+    );
+}
+
+/// Grid of `entries` in their order, at most `MAX_COLUMNS` wide.
+pub fn render(entries: &[Entry]) -> RgbaImage {
+    let columns = (1..=MAX_COLUMNS)
+        .find(|c| c * c >= entries.len())
+        .unwrap_or(MAX_COLUMNS);
+    let rows = entries.len().div_ceil(columns);
+    let mut canvas = RgbaImage::from_pixel(
+        columns as u32 * CELL_WIDTH,
+        rows.max(1) as u32 * CELL_HEIGHT,
+        BACKGROUND,
+    );
+
+    for (n, entry) in entries.iter().enumerate() {
+        let x = (n % columns) as u32 * CELL_WIDTH;
+        let y = (n / columns) as u32 * CELL_HEIGHT;
+
+        // smaller sides get centered in the square
+        let image = entry.image.thumbnail(IMAGE, IMAGE).to_rgba8();
+        let (width, height) = image.dimensions();
+        imageops::overlay(
+            &mut canvas,
+            &image,
+            x + PADDING + (IMAGE - width) / 2,
+            y + PADDING + (IMAGE - height) / 2,
+        );
+
+        let text = y + PADDING + IMAGE + 4;
+        draw_text(&mut canvas, x, text, &entry.name);
+        draw_text(&mut canvas, x, text + LINE, &entry.score);
+    }
+
+    canvas
+}
+
+pub struct Standing {
+    /// `None` for sounds and files that can't be fetched
+    pub image: Option<DynamicImage>,
+    pub name: String,
+    pub author: String,
+    pub votes: (u64, u64),
+}
+
+/// Leaderboard card of `standings` in their order under `title` and `subtitle`, every bar as
+/// long as the score is against the best one. Negative scores get no bar.
+pub fn card(title: &str, subtitle: &str, standings: &[Standing]) -> RgbaImage {
+    let header = PADDING + 7 * TITLE_SCALE + 4 + LINE + PADDING;
+    let mut canvas = RgbaImage::from_pixel(
+        CARD_WIDTH,
+        header + standings.len() as u32 * CARD_ROW + PADDING,
+        BACKGROUND,
+    );
+    let width_in = |left: u32, scale: u32| ((CARD_WIDTH - left - PADDING) / (6 * scale)) as usize;
+    draw_chars(
+        &mut canvas,
+        PADDING,
+        PADDING,
+        &cut(title, width_in(PADDING, TITLE_SCALE)),
+        TITLE_SCALE,
+    );
+    draw_chars(
+        &mut canvas,
+        PADDING,
+        PADDING + 7 * TITLE_SCALE + 4,
+        &cut(subtitle, width_in(PADDING, SCALE)),
+        SCALE,
+    );
+
+    let score = |s: &Standing| s.votes.0 as i64 - s.votes.1 as i64;
+    let best = standings.iter().map(score).max().unwrap_or(0).max(1);
+    let longest = CARD_WIDTH - CARD_TEXT - PADDING - CARD_VOTES;
+    for (n, standing) in standings.iter().enumerate() {
+        let y = header + n as u32 * CARD_ROW;
+        let rank = format!("{}.", n + 1).chars().collect::<Vec<_>>();
+        draw_chars(
+            &mut canvas,
+            PADDING,
+            y + (THUMBNAIL - 7 * SCALE) / 2,
+            &rank,
+            SCALE,
+        );
+
+        let left = PADDING + 4 * 6 * SCALE;
+        match &standing.image {
+            // smaller sides get centered in the square
+            Some(image) => {
+                let image = image.thumbnail(THUMBNAIL, THUMBNAIL).to_rgba8();
+                let (width, height) = image.dimensions();
+                imageops::overlay(
+                    &mut canvas,
+                    &image,
+                    left + (THUMBNAIL - width) / 2,
+                    y + (THUMBNAIL - height) / 2,
+                );
+            }
+            None => {
+                for dy in 0..THUMBNAIL {
+                    for dx in 0..THUMBNAIL {
+                        canvas.put_pixel(left + dx, y + dy, EMPTY);
+                    }
+                }
+            }
+        }
+
+        let name = format!("{} - {}", standing.name, standing.author);
+        draw_chars(
+            &mut canvas,
+            CARD_TEXT,
+            y + 4,
+            &cut(&name, width_in(CARD_TEXT, SCALE)),
+            SCALE,
+        );
+        let bar_y = y + 4 + LINE;
+        let bar = (score(standing).max(0) as u64 * longest as u64 / best as u64) as u32;
+        for dy in 0..7 * SCALE {
+            for dx in 0..bar {
+                canvas.put_pixel(CARD_TEXT + dx, bar_y + dy, BAR);
+            }
+        }
+        let votes = format!("+{} -{}", standing.votes.0, standing.votes.1);
+        draw_chars(
+            &mut canvas,
+            CARD_TEXT + bar + PADDING,
+            bar_y,
+            &votes.chars().collect::<Vec<_>>(),
+            SCALE,
+        );
+    }
+
+    canvas
+}
@@ -0,0 +1,189 @@
+//! Size optimization for the processed PNGs, keeps the base64 payload for Discord small.
+//! Everything encoded here carries no metadata, only the pixels are written.
+use color_quant::NeuQuant;
+use image::RgbaImage;
+use png::{BitDepth, ColorType, Compression, Encoder, EncodingError, FilterType};
+
+const FILTERS: [FilterType; 5] = [
+    FilterType::NoFilter,
+    FilterType::Sub,
+    FilterType::Up,
+    FilterType::Avg,
+    FilterType::Paeth,
+];
+
+/// Ancillary chunks that are needed to display the image, everything else (eXIf, tEXt, iTXt,
+/// zTXt, tIME, ...) is dropped. The APNG chunks are kept so animations survive.
+const KEEP_CHUNKS: [&[u8; 4]; 9] = [
+    b"tRNS", b"gAMA", b"cHRM", b"sRGB", b"iCCP", b"sBIT", b"acTL", b"fcTL", b"fdAT",
+];
+
+/// Removes EXIF, XMP and text chunks from an already encoded PNG without re-encoding it,
+/// `None` if the data isn't a well-formed PNG.
+pub fn strip_metadata(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !data.starts_with(SIGNATURE) {
+        return None;
+    }
+
+    let mut out = SIGNATURE.to_vec();
+    let mut pos = SIGNATURE.len();
+    while pos < data.len() {
+        let header = data.get(pos..pos + 8)?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk = data.get(pos..pos + 12 + len)?;
+        let kind = &header[4..8];
+
+        // uppercase first letter means critical (IHDR, PLTE, IDAT, IEND)
+        if kind[0].is_ascii_uppercase() || KEEP_CHUNKS.iter().any(|k| &k[..] == kind) {
+            out.extend_from_slice(chunk);
+        }
+
+        pos += 12 + len;
+    }
+
+    Some(out)
+}
+
+/// Encodes `img` losslessly with every filter and keeps the smallest result. A version quantized
+/// to a palette is only tried below a `quality` of 100, lower values mean fewer colors, or when
+/// the lossless file is above `max_bytes`, then with fewer colors until it fits.
+pub fn optimize(img: &RgbaImage, quality: u8, max_bytes: usize) -> Result<Vec<u8>, EncodingError> {
+    let (width, height) = img.dimensions();
+    let mut smallest = smallest_encoding(width, height, ColorType::RGBA, None, img.as_raw())?;
+    if quality >= 100 && smallest.len() <= max_bytes {
+        return Ok(smallest);
+    }
+
+    let mut quality = quality.min(90);
+    loop {
+        let indexed = quantized(img, quality)?;
+
+        // smooth images often compress better without the dithering noise of a palette
+        if indexed.len() < smallest.len() {
+            smallest = indexed;
+        }
+        if smallest.len() <= max_bytes || quality <= 10 {
+            return Ok(smallest);
+        }
+        quality = quality.saturating_sub(20).max(10);
+    }
+}
+
+fn quantized(img: &RgbaImage, quality: u8) -> Result<Vec<u8>, EncodingError> {
+    let (width, height) = img.dimensions();
+    let colors = (quality.max(1) as usize * 256 / 100).max(16);
+    let quant = NeuQuant::new(10, colors, img.as_raw());
+    let data: Vec<u8> = img
+        .as_raw()
+        .chunks(4)
+        .map(|p| quant.index_of(p) as u8)
+        .collect();
+
+    let palette = quant.color_map_rgba();
+    smallest_encoding(width, height, ColorType::Indexed, Some(&palette), &data)
+}
+
+fn smallest_encoding(
+    width: u32,
+    height: u32,
+    color: ColorType,
+    palette: Option<&[u8]>,
+    data: &[u8],
+) -> Result<Vec<u8>, EncodingError> {
+    let mut smallest: Option<Vec<u8>> = None;
+    for filter in FILTERS.iter() {
+        let mut buf = Vec::new();
+        {
+            let mut encoder = Encoder::new(&mut buf, width, height);
+            encoder.set_color(color);
+            encoder.set_depth(BitDepth::Eight);
+            encoder.set_compression(Compression::Best);
+            encoder.set_filter(*filter);
+
+            if let Some(palette) = palette {
+                encoder.set_palette(palette.chunks(4).flat_map(|c| c[..3].to_vec()).collect());
+                encoder.set_trns(palette.chunks(4).map(|c| c[3]).collect());
+            }
+
+            encoder.write_header()?.write_image_data(data)?;
+        }
+
+        if smallest.as_ref().is_none_or(|s| buf.len() < s.len()) {
+            smallest = Some(buf);
+        }
+    }
+
+    Ok(smallest.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    /// Smooth gradients, many colors and poor to compress.
+    fn gradient(size: u32) -> RgbaImage {
+        RgbaImage::from_fn(size, size, |x, y| {
+            Rgba([
+                (x * 255 / size) as u8,
+                (y * 255 / size) as u8,
+                ((x + y) % 256) as u8,
+                255,
+            ])
+        })
+    }
+
+    #[test]
+    fn lossless_by_default() {
+        let img = gradient(64);
+        let png = optimize(&img, 100, usize::MAX).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded, img);
+    }
+
+    #[test]
+    fn palette_to_fit() {
+        // noise, a palette is the only way to make it smaller
+        let img = RgbaImage::from_fn(128, 128, |x, y| {
+            let mut n = x * 128 + y + 1;
+            for _ in 0..3 {
+                n ^= n << 13;
+                n ^= n >> 17;
+                n ^= n << 5;
+            }
+            Rgba([(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, 255])
+        });
+        let lossless = optimize(&img, 100, usize::MAX).unwrap();
+        let small = optimize(&img, 100, lossless.len() / 2).unwrap();
+        assert!(small.len() < lossless.len());
+
+        let decoded = image::load_from_memory(&small).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (128, 128));
+    }
+
+    #[test]
+    fn transparency_survives_the_palette() {
+        let img = RgbaImage::from_fn(32, 32, |x, _| match x < 16 {
+            true => Rgba([0, 0, 0, 0]),
+            false => Rgba([200, 100, 50, 255]),
+        });
+        let png = optimize(&img, 50, usize::MAX).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(0, 0)[3], 0);
+        assert_eq!(decoded.get_pixel(31, 31)[3], 255);
+    }
+
+    #[test]
+    fn strips_text_chunks() {
+        let png = optimize(&gradient(16), 100, usize::MAX).unwrap();
+        // a tEXt chunk after the header: length, type, data, crc
+        let mut with_text = png[..33].to_vec();
+        with_text.extend_from_slice(&5u32.to_be_bytes());
+        with_text.extend_from_slice(b"tEXta\0bcd");
+        with_text.extend_from_slice(&[0; 4]);
+        with_text.extend_from_slice(&png[33..]);
+
+        assert_eq!(strip_metadata(&with_text), Some(png));
+    }
+}
@@ -0,0 +1,390 @@
+//! Settings that can be changed without recompiling, read from `config.json` or the file in
+//! `EMOTE_CONFIG`. Missing fields fall back to their defaults.
+use crate::{
+    cron::Cron,
+    nsfw::Nsfw,
+    permissions::Grant,
+    resize::{Aspect, Filter},
+    script::Veto,
+    telegram::Telegram,
+    templates::Announcement,
+    webhooks::Webhook,
+};
+use chrono_tz::Tz;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, GuildId, RoleId};
+use std::{collections::HashMap, env, fs, str::FromStr, sync::RwLock};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// 100 keeps processed PNGs lossless unless they are over the size limit, lower values
+    /// quantize them to fewer colors.
+    pub png_quality: u8,
+    /// Cut off transparent or solid borders before resizing.
+    pub auto_trim: bool,
+    /// Filter used for downscaling, submissions can pick their own with `filter:`.
+    pub resize_filter: Filter,
+    /// Directory of a copy of everything the bot prints, see `logfile`. `None` only prints.
+    pub log_dir: Option<String>,
+    /// Size at which the log file gets rotated.
+    pub log_max_bytes: u64,
+    /// Rotated log files that are kept.
+    pub log_keep: usize,
+    /// Retries of a Discord call that failed without an answer or with a server error, see `rest`.
+    pub rest_retries: u32,
+    /// Timeout of every request to Discord, retries only start within it after the first try.
+    pub rest_timeout_ms: u64,
+    /// Milliseconds between two messages of the outbox, see `outbox`.
+    pub outbox_interval_ms: u64,
+    /// Every `close`, `remove`, `remove-many`, `remove-author` and `revote` only reports what it
+    /// would do, like with `--dry-run`.
+    pub dry_run: bool,
+    /// Roles that may run every mod command, empty leaves them to `permissions` and the grants.
+    /// Unset it's the roles named Moderator and admin, like before there was a setting.
+    pub mod_roles: Option<Vec<RoleId>>,
+    /// Named groups of roles for `permissions`, like `{"helpers": [...], "admins": [...]}`.
+    pub role_sets: HashMap<String, Vec<RoleId>>,
+    /// Role sets that may run a mod command (by its first name) or `approve` in the approval
+    /// queue instead of the `mod_roles`, like `{"remove": ["admins"]}`.
+    pub permissions: HashMap<String, Vec<String>>,
+    /// Channel for notes to the mods, like the `alert_score` and `alert_ratio` alerts.
+    pub mod_channel: Option<ChannelId>,
+    /// Private channel that gets every removal, approval, rejection and closed round with the mod
+    /// who did it.
+    pub modlog_channel: Option<ChannelId>,
+    /// Net 👍 after which the `mod_channel` hears about a suggestion, once.
+    pub alert_score: Option<i64>,
+    /// 👎 per 👍 after which the `mod_channel` hears about a suggestion, once.
+    pub alert_ratio: Option<f64>,
+    /// DM authors when their suggestions reach one of the milestones below, they can turn it off
+    /// with `dms off`.
+    pub milestone_dms: bool,
+    /// 👍 of the first milestone.
+    pub milestone_upvotes: u64,
+    /// 👍 and 👎 together of the quorum milestone, `None` has none.
+    pub milestone_quorum: Option<u64>,
+    /// Places on the leaderboard of the last milestone.
+    pub milestone_top: usize,
+    /// Every new suggestion waits in the `mod_channel` for a ✅ before it goes into the voting.
+    pub approval_queue: bool,
+    /// Classifier that sends flagged images to the approval queue, even without `approval_queue`.
+    pub nsfw: Option<Nsfw>,
+    /// Distinct 🚩 that hide a suggestion behind a spoiler and ping the mods, `None` seeds no 🚩.
+    pub report_threshold: Option<usize>,
+    /// Days a removed suggestion can't be suggested again, matched by name or file.
+    pub resubmit_cooldown_days: i64,
+    /// Every new name has to start with this, see `validate`.
+    pub name_prefix: Option<String>,
+    /// RGB colors new images can't have as background, with some tolerance.
+    pub banned_backgrounds: Vec<[u8; 3]>,
+    /// Days an account has to exist before it can suggest something.
+    pub min_account_age_days: Option<i64>,
+    /// Suggestions a user can post per round, see `User::counter`.
+    pub max_suggestions: u64,
+    /// Static emote suggestions that can be in the voting at once.
+    pub static_quota: Option<usize>,
+    /// Animated emote suggestions that can be in the voting at once.
+    pub animated_quota: Option<usize>,
+    /// Emote slots that only keep the winners of the last closed round, they get uploaded right
+    /// away and replace the ones before them. `None` keeps every winner.
+    pub rotation_slots: Option<usize>,
+    /// A winner without a free emote slot starts a poll on which of the least used emotes makes
+    /// room for it.
+    pub replacement_polls: bool,
+    /// Weeks after which an uploaded emote faces a vote on whether it stays, see `Trial`.
+    pub trial_weeks: Option<i64>,
+    /// Rules of `script` that refuse new suggestions, see `validate`.
+    pub veto_rules: Vec<Veto>,
+    /// Expression of `script` that ranks the suggestions instead of 👍 minus 👎.
+    pub score_script: Option<String>,
+    /// Days a suggestion is voted on, counted from its posting or the last revote.
+    pub voting_days: i64,
+    /// Decide every suggestion on its own once its `voting_days` are over, without `close`.
+    pub auto_expire: bool,
+    /// Weighted 👍 minus 👎 an expiring suggestion needs to get uploaded, it's rejected otherwise.
+    pub expire_min_score: f64,
+    /// Closed and accepted suggestions get reposted here with their score, `None` just deletes
+    /// them.
+    pub archive_channel: Option<ChannelId>,
+    /// The winners of every closed round get posted and the summary pinned here.
+    pub hall_of_fame_channel: Option<ChannelId>,
+    /// Uploaded suggestions get posted here, and published when it's an announcement channel.
+    pub news_channel: Option<ChannelId>,
+    /// Role for the authors of winning suggestions, given out when a round is closed.
+    pub winner_role: Option<RoleId>,
+    /// Take the winner role away again when the next round is closed.
+    pub winner_role_until_next_round: bool,
+    /// Only votes of members with one of these roles count when a round is closed, empty allows
+    /// everyone still on the server.
+    pub voter_roles: Vec<RoleId>,
+    /// Votes lose half their weight every this many days when a round is closed, `None` counts
+    /// every vote the same.
+    pub vote_half_life_days: Option<f64>,
+    /// Weight old votes never drop below.
+    pub min_vote_weight: f64,
+    /// Categories submitters can pick with `category:`, empty allows any.
+    pub categories: Vec<String>,
+    /// Voting channel of a category, the others are voted on in the main channel.
+    pub category_channels: HashMap<String, ChannelId>,
+    /// Suggestions a member can upvote per round, over it the 👍 gets taken back. `None` allows
+    /// any number.
+    pub vote_budget: Option<usize>,
+    /// Minutes between two pairwise comparisons of random suggestions, they feed the Elo
+    /// ranking. `None` posts none.
+    pub pairwise_minutes: Option<u64>,
+    /// Days of nomination after a closed round, the voting of the next one starts after them.
+    /// `None` goes straight to voting.
+    pub nomination_days: Option<i64>,
+    /// Starts a round at these times in the guild's time zone, with the nomination of
+    /// `nomination_days` if there is one.
+    pub round_cron: Option<Cron>,
+    /// Days the voting of a round of `round_cron` lasts before it closes by itself.
+    pub round_voting_days: i64,
+    /// Keep a pinned message in the voting channel with the time left in the round.
+    pub countdown: bool,
+    /// Keep a pinned top 10 in the voting channel, edited at most once a minute.
+    pub leaderboard: bool,
+    /// Post a grid of the winners with their votes along with the results of a closed round.
+    pub results_collage: bool,
+    /// Show every suggestion of the round in the grid, ordered by score.
+    pub collage_rankings: bool,
+    /// Services that get told about new suggestions, closed rounds and winners.
+    pub webhooks: Vec<Webhook>,
+    /// Channel that gets new suggestions and round results too.
+    pub telegram: Option<Telegram>,
+    /// Own texts for announcements, DMs and result posts by their key, see `templates`.
+    pub templates: HashMap<String, String>,
+    /// Own round announcements by their key, with an optional embed, see `templates`.
+    pub announcements: HashMap<String, Announcement>,
+    /// Address of the HTTP server with the RSS feed of accepted suggestions, e.g.
+    /// `0.0.0.0:8080`. `None` starts no server.
+    pub http_address: Option<String>,
+    /// Suggestions that can win a round at most, the best scores (👍 minus 👎) get the slots.
+    pub max_winners: Option<usize>,
+    /// Scores within this much of each other fight for the last slot in a runoff.
+    pub runoff_epsilon: f64,
+    /// Threads that decode, resize and compress images.
+    pub processing_threads: usize,
+    /// Images that can wait for or be in processing, more get refused until it's quieter.
+    pub processing_queue: usize,
+    /// Seconds a submission waits for its processing before it's given up.
+    pub processing_timeout_secs: u64,
+    /// Submitted attachments that are downloaded at the same time.
+    pub max_downloads: usize,
+    /// Bytes all running downloads may add up to.
+    pub download_budget_bytes: u64,
+    /// Retries of a download that broke off, each one continues where the last one stopped.
+    pub download_retries: u32,
+    /// Largest attachment that is downloaded at all.
+    pub max_image_bytes: u64,
+    /// Smallest width and height of an emote submission, they get scaled down to 128px.
+    pub min_image_size: u64,
+    /// Factor smaller submissions may be scaled up by to reach the minimum size, 1 doesn't scale
+    /// up at all.
+    pub max_upscale: f64,
+    /// Largest width and height of a submission.
+    pub max_image_size: u64,
+    /// How many times longer one side of a submission may be than the other.
+    pub max_aspect_ratio: f64,
+    /// Which half of the bot this process runs, see `Mode`.
+    pub mode: Mode,
+    /// Where suggestions, quotas and the history are kept between restarts.
+    pub store: Backend,
+    /// Server for the `redis` store.
+    pub redis_url: String,
+    /// Days Redis keeps a pending suggestion after its deadline, in case the round is never
+    /// closed.
+    pub redis_expire_days: i64,
+    /// Database of the `postgres` store, a URL or `key=value` pairs.
+    pub postgres_url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// nothing survives a restart
+    Memory,
+    /// JSON files in `data/`, see `filestore`
+    Files,
+    Redis,
+    /// tables in `postgres_url`, see `pgstore`
+    Postgres,
+    /// embedded database in `store.sled/`, see `sledstore`
+    Sled,
+    /// the database file `store.db`, see `sqlitestore`
+    Sqlite,
+}
+
+/// The gateway answers commands and votes, the worker processes submissions. Split up they talk
+/// through the job files, so a long encode can't stall the gateway connection.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    All,
+    Gateway,
+    Worker,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            png_quality: 100,
+            auto_trim: true,
+            resize_filter: Filter::Thumbnail,
+            log_dir: None,
+            log_max_bytes: 10 * 1024 * 1024,
+            log_keep: 7,
+            rest_retries: 2,
+            rest_timeout_ms: 10000,
+            outbox_interval_ms: 1000,
+            dry_run: false,
+            mod_roles: None,
+            role_sets: HashMap::new(),
+            permissions: HashMap::new(),
+            mod_channel: None,
+            modlog_channel: None,
+            alert_score: None,
+            alert_ratio: None,
+            milestone_dms: false,
+            milestone_upvotes: 10,
+            milestone_quorum: None,
+            milestone_top: 5,
+            approval_queue: false,
+            nsfw: None,
+            report_threshold: None,
+            resubmit_cooldown_days: 30,
+            name_prefix: None,
+            banned_backgrounds: Vec::new(),
+            min_account_age_days: None,
+            max_suggestions: 3,
+            static_quota: None,
+            animated_quota: None,
+            rotation_slots: None,
+            replacement_polls: false,
+            trial_weeks: None,
+            veto_rules: Vec::new(),
+            score_script: None,
+            voting_days: 7,
+            auto_expire: false,
+            expire_min_score: 0.0,
+            archive_channel: None,
+            hall_of_fame_channel: None,
+            news_channel: None,
+            winner_role: None,
+            winner_role_until_next_round: true,
+            voter_roles: Vec::new(),
+            vote_half_life_days: None,
+            min_vote_weight: 0.5,
+            categories: Vec::new(),
+            category_channels: HashMap::new(),
+            vote_budget: None,
+            pairwise_minutes: None,
+            nomination_days: None,
+            round_cron: None,
+            round_voting_days: 7,
+            countdown: false,
+            leaderboard: false,
+            results_collage: false,
+            collage_rankings: false,
+            webhooks: Vec::new(),
+            telegram: None,
+            templates: HashMap::new(),
+            announcements: HashMap::new(),
+            http_address: None,
+            max_winners: None,
+            runoff_epsilon: 1.0,
+            processing_threads: 2,
+            processing_queue: 8,
+            processing_timeout_secs: 60,
+            max_downloads: 4,
+            download_budget_bytes: 50_000_000,
+            download_retries: 3,
+            max_image_bytes: 6_000_000,
+            min_image_size: 120,
+            max_upscale: 1.0,
+            max_image_size: 4096,
+            max_aspect_ratio: 3.0,
+            mode: Mode::All,
+            store: Backend::Sled,
+            redis_url: String::from("redis://127.0.0.1:6379"),
+            redis_expire_days: 30,
+            postgres_url: String::from("postgres://postgres@localhost/touhyou"),
+        }
+    }
+}
+
+impl Config {
+    fn load() -> Config {
+        let path = env::var("EMOTE_CONFIG").unwrap_or_else(|_| String::from("config.json"));
+
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .unwrap_or_else(|why| panic!("Invalid config {}: {}", path, why)),
+            Err(_) => {
+                log!("No config found at {}, using defaults", path);
+                Config::default()
+            }
+        }
+    }
+}
+
+/// Settings of a single guild, changed at runtime with `>>config` and kept in the store.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GuildConfig {
+    pub prefix: String,
+    /// what `add` does with non-square images without `mode:`
+    pub aspect: Aspect,
+    /// deadlines get shown and entered in this time, with its daylight saving time
+    pub timezone: Tz,
+    /// a partner guild that gets the emote winners of the voting guild too, see `federate`
+    pub federate: bool,
+    pub collisions: Collision,
+    /// mod commands granted to more roles or users with `perm`, by the first name of the command
+    pub permissions: HashMap<String, Grant>,
+}
+
+/// What `federate` does with a winner whose name a partner guild already has.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Collision {
+    /// upload it under the first free variation of the name
+    #[default]
+    Rename,
+    Skip,
+}
+
+impl FromStr for Collision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &*s.to_lowercase() {
+            "rename" => Ok(Collision::Rename),
+            "skip" => Ok(Collision::Skip),
+            _ => Err(format!(
+                "Unknown collision handling {}, use rename or skip.",
+                s
+            )),
+        }
+    }
+}
+
+impl Default for GuildConfig {
+    fn default() -> Self {
+        GuildConfig {
+            prefix: String::from(">>"),
+            aspect: Aspect::Stretch,
+            timezone: Tz::UTC,
+            federate: false,
+            collisions: Collision::Rename,
+            permissions: HashMap::new(),
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref CONFIG: RwLock<Config> = RwLock::new(Config::load());
+    pub static ref GUILDS: RwLock<HashMap<GuildId, GuildConfig>> = RwLock::new(HashMap::new());
+}
@@ -0,0 +1,348 @@
+//! Files the image decoder can't read, turned into a PNG (or a GIF when they are animated)
+//! before they go into the pipeline: SVG, AVIF and the WebPs that aren't simple lossy ones.
+use gif::{Encoder, Repeat};
+use image::{ImageFormat, RgbaImage};
+use image_webp::WebPDecoder;
+use lazy_static::lazy_static;
+use rav1d::{
+    include::dav1d::{
+        data::Dav1dData,
+        dav1d::Dav1dSettings,
+        headers::{
+            DAV1D_MC_BT2020_CL, DAV1D_MC_BT2020_NCL, DAV1D_MC_BT709, DAV1D_MC_IDENTITY,
+            DAV1D_PIXEL_LAYOUT_I400, DAV1D_PIXEL_LAYOUT_I422, DAV1D_PIXEL_LAYOUT_I444,
+        },
+        picture::Dav1dPicture,
+    },
+    src::lib::{
+        dav1d_close, dav1d_data_create, dav1d_data_unref, dav1d_default_settings,
+        dav1d_get_picture, dav1d_open, dav1d_picture_unref, dav1d_send_data,
+    },
+    Dav1dResult,
+};
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{fontdb::Database, ImageHrefResolver, Options, Tree},
+};
+use std::{io::Cursor, mem::MaybeUninit, ptr, ptr::NonNull, sync::Arc};
+
+/// Larger AV1 frames aren't decoded at all, nothing near it would pass the size limits.
+const MAX_PIXELS: u32 = 4096 * 4096;
+
+lazy_static! {
+    // loading the system fonts takes a while, so only once
+    static ref FONTS: Arc<Database> = {
+        let mut fonts = Database::new();
+        fonts.load_system_fonts();
+        Arc::new(fonts)
+    };
+}
+
+/// Rasterizes an SVG so its shorter side is `size` pixels, the square cut or fitted from it
+/// needs no upscaling then. The longer side stays within `max`.
+pub fn svg(data: &[u8], size: u32, max: u32) -> Result<Vec<u8>, String> {
+    let options = Options {
+        fontdb: FONTS.clone(),
+        // `<image>` may only embed data, a path would read files from the bot's disk
+        image_href_resolver: ImageHrefResolver {
+            resolve_string: Box::new(|_, _| None),
+            ..ImageHrefResolver::default()
+        },
+        ..Options::default()
+    };
+    let tree = Tree::from_data(data, &options).map_err(|why| why.to_string())?;
+
+    let (width, height) = (tree.size().width(), tree.size().height());
+    let scale = (size as f32 / width.min(height)).min(max as f32 / width.max(height));
+    let mut pixmap = Pixmap::new(
+        ((width * scale).round() as u32).max(1),
+        ((height * scale).round() as u32).max(1),
+    )
+    .ok_or("SVG has no size")?;
+
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.encode_png().map_err(|why| why.to_string())
+}
+
+/// Whether `data` is an AVIF or a WebP the image decoder can't read (lossless, transparent or
+/// animated), those go through `decode` first.
+pub fn needed(data: &[u8]) -> bool {
+    if data.get(4..11) == Some(&b"ftypavi"[..]) {
+        return true;
+    }
+
+    if data.len() < 21 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return false;
+    }
+
+    // VP8X flags: 0x02 animation, 0x10 alpha
+    match &data[12..16] {
+        b"VP8L" => true,
+        b"VP8X" => data[20] & 0x12 != 0,
+        _ => false,
+    }
+}
+
+/// Turns an AVIF or WebP into a PNG, animated WebPs into a GIF.
+pub fn decode(data: &[u8]) -> Result<(Vec<u8>, ImageFormat), String> {
+    let img = match data.get(4..11) == Some(&b"ftypavi"[..]) {
+        true => avif(data)?,
+        false => {
+            let mut decoder = WebPDecoder::new(Cursor::new(data)).map_err(|why| why.to_string())?;
+            if decoder.is_animated() {
+                return Ok((animated_webp(&mut decoder)?, ImageFormat::Gif));
+            }
+            webp(&mut decoder)?
+        }
+    };
+
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgba8(img)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .map_err(|why| why.to_string())?;
+    Ok((png, ImageFormat::Png))
+}
+
+fn webp(decoder: &mut WebPDecoder<Cursor<&[u8]>>) -> Result<RgbaImage, String> {
+    let (width, height) = decoder.dimensions();
+    let mut buf = vec![0; decoder.output_buffer_size().ok_or("WebP is too large")?];
+    decoder
+        .read_image(&mut buf)
+        .map_err(|why| why.to_string())?;
+    rgba(width, height, decoder.has_alpha(), buf)
+}
+
+/// Every frame comes out whole, so the GIF needs no disposal tricks. The palettes are kept at
+/// 256 colors, the pipeline reduces them later if the emote is too large.
+fn animated_webp(decoder: &mut WebPDecoder<Cursor<&[u8]>>) -> Result<Vec<u8>, String> {
+    let (width, height) = decoder.dimensions();
+    let mut buf = vec![0; decoder.output_buffer_size().ok_or("WebP is too large")?];
+
+    let mut gif = Vec::new();
+    {
+        let mut encoder = Encoder::new(&mut gif, width as u16, height as u16, &[])
+            .map_err(|why| why.to_string())?;
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|why| why.to_string())?;
+
+        for _ in 0..decoder.num_frames() {
+            let delay = decoder
+                .read_frame(&mut buf)
+                .map_err(|why| why.to_string())?;
+            let mut image = rgba(width, height, decoder.has_alpha(), buf.clone())?.into_raw();
+            let mut frame = gif::Frame::from_rgba_speed(width as u16, height as u16, &mut image, 3);
+            frame.delay = (delay / 10).clamp(1, u16::MAX as u32) as u16;
+            encoder.write_frame(&frame).map_err(|why| why.to_string())?;
+        }
+    }
+
+    Ok(gif)
+}
+
+fn rgba(width: u32, height: u32, alpha: bool, buf: Vec<u8>) -> Result<RgbaImage, String> {
+    let buf = match alpha {
+        true => buf,
+        false => buf
+            .chunks(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+    };
+    RgbaImage::from_raw(width, height, buf).ok_or_else(|| String::from("WebP has no pixels"))
+}
+
+/// Decodes the primary image of an AVIF, with its alpha if there is one.
+fn avif(data: &[u8]) -> Result<RgbaImage, String> {
+    let avif = avif_parse::read_avif(&mut Cursor::new(data)).map_err(|why| why.to_string())?;
+    let color = av1(&avif.primary_item)?;
+    let alpha = match &avif.alpha_item {
+        Some(item) => Some(av1(item)?),
+        None => None,
+    };
+
+    let (width, height) = color.size();
+    if alpha.as_ref().is_some_and(|a| a.size() != (width, height)) {
+        return Err(String::from("AVIF alpha has a different size"));
+    }
+
+    let mut img = RgbaImage::new(width as u32, height as u32);
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let (x, y) = (x as usize, y as usize);
+        let [r, g, b] = color.rgb(x, y);
+        let a = alpha
+            .as_ref()
+            .map_or(1.0, |a| a.full_range(a.sample(0, x, y)));
+        let [r, g, b] = match avif.premultiplied_alpha && a > 0.0 {
+            true => [r / a, g / a, b / a],
+            false => [r, g, b],
+        };
+        *pixel = image::Rgba([byte(r), byte(g), byte(b), byte(a)]);
+    }
+    Ok(img)
+}
+
+fn byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn result(res: Dav1dResult) -> Result<(), String> {
+    match res.0 {
+        0 => Ok(()),
+        errno => Err(format!("AV1 decoding failed with {}", -errno)),
+    }
+}
+
+/// Decodes a single AV1 frame, rav1d only comes with the C interface of dav1d.
+fn av1(data: &[u8]) -> Result<Picture, String> {
+    // SAFETY: every pointer handed to rav1d points to a live local, the context is closed
+    // before returning and the picture keeps its own reference on the pixels
+    unsafe {
+        let mut settings = MaybeUninit::<Dav1dSettings>::uninit();
+        dav1d_default_settings(NonNull::from(&mut settings).cast());
+        let mut settings = settings.assume_init();
+        settings.n_threads = 1;
+        settings.max_frame_delay = 1;
+        settings.all_layers = 0;
+        settings.frame_size_limit = MAX_PIXELS;
+
+        let mut ctx = None;
+        result(dav1d_open(
+            Some(NonNull::from(&mut ctx)),
+            Some(NonNull::from(&mut settings)),
+        ))?;
+
+        let decoded = (|| {
+            let mut input = Dav1dData::default();
+            let buf = dav1d_data_create(Some(NonNull::from(&mut input)), data.len());
+            if buf.is_null() {
+                return Err(String::from("AV1 data is too large"));
+            }
+            ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len());
+
+            let sent = dav1d_send_data(ctx, Some(NonNull::from(&mut input)));
+            dav1d_data_unref(Some(NonNull::from(&mut input)));
+            result(sent)?;
+
+            let mut picture = Picture(Dav1dPicture::default());
+            result(dav1d_get_picture(ctx, Some(NonNull::from(&mut picture.0))))?;
+            match picture.0.stride[0] > 0 && picture.0.data[0].is_some() {
+                true => Ok(picture),
+                false => Err(String::from("AV1 frame has no pixels")),
+            }
+        })();
+
+        dav1d_close(Some(NonNull::from(&mut ctx)));
+        decoded
+    }
+}
+
+struct Picture(Dav1dPicture);
+
+impl Drop for Picture {
+    fn drop(&mut self) {
+        // SAFETY: the picture was filled by `dav1d_get_picture` and is only released here
+        unsafe { dav1d_picture_unref(Some(NonNull::from(&mut self.0))) };
+    }
+}
+
+impl Picture {
+    fn size(&self) -> (usize, usize) {
+        (self.0.p.w as usize, self.0.p.h as usize)
+    }
+
+    /// Raw value of `plane` (0 is luma) at the luma position `x`, `y`, scaled to 0..1.
+    fn sample(&self, plane: usize, x: usize, y: usize) -> f32 {
+        let (x, y) = match (plane, self.0.p.layout) {
+            (0, _) | (_, DAV1D_PIXEL_LAYOUT_I444) => (x, y),
+            (_, DAV1D_PIXEL_LAYOUT_I422) => (x / 2, y),
+            _ => (x / 2, y / 2),
+        };
+        let stride = self.0.stride[(plane > 0) as usize] as usize;
+        let base = match self.0.data[plane] {
+            Some(base) => base.as_ptr() as *const u8,
+            None => return 0.5,
+        };
+
+        // SAFETY: rav1d allocates `stride` bytes for every row of a plane, `x` and `y` are within
+        // the (subsampled) frame size
+        let value = unsafe {
+            match self.0.p.bpc {
+                8 => *base.add(y * stride + x) as f32,
+                _ => (base.add(y * stride + x * 2) as *const u16).read_unaligned() as f32,
+            }
+        };
+        value / ((1 << self.0.p.bpc) - 1) as f32
+    }
+
+    /// Expands a luma value from the studio range (16-235 at 8 bits) if the frame uses it.
+    fn full_range(&self, value: f32) -> f32 {
+        // SAFETY: the sequence header lives as long as the picture
+        match unsafe { self.0.seq_hdr.map(|h| h.as_ref().color_range) } {
+            Some(0) => (value - 16.0 / 255.0) * 255.0 / 219.0,
+            _ => value,
+        }
+    }
+
+    /// RGB at `x`, `y`, converted with the matrix of the sequence header.
+    fn rgb(&self, x: usize, y: usize) -> [f32; 3] {
+        let luma = self.full_range(self.sample(0, x, y));
+        if self.0.p.layout == DAV1D_PIXEL_LAYOUT_I400 {
+            return [luma; 3];
+        }
+
+        // SAFETY: the sequence header lives as long as the picture
+        let (matrix, full) = unsafe {
+            self.0.seq_hdr.map_or((0, true), |h| {
+                (h.as_ref().mtrx, h.as_ref().color_range != 0)
+            })
+        };
+        let chroma = |plane| match full {
+            true => self.sample(plane, x, y) - 0.5,
+            false => (self.sample(plane, x, y) - 128.0 / 255.0) * 255.0 / 224.0,
+        };
+        let (cb, cr) = (chroma(1), chroma(2));
+
+        // identity stores G, B and R in the three planes
+        if matrix == DAV1D_MC_IDENTITY {
+            return [cr + 0.5, luma, cb + 0.5];
+        }
+
+        let (kr, kb) = match matrix {
+            DAV1D_MC_BT709 => (0.2126, 0.0722),
+            DAV1D_MC_BT2020_NCL | DAV1D_MC_BT2020_CL => (0.2627, 0.0593),
+            // BT.601, also what encoders assume when nothing is set
+            _ => (0.299, 0.114),
+        };
+        let r = luma + 2.0 * (1.0 - kr) * cr;
+        let b = luma + 2.0 * (1.0 - kb) * cb;
+        let g = (luma - kr * r - kb * b) / (1.0 - kr - kb);
+        [r, g, b]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    #[test]
+    fn svg_shorter_side_fits() {
+        let wide = br#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10">
+            <rect width="20" height="10" fill="red"/></svg>"#;
+        let png = svg(wide, 128, 4096).unwrap();
+        assert_eq!(
+            image::load_from_memory(&png).unwrap().dimensions(),
+            (256, 128)
+        );
+
+        let png = svg(wide, 128, 200).unwrap();
+        assert_eq!(
+            image::load_from_memory(&png).unwrap().dimensions(),
+            (200, 100)
+        );
+    }
+}
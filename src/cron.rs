@@ -0,0 +1,200 @@
+//! Cron expressions for recurring rounds: minute, hour, day of month, month and day of week,
+//! each `*`, a number, a range `a-b` or a list of them, optionally with a step like `*/15`.
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cron {
+    source: String,
+    /// allowed values of every field as bits, in the order of the expression
+    fields: [u64; 5],
+    /// with both days restricted either one matching is enough, like in crontab
+    any_day: bool,
+}
+
+/// Lowest and highest value of every field, a 7 in the day of week is another Sunday.
+const RANGES: [(u32, u32); 5] = [(0, 59), (0, 23), (1, 31), (1, 12), (0, 7)];
+
+fn field(part: &str, (low, high): (u32, u32)) -> Result<u64, String> {
+    let mut bits = 0;
+    for item in part.split(',') {
+        let (range, step) = match item.find('/') {
+            Some(n) => (&item[..n], item[n + 1..].parse::<u32>().map_err(|_| item)?),
+            None => (item, 1),
+        };
+        let (from, to) = match range {
+            "*" => (low, high),
+            _ => match range.find('-') {
+                Some(n) => (
+                    range[..n].parse().map_err(|_| item)?,
+                    range[n + 1..].parse().map_err(|_| item)?,
+                ),
+                None => {
+                    let value = range.parse().map_err(|_| item)?;
+                    // `5/10` means from 5 to the end in steps of 10
+                    (value, if item.contains('/') { high } else { value })
+                }
+            },
+        };
+        if step == 0 || from < low || to > high || from > to {
+            return Err(format!("{} is out of {}-{}", item, low, high));
+        }
+        for value in (from..=to).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+impl TryFrom<String> for Cron {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        let parts = source.split_whitespace().collect::<Vec<_>>();
+        if parts.len() != 5 {
+            return Err(format!(
+                "{} needs 5 fields: minute hour day month weekday",
+                source
+            ));
+        }
+
+        let mut fields = [0; 5];
+        for ((bits, part), range) in fields.iter_mut().zip(parts.iter()).zip(RANGES.iter()) {
+            *bits = field(part, *range).map_err(|why| format!("Invalid cron field {}", why))?;
+        }
+        // Sunday is 0 and 7
+        if fields[4] & 1 << 7 != 0 {
+            fields[4] |= 1;
+        }
+
+        Ok(Cron {
+            any_day: !parts[2].starts_with('*') && !parts[4].starts_with('*'),
+            source,
+            fields,
+        })
+    }
+}
+
+impl From<Cron> for String {
+    fn from(cron: Cron) -> Self {
+        cron.source
+    }
+}
+
+impl Cron {
+    /// Whether the minute of `at` is in the schedule.
+    pub fn matches(&self, at: NaiveDateTime) -> bool {
+        let has = |n: usize, value: u32| self.fields[n] & 1 << value != 0;
+        let day = has(2, at.day());
+        let weekday = has(4, at.weekday().num_days_from_sunday());
+
+        has(0, at.minute())
+            && has(1, at.hour())
+            && has(3, at.month())
+            && if self.any_day {
+                day || weekday
+            } else {
+                day && weekday
+            }
+    }
+
+    /// First minute of the schedule after `after` on the clocks of its time zone, `None` if there
+    /// is none within a year. A time skipped by daylight saving time never comes.
+    pub fn next<Tz: TimeZone>(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let start = after.with_second(0)?.with_nanosecond(0)?;
+        (1..=366 * 24 * 60)
+            .map(|minutes| start.clone() + Duration::minutes(minutes))
+            .find(|at| self.matches(at.naive_local()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    fn cron(source: &str) -> Result<Cron, String> {
+        Cron::try_from(source.to_string())
+    }
+
+    #[test]
+    fn parses_fields() {
+        let every = cron("*/15 18 1,15 * 1-5").unwrap();
+        assert_eq!(every.fields[0], 1 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(every.fields[1], 1 << 18);
+        assert_eq!(every.fields[2], 1 << 1 | 1 << 15);
+        assert_eq!(every.fields[4], 0b111110);
+        assert!(every.any_day);
+
+        // from 5 to the end in steps of 20
+        assert_eq!(
+            cron("5/20 * * * *").unwrap().fields[0],
+            1 << 5 | 1 << 25 | 1 << 45
+        );
+        // 7 is Sunday as well
+        assert_eq!(cron("0 0 * * 7").unwrap().fields[4], 1 | 1 << 7);
+        assert_eq!(String::from(cron("0 0 * * 7").unwrap()), "0 0 * * 7");
+    }
+
+    #[test]
+    fn refuses_invalid() {
+        assert!(cron("* * * *").is_err());
+        assert!(cron("60 * * * *").is_err());
+        assert!(cron("* * 0 * *").is_err());
+        assert!(cron("*/0 * * * *").is_err());
+        assert!(cron("5-1 * * * *").is_err());
+        assert!(cron("a * * * *").is_err());
+    }
+
+    #[test]
+    fn next_minute() {
+        let monthly = cron("0 18 1 * *").unwrap();
+        let after = Utc.with_ymd_and_hms(2021, 5, 1, 18, 0, 30).unwrap();
+        assert_eq!(
+            monthly.next(after),
+            Some(Utc.with_ymd_and_hms(2021, 6, 1, 18, 0, 0).unwrap())
+        );
+
+        // day of month or weekday, like crontab
+        let either = cron("0 12 13 * 5").unwrap();
+        let after = Utc.with_ymd_and_hms(2021, 5, 10, 0, 0, 0).unwrap();
+        assert_eq!(
+            either.next(after),
+            Some(Utc.with_ymd_and_hms(2021, 5, 13, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            either.next(Utc.with_ymd_and_hms(2021, 5, 13, 12, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2021, 5, 14, 12, 0, 0).unwrap())
+        );
+
+        assert_eq!(cron("0 0 31 2 *").unwrap().next(after), None);
+    }
+
+    #[test]
+    fn next_in_time_zone() {
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        let daily = cron("0 18 * * *").unwrap();
+
+        // 18:00 stays 18:00 on the clock over the end of summer time
+        let after = berlin.with_ymd_and_hms(2021, 10, 30, 19, 0, 0).unwrap();
+        let next = daily.next(after).unwrap();
+        assert_eq!(
+            next,
+            berlin.with_ymd_and_hms(2021, 10, 31, 18, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next.with_timezone(&Utc),
+            Utc.with_ymd_and_hms(2021, 10, 31, 17, 0, 0).unwrap()
+        );
+        assert_eq!(
+            daily
+                .next(berlin.with_ymd_and_hms(2021, 10, 30, 12, 0, 0).unwrap())
+                .map(|at| at.with_timezone(&Utc)),
+            Some(Utc.with_ymd_and_hms(2021, 10, 30, 16, 0, 0).unwrap())
+        );
+    }
+}
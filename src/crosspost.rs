@@ -0,0 +1,23 @@
+//! Publishing in announcement channels, serenity 0.8 doesn't know about it so it's called
+//! directly. A published message shows up in every server that follows the channel.
+use reqwest::blocking::Client;
+use serenity::{
+    http::Http,
+    model::id::{ChannelId, MessageId},
+};
+use std::error::Error;
+
+const API: &str = "https://discord.com/api/v9";
+
+pub fn publish(http: &Http, channel: ChannelId, message: MessageId) -> Result<(), Box<dyn Error>> {
+    Client::new()
+        .post(&format!(
+            "{}/channels/{}/messages/{}/crosspost",
+            API, channel.0, message.0
+        ))
+        .header("Authorization", &http.token)
+        .send()?
+        .error_for_status()?;
+
+    Ok(())
+}
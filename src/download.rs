@@ -0,0 +1,168 @@
+//! Attachment downloads, streamed into a temp file so only finished files end up in memory.
+//! How many run at once and how many bytes they add up to is limited, above that submissions
+//! get refused instead of piling up. A download that breaks off is retried up to
+//! `download_retries` times and continues with a range request where it stopped.
+use crate::config::CONFIG;
+use lazy_static::lazy_static;
+use reqwest::{blocking::Client, header::RANGE, StatusCode};
+use serenity::model::channel::Attachment;
+use std::{
+    env, fmt,
+    fs::{self, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Pause before the first retry, it doubles with every further one.
+const BACKOFF_MS: u64 = 500;
+
+#[derive(Debug)]
+pub enum Error {
+    /// `max_downloads` or `download_budget_bytes` would be exceeded
+    Busy,
+    Http(reqwest::Error),
+    /// the answer ended before the whole file was there
+    Incomplete(u64, u64),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Busy => write!(f, "download limit reached"),
+            Error::Http(why) => write!(f, "downloading: {}", why),
+            Error::Incomplete(have, size) => write!(f, "got {} of {} bytes", have, size),
+            Error::Io(why) => write!(f, "temp file: {}", why),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Usage {
+    running: usize,
+    bytes: u64,
+}
+
+lazy_static! {
+    static ref USAGE: Mutex<Usage> = Mutex::new(Usage::default());
+}
+
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Budget taken by one download, given back on drop.
+struct Reservation(u64);
+
+impl Reservation {
+    fn take(bytes: u64) -> Result<Reservation, Error> {
+        let (max_downloads, budget) = {
+            let config = CONFIG.read().unwrap();
+            (config.max_downloads, config.download_budget_bytes)
+        };
+
+        let mut usage = USAGE.lock().unwrap();
+        if usage.running >= max_downloads || usage.bytes + bytes > budget {
+            return Err(Error::Busy);
+        }
+        usage.running += 1;
+        usage.bytes += bytes;
+
+        Ok(Reservation(bytes))
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut usage = USAGE.lock().unwrap();
+        usage.running -= 1;
+        usage.bytes -= self.0;
+    }
+}
+
+/// Temp file that is deleted on drop, whatever happened to the download.
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Appends the rest of `attachment` to the temp file at `path`, from where the last try stopped.
+fn resume(client: &Client, attachment: &Attachment, path: &Path) -> Result<(), Error> {
+    let have = fs::metadata(path).map_or(0, |m| m.len());
+    if have > 0 && have >= attachment.size {
+        return Ok(());
+    }
+    let mut request = client.get(&attachment.url);
+    if have > 0 {
+        request = request.header(RANGE, format!("bytes={}-", have));
+    }
+    let mut response = request
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(Error::Http)?;
+
+    // without 206 the CDN ignored the range and sends the whole file again
+    let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(partial)
+        .truncate(!partial)
+        .open(path)
+        .map_err(Error::Io)?;
+    response.copy_to(&mut file).map_err(Error::Http)?;
+
+    let have = fs::metadata(path).map_err(Error::Io)?.len();
+    match have < attachment.size {
+        true => Err(Error::Incomplete(have, attachment.size)),
+        false => Ok(()),
+    }
+}
+
+/// Broken connections, server errors and cut off answers, a 404 of an expired link stays.
+fn transient(error: &Error) -> bool {
+    match error {
+        Error::Http(why) => why.status().is_none_or(|s| s.is_server_error()),
+        Error::Incomplete(_, _) | Error::Io(_) => true,
+        Error::Busy => false,
+    }
+}
+
+pub fn fetch(attachment: &Attachment) -> Result<Vec<u8>, Error> {
+    let _reservation = Reservation::take(attachment.size)?;
+    let retries = CONFIG.read().unwrap().download_retries;
+
+    let temp = TempFile(env::temp_dir().join(format!(
+        "emote_touhyou_{}_{}",
+        process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::SeqCst)
+    )));
+
+    let client = Client::new();
+    let mut tries = 0;
+    loop {
+        match resume(&client, attachment, &temp.0) {
+            Ok(_) => break,
+            Err(why) if tries < retries && transient(&why) => {
+                log!(
+                    "Download of {} broke off ({}), retrying",
+                    attachment.filename,
+                    why
+                );
+                thread::sleep(Duration::from_millis(BACKOFF_MS << tries));
+                tries += 1;
+            }
+            Err(why) => return Err(why),
+        }
+    }
+
+    fs::read(&temp.0).map_err(Error::Io)
+}
@@ -0,0 +1,22 @@
+//! Elo ratings from pairwise comparisons, every pair is one match and its vote share the result.
+//! Unlike 👍/👎 counts this ranks well when most voters only ever see a few candidates.
+
+/// Rating of a new suggestion.
+pub const START: f64 = 1000.0;
+/// Most a rating can move with one pair.
+const K: f64 = 32.0;
+
+pub fn start() -> f64 {
+    START
+}
+
+/// Chance of `a` winning against `b`.
+fn expected(a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((b - a) / 400.0))
+}
+
+/// New ratings of `a` and `b` after `a` got `share` of the votes in their pair.
+pub fn update(a: f64, b: f64, share: f64) -> (f64, f64) {
+    let change = K * (share - expected(a, b));
+    (a + change, b - change)
+}
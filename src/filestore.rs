@@ -0,0 +1,115 @@
+//! One JSON file per namespace in `data/` (or the directory in `EMOTE_DATA`), the `sled` store
+//! takes them over. Only namespaces that changed since the last save get written again.
+use crate::store::{State, Store};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, error::Error, fs, io, path::PathBuf, sync::Mutex};
+
+pub const NAMESPACES: [&str; 17] = [
+    "users",
+    "queue",
+    "suggestions",
+    "trash",
+    "variants",
+    "runoffs",
+    "replacements",
+    "pair",
+    "round",
+    "history",
+    "crowned",
+    "guilds",
+    "rotation",
+    "trials",
+    "emoji_uses",
+    "modlog",
+    "version",
+];
+
+pub struct FileStore {
+    dir: PathBuf,
+    /// content of every namespace as it was last written
+    written: Mutex<HashMap<&'static str, String>>,
+}
+
+impl FileStore {
+    pub fn new(dir: PathBuf) -> FileStore {
+        FileStore {
+            dir,
+            written: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn path(&self, namespace: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", namespace))
+    }
+
+    fn read(&self, namespace: &str) -> Result<Option<Value>, Box<dyn Error>> {
+        match fs::read_to_string(self.path(namespace)) {
+            Ok(content) => {
+                Ok(Some(serde_json::from_str(&content).map_err(|why| {
+                    format!("{}: {}", self.path(namespace).display(), why)
+                })?))
+            }
+            Err(why) if why.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(why) => Err(Box::new(why)),
+        }
+    }
+
+    /// Writes next to the file first, a crash never leaves half a namespace.
+    fn write<T: Serialize>(
+        &self,
+        written: &mut HashMap<&'static str, String>,
+        namespace: &'static str,
+        value: &T,
+    ) -> Result<(), Box<dyn Error>> {
+        let content = serde_json::to_string(value)?;
+        if written.get(namespace) == Some(&content) {
+            return Ok(());
+        }
+
+        let tmp = self.path(namespace).with_extension("tmp");
+        fs::write(&tmp, &content)?;
+        fs::rename(&tmp, self.path(namespace))?;
+        written.insert(namespace, content);
+
+        Ok(())
+    }
+}
+
+impl Store for FileStore {
+    fn load(&self) -> Result<Map<String, Value>, Box<dyn Error>> {
+        let mut state = Map::new();
+        for namespace in NAMESPACES.iter() {
+            if let Some(value) = self.read(namespace)? {
+                state.insert(namespace.to_string(), value);
+            }
+        }
+
+        Ok(state)
+    }
+
+    fn save(&self, state: &State) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.dir)?;
+        let mut written = self.written.lock().unwrap();
+
+        self.write(&mut written, "users", &state.users)?;
+        self.write(&mut written, "queue", &state.queue)?;
+        self.write(&mut written, "suggestions", &state.suggestions)?;
+        self.write(&mut written, "trash", &state.trash)?;
+        self.write(&mut written, "variants", &state.variants)?;
+        self.write(&mut written, "runoffs", &state.runoffs)?;
+        self.write(&mut written, "replacements", &state.replacements)?;
+        self.write(&mut written, "pair", &state.pair)?;
+        self.write(&mut written, "round", &state.round)?;
+        self.write(&mut written, "history", &state.history)?;
+        self.write(&mut written, "crowned", &state.crowned)?;
+        self.write(&mut written, "guilds", &state.guilds)?;
+        self.write(&mut written, "rotation", &state.rotation)?;
+        self.write(&mut written, "trials", &state.trials)?;
+        self.write(&mut written, "emoji_uses", &state.emoji_uses)?;
+        self.write(&mut written, "modlog", &state.modlog)?;
+        self.write(&mut written, "version", &state.version)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+//! Every processed file that goes into the voting is kept in `images/` (or the directory in
+//! `EMOTE_IMAGES`) under its `file_hash`, so posting it again needs no download from the Discord
+//! CDN, whose attachment links expire. The same file is only written once.
+use crate::file_hash;
+use std::{env, fs, path::PathBuf};
+
+fn path(hash: u64) -> PathBuf {
+    PathBuf::from(env::var("EMOTE_IMAGES").unwrap_or_else(|_| String::from("images")))
+        .join(format!("{:016x}", hash))
+}
+
+/// Stores `data` and returns its key, `None` when it couldn't be written.
+pub fn put(data: &[u8]) -> Option<u64> {
+    let hash = file_hash(data);
+    let path = path(hash);
+    if path.exists() {
+        return Some(hash);
+    }
+
+    // written next to it first, a crash leaves no half image behind
+    let tmp = path.with_extension("tmp");
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&tmp, data))
+        .and_then(|_| fs::rename(&tmp, &path));
+    match written {
+        Ok(_) => Some(hash),
+        Err(why) => {
+            log!("Could not store image {:016x}: {}", hash, why);
+            None
+        }
+    }
+}
+
+pub fn get(hash: u64) -> Option<Vec<u8>> {
+    fs::read(path(hash)).ok()
+}
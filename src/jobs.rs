@@ -0,0 +1,192 @@
+//! Emote suggestions get processed one after another by a worker, so long GIF encodes don't
+//! hold up the commands. Every job is a file in `jobs/` (or the directory in `EMOTE_JOBS`), so
+//! the worker can run in its own process and a restart picks up where the last run stopped.
+//!
+//! `<id>.json` waits, `<id>.running.json` is taken by a worker and `<id>.done.json` waits for
+//! the gateway to put its suggestions into the voting.
+use crate::{EmoteMessage, Options};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serenity::{framework::standard::CommandResult, http::Http, model::channel::Message};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread, time,
+};
+
+const QUEUED: &str = ".json";
+const RUNNING: &str = ".running.json";
+const DONE: &str = ".done.json";
+
+/// How often the worker looks for new jobs and the gateway for finished ones.
+const POLL: time::Duration = time::Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Job {
+    /// nanoseconds of the submission, unique and in order across processes
+    pub id: u64,
+    /// the `add` message, its attachments are the images of the job
+    pub msg: Message,
+    /// one name per attachment
+    pub names: Vec<String>,
+    pub options: Options,
+    /// all attachments are in `files`, from here on the original message is gone
+    pub downloaded: bool,
+    /// suggestions that are already in the voting
+    pub posted: Vec<EmoteMessage>,
+    /// suggestions that wait for the mods, see `submit`
+    #[serde(default)]
+    pub queued: Vec<EmoteMessage>,
+    /// the job stopped early, the slots of the missing suggestions go back to the author
+    #[serde(default)]
+    pub failed: bool,
+}
+
+lazy_static! {
+    /// time of the last save and whether it worked
+    static ref LAST_SAVE: Mutex<Option<(DateTime<Utc>, bool)>> = Mutex::new(None);
+}
+
+fn dir() -> PathBuf {
+    PathBuf::from(env::var("EMOTE_JOBS").unwrap_or_else(|_| String::from("jobs")))
+}
+
+fn file(id: u64, state: &str) -> PathBuf {
+    dir().join(format!("{}{}", id, state))
+}
+
+/// Directory for the downloads of `job`.
+pub fn files(job: &Job) -> PathBuf {
+    dir().join(job.id.to_string())
+}
+
+/// Ids of the jobs in `state`, oldest first.
+fn list(state: &str) -> Vec<u64> {
+    let mut ids = fs::read_dir(dir())
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter_map(|name| name.strip_suffix(state)?.parse::<u64>().ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    ids.sort_unstable();
+
+    ids
+}
+
+fn read(path: &Path) -> Option<Job> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(job) => Some(job),
+        Err(why) => {
+            log!("Invalid job {}: {}", path.display(), why);
+            None
+        }
+    }
+}
+
+/// Writes next to `path` first, the other process never sees half a job.
+fn save(job: &Job, path: &Path) {
+    let tmp = path.with_extension("tmp");
+    let saved = fs::create_dir_all(dir())
+        .map_err(|why| why.to_string())
+        .and_then(|_| serde_json::to_string(job).map_err(|why| why.to_string()))
+        .and_then(|content| fs::write(&tmp, content).map_err(|why| why.to_string()))
+        .and_then(|_| fs::rename(&tmp, path).map_err(|why| why.to_string()));
+
+    if let Err(why) = &saved {
+        log!("Could not save job {}: {}", job.id, why);
+    }
+    *LAST_SAVE.lock().unwrap() = Some((Utc::now(), saved.is_ok()));
+}
+
+/// Jobs that are waiting or running.
+pub fn len() -> usize {
+    list(QUEUED).len() + list(RUNNING).len()
+}
+
+pub fn last_save() -> Option<(DateTime<Utc>, bool)> {
+    *LAST_SAVE.lock().unwrap()
+}
+
+/// Queues the suggestions of `msg`, returns the number of jobs in front of it.
+pub fn push(msg: Message, names: Vec<String>, options: Options) -> usize {
+    let job = Job {
+        id: Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64,
+        msg,
+        names,
+        options,
+        downloaded: false,
+        posted: Vec::new(),
+        queued: Vec::new(),
+        failed: false,
+    };
+    let ahead = len();
+    save(&job, &file(job.id, QUEUED));
+
+    ahead
+}
+
+/// Saves the progress of a running job.
+pub fn update(job: &Job) {
+    save(job, &file(job.id, RUNNING));
+}
+
+/// Takes the oldest waiting job, the rename fails when another worker was faster.
+fn claim() -> Option<Job> {
+    list(QUEUED).into_iter().find_map(|id| {
+        let running = file(id, RUNNING);
+        fs::rename(file(id, QUEUED), &running).ok()?;
+        read(&running)
+    })
+}
+
+/// Runs jobs until the process ends. Jobs that were running when the last worker stopped come
+/// first, so there should only be one worker at a time.
+pub fn work(http: &Arc<Http>, run: fn(&Arc<Http>, &mut Job) -> CommandResult) -> ! {
+    for id in list(RUNNING) {
+        let _ = fs::rename(file(id, RUNNING), file(id, QUEUED));
+    }
+
+    loop {
+        let mut job = match claim() {
+            Some(job) => job,
+            None => {
+                thread::sleep(POLL);
+                continue;
+            }
+        };
+
+        if let Err(why) = run(http, &mut job) {
+            log!("Job {} returned error {:?}", job.id, why);
+            job.failed = true;
+        }
+
+        let _ = fs::remove_dir_all(files(&job));
+        save(&job, &file(job.id, DONE));
+        let _ = fs::remove_file(file(job.id, RUNNING));
+    }
+}
+
+/// Starts a worker in this process.
+pub fn start(http: Arc<Http>, run: fn(&Arc<Http>, &mut Job) -> CommandResult) {
+    thread::spawn(move || work(&http, run));
+}
+
+/// Hands every finished job to `finish` on the gateway side.
+pub fn collect(finish: fn(Job)) {
+    thread::spawn(move || loop {
+        for id in list(DONE) {
+            let path = file(id, DONE);
+            if let Some(job) = read(&path) {
+                finish(job);
+            }
+            let _ = fs::remove_file(path);
+        }
+        thread::sleep(POLL);
+    });
+}
@@ -0,0 +1,72 @@
+//! Rough legibility check of a processed emote at the size it's shown in chat.
+use image::{imageops, imageops::FilterType, RgbaImage};
+
+/// Emotes show up at ~32px inline.
+const CHAT_SIZE: u32 = 32;
+/// Dark theme chat background (#36393f), transparent areas show it.
+const BACKGROUND: [f32; 3] = [54.0, 57.0, 63.0];
+/// Gradient magnitude above which a pixel counts as edge.
+const EDGE: f32 = 0.25;
+
+fn luminance(rgb: [f32; 3]) -> f32 {
+    (0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2]) / 255.0
+}
+
+/// Returns the reasons why `img` will likely be hard to read, empty if it looks fine.
+pub fn check(img: &RgbaImage) -> Vec<&'static str> {
+    let small = imageops::resize(img, CHAT_SIZE, CHAT_SIZE, FilterType::Triangle);
+
+    // blend over the chat background, that's what people will actually see
+    let lum = small
+        .pixels()
+        .map(|p| {
+            let a = p[3] as f32 / 255.0;
+            let mut rgb = [0.0; 3];
+            for (i, c) in rgb.iter_mut().enumerate() {
+                *c = p[i] as f32 * a + BACKGROUND[i] * (1.0 - a);
+            }
+            luminance(rgb)
+        })
+        .collect::<Vec<_>>();
+
+    let n = lum.len() as f32;
+    let mean = lum.iter().sum::<f32>() / n;
+    let deviation = (lum.iter().map(|l| (l - mean).powi(2)).sum::<f32>() / n).sqrt();
+
+    let size = CHAT_SIZE as usize;
+    let at = |x: usize, y: usize| lum[y * size + x];
+    let mut edges = 0;
+    for y in 1..size - 1 {
+        for x in 1..size - 1 {
+            // sobel
+            let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x, y - 1)
+                - at(x + 1, y - 1);
+            if (gx * gx + gy * gy).sqrt() > EDGE {
+                edges += 1;
+            }
+        }
+    }
+    let density = edges as f32 / ((size - 2) * (size - 2)) as f32;
+
+    let mut issues = Vec::new();
+    if deviation < 0.06 {
+        issues.push("very low contrast");
+    }
+    if (mean - luminance(BACKGROUND)).abs() < 0.05 && deviation < 0.12 {
+        issues.push("blends into the dark chat background");
+    }
+    if density < 0.01 {
+        issues.push("almost no visible shapes");
+    }
+    if density > 0.35 {
+        issues.push("lots of fine detail that will blur together");
+    }
+
+    issues
+}
@@ -0,0 +1,107 @@
+//! Optional copy of everything the bot prints in `log_dir`, with a time in front of every line.
+//! `touhyou.log` moves to `touhyou.log.1` (the older ones one further up) when it reaches
+//! `log_max_bytes` or a new day starts, only `log_keep` old files are kept.
+use chrono::{DateTime, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+struct Log {
+    dir: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    size: u64,
+    day: NaiveDate,
+}
+
+lazy_static! {
+    static ref LOG: Mutex<Option<Log>> = Mutex::new(None);
+}
+
+fn path(dir: &Path, n: usize) -> PathBuf {
+    match n {
+        0 => dir.join("touhyou.log"),
+        n => dir.join(format!("touhyou.log.{}", n)),
+    }
+}
+
+fn open(dir: &Path) -> std::io::Result<File> {
+    fs::create_dir_all(dir)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path(dir, 0))
+}
+
+/// Starts writing lines to `dir` as well, the lines before only went to stdout.
+pub fn start(dir: &str, max_bytes: u64, keep: usize) {
+    let dir = PathBuf::from(dir);
+    let file = match open(&dir) {
+        Ok(x) => x,
+        Err(why) => return println!("Could not open the log in {}: {}", dir.display(), why),
+    };
+
+    let size = file.metadata().map_or(0, |m| m.len());
+    // a file from an earlier day gets rotated with the first line
+    let day = fs::metadata(path(&dir, 0))
+        .and_then(|m| m.modified())
+        .map_or_else(
+            |_| Utc::now().date_naive(),
+            |t| DateTime::<Utc>::from(t).date_naive(),
+        );
+    *LOG.lock().unwrap() = Some(Log {
+        dir,
+        max_bytes,
+        keep,
+        file,
+        size,
+        day,
+    });
+}
+
+fn rotate(log: &mut Log) -> std::io::Result<()> {
+    let _ = fs::remove_file(path(&log.dir, log.keep));
+    for n in (0..log.keep).rev() {
+        if path(&log.dir, n).exists() {
+            fs::rename(path(&log.dir, n), path(&log.dir, n + 1))?;
+        }
+    }
+    if log.keep == 0 {
+        let _ = fs::remove_file(path(&log.dir, 0));
+    }
+
+    log.file = open(&log.dir)?;
+    log.size = 0;
+    Ok(())
+}
+
+/// Prints `text` and appends it to the log file, see `println`.
+pub fn line(text: String) {
+    println!("{}", text);
+
+    let mut log = LOG.lock().unwrap();
+    let log = match log.as_mut() {
+        Some(x) => x,
+        None => return,
+    };
+
+    let now = Utc::now();
+    let line = format!("{} {}\n", now.format("%Y-%m-%d %H:%M:%S"), text);
+    if log.size > 0 && (log.size + line.len() as u64 > log.max_bytes || now.date_naive() != log.day)
+    {
+        if let Err(why) = rotate(log) {
+            println!("Could not rotate the log: {}", why);
+        }
+    }
+    log.day = now.date_naive();
+
+    match log.file.write_all(line.as_bytes()) {
+        Ok(()) => log.size += line.len() as u64,
+        Err(why) => println!("Could not write to the log: {}", why),
+    }
+}
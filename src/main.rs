@@ -1,342 +1,7560 @@
-use base64;
-use env_logger;
-use image;
-use image::ImageOutputFormat::Png;
+// prints a line that also goes to the `logfile` once it's started, this has to come before the
+// modules to be seen in them
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::logfile::line(format!($($arg)*))
+    };
+}
+
+mod animated;
+mod api;
+mod collage;
+mod compress;
+mod config;
+mod convert;
+mod cron;
+mod crosspost;
+mod download;
+mod elo;
+mod filestore;
+mod images;
+mod jobs;
+mod legibility;
+mod logfile;
+mod names;
+mod nsfw;
+mod outbox;
+mod permissions;
+mod pgstore;
+mod pool;
+mod redis;
+mod resize;
+mod rest;
+mod script;
+mod selfcheck;
+mod server;
+mod slash;
+mod sledstore;
+mod slots;
+mod sound;
+mod sqlitestore;
+mod sticker;
+mod store;
+mod telegram;
+mod templates;
+mod trim;
+mod usage;
+mod validate;
+mod wal;
+mod webhooks;
+
+#[cfg(test)]
+mod tests;
+
+use api::Discord;
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use config::{Collision, GuildConfig, Mode, CONFIG, GUILDS};
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat};
 use lazy_static::lazy_static;
+use permissions::{Target, PERMITTED_CHECK};
+use rand::seq::SliceRandom;
 use rayon::prelude::*;
+use resize::{Aspect, Filter, Window};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use serenity::{
+    cache::CacheRwLock,
+    client::bridge::gateway::ShardManager,
     framework::standard::{
         help_commands,
         macros::{command, group, help},
-        Args, CommandError, CommandGroup, CommandResult, DispatchError, HelpOptions,
-        StandardFramework,
+        Args, CommandError, CommandGroup, CommandResult, Delimiter, DispatchError, HelpOptions,
+        Reason, StandardFramework,
     },
     http::Http,
     model::{
-        channel::{Message, ReactionType},
+        channel::{Attachment, Channel, ChannelType, Message, Reaction, ReactionType},
         gateway::Ready,
-        guild::Emoji,
-        id::{ChannelId, GuildId, MessageId, UserId},
+        guild::{Emoji, Guild, PartialGuild, PremiumTier},
+        id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId},
+        user,
     },
     prelude::*,
 };
 use std::{
-    collections::{HashMap, HashSet},
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet, VecDeque},
     env,
     ffi::OsStr,
+    fs,
+    hash::{Hash, Hasher},
     path::Path,
-    sync::{Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc, RwLock,
+    },
+    thread, time,
 };
+use store::Counter;
+use wal::Op;
+use webhooks::Event;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct User {
     name: String,
     counter: u64,
+    /// turned off the milestone DMs with `dms off`
+    #[serde(default)]
+    quiet: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct EmoteMessage {
     messages: [Message; 2],
     emote: Emote,
+    /// start of the voting, a revote starts it again
+    since: DateTime<Utc>,
+    /// last 👍 or 👎 of every voter, only tracked with `vote_half_life_days`
+    #[serde(default)]
+    voted: HashMap<UserId, DateTime<Utc>>,
+    /// 👍 of this round, only tracked with `vote_budget`
+    #[serde(default)]
+    upvoted: HashSet<UserId>,
+    /// from the pairwise comparisons, see `elo`
+    #[serde(default = "elo::start")]
+    rating: f64,
+    /// thresholds the mods were already told about, see `alert`
+    #[serde(default)]
+    alerted: HashSet<Alert>,
+    /// everyone who flagged it with 🚩, see `report`
+    #[serde(default)]
+    reported: HashSet<UserId>,
+    /// the file is behind a spoiler since `report_threshold` was reached
+    #[serde(default)]
+    hidden: bool,
+    /// won its round and waits for `accept`, it holds a slot until then
+    #[serde(default)]
+    won: bool,
+    /// what the author was already told about, see `milestones`
+    #[serde(default)]
+    milestones: HashSet<Milestone>,
+    /// 👍 and 👎 from before `restore` posted it again, their reactions are gone
+    #[serde(default)]
+    carried: (u64, u64),
+}
+
+impl EmoteMessage {
+    fn new(messages: [Message; 2], emote: Emote) -> EmoteMessage {
+        EmoteMessage {
+            messages,
+            emote,
+            since: Utc::now(),
+            voted: HashMap::new(),
+            upvoted: HashSet::new(),
+            rating: elo::START,
+            alerted: HashSet::new(),
+            reported: HashSet::new(),
+            hidden: false,
+            won: false,
+            milestones: HashSet::new(),
+            carried: (0, 0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Alert {
+    /// `alert_score` net 👍
+    Winner,
+    /// 👎 at `alert_ratio` times the 👍
+    Loser,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Milestone {
+    /// `milestone_upvotes` 👍
+    Upvotes,
+    /// `milestone_quorum` votes of both kinds
+    Quorum,
+    /// among the `milestone_top` of the leaderboard
+    Top,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct Emote {
     name: String,
     author: String,
+    author_id: UserId,
+    kind: Kind,
+    animated: bool,
+    /// hash of the submitted file, see `file_hash`
+    hash: u64,
+    /// `hash` is from before version 2 of the store, see `legacy_hash`
+    #[serde(default)]
+    legacy: bool,
+    /// picked with `category:`
+    #[serde(default)]
+    category: Option<String>,
+    /// key of the processed file in `images`, `None` from before the store, see `file_of`
+    #[serde(default)]
+    image: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Emoji,
+    Sticker,
+    Sound,
+}
+
+/// Two versions of the same emote, the winner moves on into the normal voting.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct VariantPoll {
+    message: Message,
+    name: String,
+    author: String,
+    author_id: UserId,
+    animated: [bool; 2],
+    hashes: [u64; 2],
+    /// `hashes` are from before version 2 of the store, see `legacy_hash`
+    #[serde(default)]
+    legacy: bool,
+    #[serde(default)]
+    category: Option<String>,
+    /// keys of the posted files in `images`
+    #[serde(default)]
+    images: [Option<u64>; 2],
+}
+
+/// A suggestion that left the voting. Neither the name nor the file of a rejected one can come
+/// back before the cooldown ends.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Finished {
+    /// id of the vote message, same as the key in `MESSAGES` while it was pending
+    id: MessageId,
+    name: String,
+    author: String,
+    author_id: UserId,
+    hash: u64,
+    /// `hash` is from before version 2 of the store, see `legacy_hash`
+    #[serde(default)]
+    legacy: bool,
+    votes: (u64, u64),
+    accepted: bool,
+    at: DateTime<Utc>,
+}
+
+/// A suggestion `remove` took out of the voting, its messages stay until `UNDO_MINUTES` are
+/// over so `undo` can bring it back with its votes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Trashed {
+    emsg: EmoteMessage,
+    at: DateTime<Utc>,
+    /// set once the messages are deleted, `restore` posts it again until `RESTORE_DAYS` are over
+    #[serde(default)]
+    purged: Option<Purged>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Purged {
+    /// base64 of the submitted file
+    file: String,
+    filename: String,
+    votes: (u64, u64),
+}
+
+/// Something a mod did, posted in the `modlog_channel` and kept for `modlog`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ModAction {
+    actor: String,
+    actor_id: UserId,
+    /// like `removed` or `approved`
+    action: String,
+    /// name and id of the suggestion, or the round
+    target: String,
+    at: DateTime<Utc>,
+}
+
+/// Head-to-head vote between the two suggestions fighting for the last winner slot of a round,
+/// see `close`. The loser gets rejected when it ends.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Runoff {
+    message: Message,
+    /// vote message ids of 🅰️ and 🅱️
+    candidates: [MessageId; 2],
+    /// valid votes of both in the round, for the records
+    votes: [(u64, u64); 2],
+    ends: DateTime<Utc>,
+}
+
+/// Vote on which emote of the full pool gets deleted for a winner, see `poll_replacements`.
+/// The winner gets uploaded once it ends.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Replacement {
+    message: Message,
+    /// vote message id of the winner
+    winner: MessageId,
+    /// the least used emotes of the pool by `REPLACEMENT_CHOICES`, with their names
+    candidates: Vec<(EmojiId, String)>,
+    ends: DateTime<Utc>,
+}
+
+/// An emote of `rotation_slots`, the next closed round replaces it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Rotated {
+    emoji: EmojiId,
+    name: String,
+    since: DateTime<Utc>,
+}
+
+/// An uploaded emote that has to pass a vote on whether it stays once `trial_weeks` are over,
+/// see `confirm_trials`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Trial {
+    emoji: EmojiId,
+    name: String,
+    installed: DateTime<Utc>,
+    /// the confirmation vote once it started
+    message: Option<Message>,
+    ends: Option<DateTime<Utc>>,
+}
+
+/// During the nomination suggestions come in without votes, the voting starts for all of them
+/// at once so early posts have no head start.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+enum Phase {
+    Nomination,
+    #[default]
+    Voting,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct Round {
+    phase: Phase,
+    /// the scheduler opens the voting at this time
+    voting_at: Option<DateTime<Utc>>,
+    /// the scheduler closes the round at this time, see `round_cron`
+    #[serde(default)]
+    closes_at: Option<DateTime<Utc>>,
+    /// pinned message in the voting channel with the time left, see `countdown`
+    #[serde(default)]
+    countdown: Option<MessageId>,
+    /// pinned top 10 in the voting channel, see `standings`
+    #[serde(default)]
+    leaderboard: Option<MessageId>,
+}
+
+/// The open pairwise comparison, it's counted when the next one gets posted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Pair {
+    message: Message,
+    /// vote message ids of 🅰️ and 🅱️
+    candidates: [MessageId; 2],
+}
+
+/// A submission of the last `DUPLICATE_SECONDS`, a second one of the same image is ignored.
+struct Recent {
+    name: String,
+    /// the `add` message
+    message: MessageId,
+    at: DateTime<Utc>,
+}
+
+/// Mods remove suggestions by reacting with it, Discord may send it with a variation selector.
+const TRASH: &str = "🗑";
+const REPORT: &str = "🚩";
+const APPROVE: &str = "✅";
+const REJECT: &str = "❌";
+/// Discord hides attachments whose name starts with this until they get clicked.
+const SPOILER: &str = "SPOILER_";
+
+const VARIANT_A: &str = "🅰️";
+const VARIANT_B: &str = "🅱️";
+
+/// Optional `key:value` arguments after the name of a suggestion.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Options {
+    filter: Filter,
+    #[serde(default)]
+    aspect: Aspect,
+    /// crop picked with `mode:adjust`
+    #[serde(default)]
+    window: Window,
+    /// `mode:adjust`, the author moves the crop before the job is queued
+    #[serde(skip)]
+    adjust: bool,
+    #[serde(default)]
+    category: Option<String>,
+}
+
+impl Options {
+    /// Defaults come from the config and the settings of `guild`.
+    fn parse(args: &mut Args, guild: Option<GuildId>) -> Result<Options, String> {
+        let mut options = Options {
+            filter: CONFIG.read().unwrap().resize_filter,
+            aspect: guild
+                .and_then(|g| GUILDS.read().unwrap().get(&g).map(|g| g.aspect))
+                .unwrap_or_default(),
+            window: Window::default(),
+            adjust: false,
+            category: None,
+        };
+
+        for arg in args.iter::<String>().filter_map(Result::ok) {
+            match arg.splitn(2, ':').collect::<Vec<_>>()[..] {
+                ["filter", value] => options.filter = value.parse()?,
+                ["mode", "adjust"] => {
+                    options.aspect = Aspect::Crop;
+                    options.adjust = true;
+                }
+                ["mode", value] => options.aspect = value.parse()?,
+                ["category", value] => options.category = Some(category(value)?),
+                _ => return Err(format!("Unknown option {}.", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Checks a `category:` against the `categories` of the config, they're compared in lowercase.
+fn category(value: &str) -> Result<String, String> {
+    let value = value.to_lowercase();
+    let categories = &CONFIG.read().unwrap().categories;
+
+    if value.is_empty()
+        || value.len() > 32
+        || !value
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(String::from(
+            "A category has up to 32 letters, digits, - or _.",
+        ));
+    }
+    if !categories.is_empty() && !categories.iter().any(|c| c.to_lowercase() == value) {
+        return Err(format!(
+            "Unknown category {}, pick one of: {}.",
+            value,
+            categories.join(", ")
+        ));
+    }
+
+    Ok(value)
+}
+
+fn voting_open() -> bool {
+    ROUND.read().unwrap().phase == Phase::Voting
 }
 
+/// Adds the vote reactions to a new suggestion, during the nomination they come later. 🚩 is
+/// there from the start with `report_threshold`.
+fn seed_votes() -> Vec<ReactionType> {
+    let mut reactions = match voting_open() {
+        true => vec![ReactionType::from("👍"), ReactionType::from("👎")],
+        false => Vec::new(),
+    };
+    if CONFIG.read().unwrap().report_threshold.is_some() {
+        reactions.push(ReactionType::from(REPORT));
+    }
+    reactions
+}
+
+/// Where suggestions of `category` are voted on, see `category_channels`.
+fn voting_channel(category: Option<&str>) -> ChannelId {
+    category
+        .and_then(|c| CONFIG.read().unwrap().category_channels.get(c).copied())
+        .unwrap_or(CHANNEL)
+}
+
+/// Minutes between two edits of the countdown message.
+const COUNTDOWN_MINUTES: u64 = 5;
+/// Suggestions on the leaderboard.
+const LEADERBOARD_SIZE: usize = 10;
+/// Suggestions `export card` shows at most, the card gets tall enough with them.
+const CARD_LIMIT: usize = 25;
+
+/// A vote or suggestion came or went since the leaderboard was last edited.
+static STANDINGS_CHANGED: AtomicBool = AtomicBool::new(true);
+/// A vote came or went since the last check of the `milestones`.
+static VOTES_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Length of a runoff, a tie at the end runs it this long again.
+const RUNOFF_HOURS: i64 = 24;
+
+/// Length of a poll for the emote that makes room for a winner, see `poll_replacements`.
+const REPLACEMENT_HOURS: i64 = 24;
+/// Reactions of the least used emotes on a replacement poll, there are as many choices.
+const REPLACEMENT_CHOICES: [&str; 5] = ["1️⃣", "2️⃣", "3️⃣", "4️⃣", "5️⃣"];
+/// Length of the confirmation vote of a `Trial`.
+const TRIAL_HOURS: i64 = 48;
+/// Emoji uses were counted since the last save.
+static USES_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Time in which `undo` brings back a removed suggestion, its messages are deleted afterwards.
+const UNDO_MINUTES: i64 = 10;
+/// Time in which `restore` posts a removed suggestion again, its file is dropped afterwards.
+const RESTORE_DAYS: i64 = 30;
+
+/// Time mods have to confirm a removal of several suggestions.
+const CONFIRM_SECONDS: u64 = 30;
+
+/// Argument of the destructive commands that only reports what they would do.
+const DRY_RUN: &str = "--dry-run";
+/// Argument of `add` that submits a taken name under the first free variation instead.
+const FORCE_SUGGESTED: &str = "--force-suggested";
+/// Free variations of a taken name offered to the author.
+const NAME_SUGGESTIONS: usize = 3;
+
+/// Time to pick a reaction on a crop preview, and how often the crop can be moved at most.
+const ADJUST_SECONDS: u64 = 60;
+const ADJUST_ROUNDS: usize = 20;
+/// Reactions on a crop preview, in the order of `adjust_crop`.
+const ADJUST: [&str; 6] = ["⬅️", "➡️", "⬆️", "⬇️", "🔍", "✅"];
+
+/// Matches `search` lists at most, Discord messages are limited to 2000 characters.
+const SEARCH_LIMIT: usize = 15;
+/// Suggestions `list` shows at most, for the same reason.
+const LIST_LIMIT: usize = 30;
+/// Mod actions `modlog` shows at most, for the same reason.
+const MODLOG_LIMIT: usize = 20;
+/// Most called kinds of Discord calls that `diag` shows.
+const DIAG_CALLS: usize = 8;
+
+/// Seconds in which the same image from the same author counts as the same submission, covers
+/// double sends and events Discord delivers twice.
+const DUPLICATE_SECONDS: i64 = 120;
+
 const CHANNEL: ChannelId = ChannelId(292651939555049472);
 const GUILD: GuildId = GuildId(292651939555049472);
 
 lazy_static! {
     static ref USERS: RwLock<HashMap<UserId, User>> = RwLock::new(HashMap::new());
+    /// suggestions waiting for the mods by the id of their decision message, see `submit`
+    static ref QUEUE: RwLock<HashMap<MessageId, EmoteMessage>> = RwLock::new(HashMap::new());
     static ref MESSAGES: RwLock<HashMap<MessageId, EmoteMessage>> = RwLock::new(HashMap::new());
+    /// removed suggestions by the id of their vote message, see `trash_suggestion`
+    static ref REMOVED: RwLock<HashMap<MessageId, Trashed>> = RwLock::new(HashMap::new());
+    static ref VARIANTS: RwLock<HashMap<MessageId, VariantPoll>> = RwLock::new(HashMap::new());
+    static ref RUNOFFS: RwLock<HashMap<MessageId, Runoff>> = RwLock::new(HashMap::new());
+    static ref REPLACEMENTS: RwLock<HashMap<MessageId, Replacement>> = RwLock::new(HashMap::new());
+    static ref PAIR: RwLock<Option<Pair>> = RwLock::new(None);
+    static ref ROUND: RwLock<Round> = RwLock::new(Round::default());
+    static ref HISTORY: RwLock<Vec<Finished>> = RwLock::new(Vec::new());
+    /// when commands failed within the last day, for `diag`
+    static ref ERRORS: RwLock<VecDeque<DateTime<Utc>>> = RwLock::new(VecDeque::new());
+    /// authors that got the winner role with the last closed round
+    static ref CROWNED: RwLock<HashSet<UserId>> = RwLock::new(HashSet::new());
+    /// submissions of the last `DUPLICATE_SECONDS` by `submission_key`
+    static ref RECENT: RwLock<HashMap<u64, Recent>> = RwLock::new(HashMap::new());
+    /// expired suggestions that passed but could not be uploaded, they wait for `accept`
+    static ref UNUPLOADED: RwLock<HashSet<MessageId>> = RwLock::new(HashSet::new());
+    /// uploaded emotes on trial by their id, see `confirm_trials`
+    static ref TRIALS: RwLock<HashMap<EmojiId, Trial>> = RwLock::new(HashMap::new());
+    /// the emotes in the `rotation_slots`, uploaded with the last closed round
+    static ref ROTATION: RwLock<Vec<Rotated>> = RwLock::new(Vec::new());
+    /// messages and reactions with each emote of the guild, replacement polls offer the least used
+    static ref EMOJI_USES: RwLock<HashMap<EmojiId, u64>> = RwLock::new(HashMap::new());
+    /// oldest first, see `audit`
+    static ref MODLOG: RwLock<Vec<ModAction>> = RwLock::new(Vec::new());
+    /// vote messages that got or lost votes since the last minute, for `alert`
+    static ref VOTED: RwLock<HashSet<MessageId>> = RwLock::new(HashSet::new());
 }
 
 struct Handler;
 
+struct ShardManagerContainer;
+
+impl TypeMapKey for ShardManagerContainer {
+    type Value = Arc<Mutex<ShardManager>>;
+}
+
 impl EventHandler for Handler {
-    fn ready(&self, _: Context, ready: Ready) {
-        println!("{} is connected!", ready.user.name);
+    fn ready(&self, mut ctx: Context, ready: Ready) {
+        rest::timed(&mut ctx);
+        log!("{} is connected!", ready.user.name);
+
+        // only the shard that has the guild sets it up
+        if let Some([shard, total]) = ready.shard {
+            if (GUILD.0 >> 22) % total != shard {
+                return;
+            }
+        }
+
+        permissions::seed(&ctx.http);
+
+        let mut channels = vec![CHANNEL];
+        channels.extend(CONFIG.read().unwrap().category_channels.values());
+        channels.sort();
+        channels.dedup();
+        for channel in channels {
+            match selfcheck::missing(&ctx.http, GUILD, channel, ready.user.id) {
+                Ok(missing) if missing.is_empty() => {}
+                Ok(missing) => {
+                    let content = format!(
+                        "The bot is missing these permissions in <#{}>: {}.",
+                        channel,
+                        missing.join(", ")
+                    );
+                    log!("{}", content);
+                    if !missing.contains(&"Send Messages") {
+                        send(ctx.http.clone(), CHANNEL, &content);
+                    }
+                }
+                Err(why) => log!("Could not check permissions: {:?}", why),
+            }
+        }
+
+        if let Err(why) = slash::register(&ctx.http, ready.user.id, GUILD) {
+            log!("Could not register slash commands: {:?}", why);
+        }
     }
-}
 
-#[group]
-#[commands(add, stats, remove)]
-struct General;
+    // the cache still has the guild from before the update
+    fn guild_update(
+        &self,
+        mut ctx: Context,
+        old: Option<Arc<serenity::prelude::RwLock<Guild>>>,
+        new: PartialGuild,
+    ) {
+        rest::timed(&mut ctx);
+        let old = match old {
+            Some(guild) if new.id == GUILD => guild.read().premium_tier,
+            _ => return,
+        };
+        if old != new.premium_tier {
+            thread::spawn(move || tier_changed(&ctx.http, old, &new));
+        }
+    }
 
-#[help]
-#[individual_command_tip = "If you want more information about a specific command, just pass the command as argument."]
-#[command_not_found_text = "Could not find: `{}`."]
-#[max_levenshtein_distance(3)]
-#[lacking_permissions = "Hide"]
-fn my_help(
-    context: &mut Context,
-    msg: &Message,
-    args: Args,
-    help_options: &'static HelpOptions,
-    groups: &[&'static CommandGroup],
-    owners: HashSet<UserId>,
-) -> CommandResult {
-    help_commands::with_embeds(context, msg, args, help_options, groups, owners)
-}
+    // uses of the emotes of the guild, for `poll_replacements`
+    fn message(&self, mut ctx: Context, msg: Message) {
+        rest::timed(&mut ctx);
+        if msg.guild_id == Some(GUILD) && !msg.author.bot {
+            count_uses(&ctx.cache, emoji_mentions(&msg.content));
+        }
+    }
 
-#[command]
-#[only_in(guilds)]
-#[example("FeelsGoodMan [image as attachment]")]
-fn add(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
-    let http = ctx.http.clone();
-    let mut users = USERS.write().unwrap();
+    // mods can remove a suggestion by reacting with 🗑️
+    fn reaction_add(&self, mut ctx: Context, reaction: Reaction) {
+        rest::timed(&mut ctx);
+        match &reaction.emoji {
+            ReactionType::Custom { id, .. } if reaction.guild_id == Some(GUILD) => {
+                return count_uses(&ctx.cache, vec![*id])
+            }
+            ReactionType::Unicode(n) if n == "👍" || n == "👎" => {
+                STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+                VOTES_CHANGED.store(true, AtomicOrdering::Relaxed);
+                VOTED.write().unwrap().insert(reaction.message_id);
+                return record_vote(&ctx, &reaction);
+            }
+            ReactionType::Unicode(n) if n == APPROVE || n == REJECT => {
+                return decide(&ctx, &reaction)
+            }
+            ReactionType::Unicode(n) if n.trim_end_matches('\u{fe0f}') == REPORT => {
+                return report(&ctx, &reaction)
+            }
+            ReactionType::Unicode(n) if n.trim_end_matches('\u{fe0f}') == TRASH => {}
+            _ => return,
+        }
 
-    println!("{}   Args for stats: {}", msg.author.name, &args.message());
-    let user = users.entry(msg.author.id).or_insert(User {
-        name: msg.author.name.clone(),
-        counter: 0,
-    });
+        let mut messages = MESSAGES.write().unwrap();
+        let id = match suggestion_of(reaction.message_id, &messages) {
+            Some(x) => x,
+            None => return,
+        };
+
+        let member = match rest::call("Fetching member", || GUILD.member(&ctx, reaction.user_id)) {
+            Ok(x) => x,
+            Err(why) => {
+                log!("Could not fetch member {}: {:?}", reaction.user_id, why);
+                return;
+            }
+        };
+
+        if !permissions::allowed("remove", reaction.user_id, &member.roles) {
+            if let Err(why) = rest::call("Deleting reaction", || reaction.delete(&ctx)) {
+                log!("Could not delete reaction: {:?}", why);
+            }
+            return;
+        }
 
-    if user.counter == 3 {
-        return dm_user_err(http, msg, "You can only post 3 suggestions.");
+        let name = messages[&id].emote.name.clone();
+        match trash_suggestion(&ctx.http, &mut messages, id) {
+            Ok(_) => audit(
+                ctx.http.clone(),
+                &member.user.read().name,
+                reaction.user_id,
+                "removed with 🗑",
+                format!("{} ({})", name, short_id(id)),
+            ),
+            Err(why) => log!("Could not remove {} by reaction: {}", name, why),
+        }
+        drop(messages);
+        store::save(snapshot);
     }
 
-    // check for the name
-    let name = match args.single::<String>() {
-        Ok(x) => x,
-        Err(_) => return dm_user_err(http, msg, "No name found."),
-    };
+    // a taken back 👍 frees a slot of the `vote_budget`
+    fn reaction_remove(&self, _: Context, reaction: Reaction) {
+        match &reaction.emoji {
+            ReactionType::Unicode(n) if n == "👍" || n == "👎" => {}
+            _ => return,
+        }
+        STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+        VOTES_CHANGED.store(true, AtomicOrdering::Relaxed);
+        VOTED.write().unwrap().insert(reaction.message_id);
+        if !matches!(&reaction.emoji, ReactionType::Unicode(n) if n == "👍") {
+            return;
+        }
 
-    // check if there is exactly one attachment
-    if msg.attachments.len() != 1 {
-        return dm_user_err(http, msg, "No attachment found.");
+        let mut messages = MESSAGES.write().unwrap();
+        let emsg = match messages.get_mut(&reaction.message_id) {
+            Some(x) => x,
+            None => return,
+        };
+        if !emsg.upvoted.remove(&reaction.user_id) {
+            return;
+        }
+        store::log(Op::Suggestion(Box::new(emsg.clone())));
+        drop(messages);
+        store::save(snapshot);
     }
 
-    let attachment = msg.attachments.first().unwrap();
+    fn unknown(&self, mut ctx: Context, name: String, raw: Value) {
+        rest::timed(&mut ctx);
+        if name != "INTERACTION_CREATE" {
+            return;
+        }
 
-    //check emoji size, max 6MB
-    if attachment.size >= 6_000_000 {
-        return dm_user_err(http, msg, "6MB is the size limit for images.");
+        match serde_json::from_value(raw) {
+            Ok(interaction) => {
+                if let Err(why) = on_interaction(&ctx, interaction) {
+                    log!("Could not answer interaction: {:?}", why);
+                }
+                store::save(snapshot);
+            }
+            Err(why) => log!("Could not parse interaction: {:?}", why),
+        }
     }
+}
 
-    // check if the attachment is an image and check for best size of emotes (128x128px)
-    match attachment.dimensions() {
-        Some(dimensions) => {
-            if dimensions.0 < 120 || dimensions.1 < 120 {
-                return dm_user_err(http, msg, "Image must be at least 128x128px.");
+/// Remembers when a vote was cast, so it can lose weight with `vote_half_life_days`, and takes
+/// back a 👍 over the `vote_budget`.
+fn record_vote(ctx: &Context, reaction: &Reaction) {
+    if !voting_open() && reaction.user_id != ctx.cache.read().user.id {
+        if MESSAGES.read().unwrap().contains_key(&reaction.message_id) {
+            if let Err(why) = rest::call("Deleting reaction", || reaction.delete(ctx)) {
+                log!("Could not delete reaction: {:?}", why);
             }
         }
-        None => return dm_user_err(http, msg, "Attachment is not an image."),
+        return;
+    }
+
+    let (decay, budget) = {
+        let config = CONFIG.read().unwrap();
+        (config.vote_half_life_days.is_some(), config.vote_budget)
     };
+    let budget =
+        budget.filter(|_| matches!(&reaction.emoji, ReactionType::Unicode(n) if n == "👍"));
+    if !decay && budget.is_none() || reaction.user_id == ctx.cache.read().user.id {
+        return;
+    }
 
-    // get the attachment
-    let img = match attachment.download() {
-        Ok(x) => x,
-        Err(why) => {
-            dm_user(http, msg, "Attachment download failed, try again later.");
-            return Err(CommandError(format!("Attachment download: {:?}", why)));
-        }
+    let mut messages = MESSAGES.write().unwrap();
+    let used = messages
+        .values()
+        .filter(|e| e.upvoted.contains(&reaction.user_id))
+        .count();
+    let emsg = match messages.get_mut(&reaction.message_id) {
+        Some(x) => x,
+        None => return,
     };
 
-    // delete original message after download is finished!
-    match msg.delete(http.clone()) {
-        Ok(_) => {}
-        Err(why) => {
-            dm_user(http, msg, "Discord error, pls try again later.");
-            return Err(CommandError(format!("Deleting org. msg: {:?}", why)));
+    if let Some(budget) = budget {
+        if used >= budget && !emsg.upvoted.contains(&reaction.user_id) {
+            drop(messages);
+            if let Err(why) = rest::call("Deleting reaction", || reaction.delete(ctx)) {
+                log!("Could not delete reaction: {:?}", why);
+            }
+            let content = format!(
+                "You can upvote {} suggestions per round, take back one of your 👍 first.",
+                budget
+            );
+            if let Err(why) = rest::post("Sending DM", || {
+                reaction
+                    .user_id
+                    .create_dm_channel(ctx)
+                    .and_then(|dm| dm.say(ctx, &content))
+            }) {
+                log!("Could not DM {}: {:?}", reaction.user_id, why);
+            }
+            return;
         }
+        emsg.upvoted.insert(reaction.user_id);
     }
+    if decay {
+        emsg.voted.insert(reaction.user_id, Utc::now());
+    }
+    store::log(Op::Suggestion(Box::new(emsg.clone())));
+    drop(messages);
+    store::save(snapshot);
+}
 
-    // split the filename with extension
-    let filetype = match Path::new(&attachment.filename)
-        .extension()
-        .and_then(OsStr::to_str)
-    {
+/// Ids of the custom emotes like `<:name:id>` and `<a:name:id>` in `content`.
+fn emoji_mentions(content: &str) -> Vec<EmojiId> {
+    content
+        .split('<')
+        .skip(1)
+        .filter_map(|rest| {
+            let inner = &rest[..rest.find('>')?];
+            let inner = inner.strip_prefix('a').unwrap_or(inner).strip_prefix(':')?;
+            let (_, id) = inner.split_once(':')?;
+            id.parse().ok().map(EmojiId)
+        })
+        .collect()
+}
+
+/// Emotes of other servers take no slot here, they aren't counted.
+fn count_uses(cache: &CacheRwLock, used: Vec<EmojiId>) {
+    if used.is_empty() {
+        return;
+    }
+    let guild = match cache.read().guild(GUILD) {
         Some(x) => x,
-        None => return dm_user_err(http, msg, "Filename is not processable."),
+        None => return,
     };
+    let guild = guild.read();
 
-    // check image type
-    if !(vec!["jpeg", "jpg", "png"].contains(&filetype)) {
-        return dm_user_err(http, msg, "JPG, JPEG or PNG, nothing else is allowed.");
+    let mut uses = EMOJI_USES.write().unwrap();
+    for id in used.into_iter().filter(|id| guild.emojis.contains_key(id)) {
+        *uses.entry(id).or_insert(0) += 1;
+        store::add(Counter::EmojiUses(id), 1);
+        USES_CHANGED.store(true, AtomicOrdering::Relaxed);
     }
+}
 
-    let mut buf = Vec::new();
-    let emote = Emote {
-        name: name.clone(),
-        author: msg.author.name.to_string(),
+/// Counts a 🚩, at `report_threshold` distinct reporters the file goes behind a spoiler and the
+/// mods get pinged in the `mod_channel` without waiting for the voting.
+fn report(ctx: &Context, reaction: &Reaction) {
+    let (threshold, channel) = {
+        let config = CONFIG.read().unwrap();
+        (config.report_threshold, config.mod_channel)
+    };
+    let threshold = match threshold {
+        Some(x) => x,
+        None => return,
     };
+    if reaction.user_id == ctx.cache.read().user.id {
+        return;
+    }
 
-    let img = match image::load_from_memory(&img) {
-        Ok(img) => img,
-        Err(why) => {
-            dm_user(http, msg, "Error processing image.");
-            return Err(CommandError(format!("Processing image: {:?}", why)));
-        }
+    let mut messages = MESSAGES.write().unwrap();
+    let emsg = match messages.get_mut(&reaction.message_id) {
+        Some(x) => x,
+        None => return,
     };
-    img.thumbnail_exact(128, 128).write_to(&mut buf, Png)?;
-    let emote_string = base64::encode(&buf);
+    if !emsg.reported.insert(reaction.user_id) {
+        return;
+    }
+    log!("{} reported {}", reaction.user_id, emsg.emote.name);
 
-    let em: Emoji = match GUILD.create_emoji(
-        http.clone(),
-        &*emote.name,
-        &*format!("data:image/png;base64,{}", emote_string),
-    ) {
-        Ok(x) => x,
-        Err(why) => {
-            dm_user(http, msg, "Discord error, pls try again later.");
-            return Err(CommandError(format!("Creating emote: {:?}", why)));
+    let reached = emsg.reported.len() >= threshold && !emsg.hidden;
+    if reached {
+        match hide(&ctx.http, emsg) {
+            Ok(()) => emsg.hidden = true,
+            Err(why) => log!("Could not hide {}: {:?}", emsg.emote.name, why),
         }
+    }
+    store::log(Op::Suggestion(Box::new(emsg.clone())));
+
+    if let Some(channel) = channel.filter(|_| reached) {
+        let mods = CONFIG
+            .read()
+            .unwrap()
+            .mod_roles
+            .iter()
+            .flatten()
+            .map(|r| format!("<@&{}> ", r))
+            .collect::<String>();
+        let content = format!(
+            "{}**{}** was reported by {} and is hidden now, remove it with remove {}: https://discord.com/channels/{}/{}/{}",
+            mods,
+            emsg.emote.name,
+            emsg.reported
+                .iter()
+                .map(|u| format!("<@{}>", u))
+                .collect::<Vec<_>>()
+                .join(", "),
+            reaction.message_id,
+            GUILD,
+            reaction.channel_id,
+            reaction.message_id
+        );
+        send(ctx.http.clone(), channel, &content);
+    }
+    drop(messages);
+    store::save(snapshot);
+}
+
+/// Posts the file of a suggestion again behind a spoiler and deletes the old one, attachments
+/// can't be edited.
+fn hide(http: &Http, emsg: &mut EmoteMessage) -> serenity::Result<()> {
+    let old = &emsg.messages[0];
+    let attachment = match old.attachments.first() {
+        Some(x) => x,
+        None => return Ok(()),
     };
+    let file = file_of(emsg, attachment)?;
+    let filename = format!("{}{}", SPOILER, attachment.filename);
 
-    let bot_msg1 = match CHANNEL.send_message(&ctx.http, |m| {
-        m.content(format!("{}", emote.name));
-        m.add_files(vec![(&*buf, &*format!("{}.png", name))])
-    }) {
-        Ok(x) => x,
-        Err(why) => {
-            dm_user(http, msg, "Discord error, pls try again later.");
-            return Err(CommandError(format!("Sending msg one: {:?}", why)));
-        }
+    let new = rest::post("Sending message", || {
+        old.channel_id.send_message(http, |m| {
+            m.content(format!(
+                "{}\nHidden after reports, a mod is looking at it.",
+                old.content
+            ));
+            m.add_file((&*file, &*filename))
+        })
+    })?;
+    if let Err(why) = rest::call("Deleting message", || old.delete(http)) {
+        log!("Could not delete message {}: {:?}", old.id, why);
+    }
+    emsg.messages[0] = new;
+
+    Ok(())
+}
+
+/// Tells the `mod_channel` once a suggestion crosses `alert_score` or `alert_ratio`, so obvious
+/// winners can be accepted and obvious losers removed early. Checked each minute for the
+/// suggestions that were `VOTED` on, one fetch of the votes covers all votes in between.
+fn alert(http: &Arc<Http>, id: MessageId) {
+    let (channel, score, ratio) = {
+        let config = CONFIG.read().unwrap();
+        (config.mod_channel, config.alert_score, config.alert_ratio)
+    };
+    let channel = match channel {
+        Some(x) if score.is_some() || ratio.is_some() => x,
+        _ => return,
     };
 
-    let bot_msg2 = match CHANNEL.send_message(&ctx.http, |m| {
-        m.content(format!("<:{}:{}>", em.name, em.id));
-        m.reactions(vec![ReactionType::from("👍"), ReactionType::from("👎")])
-    }) {
-        Ok(x) => x,
-        Err(why) => {
-            dm_user(http, msg, "Discord error, pls try again later.");
-            return Err(CommandError(format!("Sending msg one: {:?}", why)));
-        }
+    let (vote_msg, carried) = match MESSAGES.read().unwrap().get(&id) {
+        Some(emsg) => (emsg.messages[1].clone(), emsg.carried),
+        None => return,
+    };
+    // without the seeded reactions of the bot
+    let (pos, neg) = match votes(http, &vote_msg) {
+        Ok((pos, neg)) => (
+            pos.saturating_sub(1) + carried.0,
+            neg.saturating_sub(1) + carried.1,
+        ),
+        Err(why) => return log!("Could not fetch the votes of {}: {:?}", id, why),
     };
 
-    MESSAGES.write().unwrap().insert(
-        bot_msg2.id.clone(),
-        EmoteMessage {
-            messages: [bot_msg1, bot_msg2],
-            emote,
-        },
+    let mut messages = MESSAGES.write().unwrap();
+    let emsg = match messages.get_mut(&id) {
+        Some(x) => x,
+        None => return,
+    };
+    let link = format!(
+        "https://discord.com/channels/{}/{}/{}",
+        GUILD, vote_msg.channel_id, vote_msg.id
     );
-    user.counter += 1;
-
-    if let Err(why) = em.delete(ctx) {
-        dm_user(http, msg, "Internal error, pls DM Infi#8527.");
-        return Err(CommandError(format!("Deleting emote: {:?}", why)));
+    let mut alerts = Vec::new();
+    if score.is_some_and(|score| pos as i64 - neg as i64 >= score)
+        && emsg.alerted.insert(Alert::Winner)
+    {
+        alerts.push(format!(
+            "**{}** has 👍 {} 👎 {}, accept it early with accept {}: {}",
+            emsg.emote.name, pos, neg, id, link
+        ));
+    }
+    if ratio.is_some_and(|ratio| neg as f64 >= ratio * pos.max(1) as f64)
+        && emsg.alerted.insert(Alert::Loser)
+    {
+        alerts.push(format!(
+            "**{}** has 👍 {} 👎 {}, remove it early with remove {}: {}",
+            emsg.emote.name, pos, neg, id, link
+        ));
+    }
+    if alerts.is_empty() {
+        return;
     }
 
-    Ok(())
+    store::log(Op::Suggestion(Box::new(emsg.clone())));
+    drop(messages);
+    for content in alerts {
+        send(http.clone(), channel, &content);
+    }
+    store::save(snapshot);
 }
 
-#[command]
-#[only_in(guilds)]
-#[allowed_roles("Moderator", "admin")]
-fn stats(ctx: &mut Context, msg: &Message) -> CommandResult {
-    let http = ctx.http.clone();
-    let messages = MESSAGES.read().unwrap();
+/// DMs the authors whose suggestions reached a milestone since the last check, once per
+/// milestone and not to those who turned it off with `dms`. Returns whether one was reached.
+fn milestones(http: &Http) -> serenity::Result<bool> {
+    let (upvotes, quorum, top) = {
+        let config = CONFIG.read().unwrap();
+        (
+            config.milestone_upvotes,
+            config.milestone_quorum,
+            config.milestone_top,
+        )
+    };
+    let quiet = USERS
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(_, u)| u.quiet)
+        .map(|(id, _)| *id)
+        .collect::<HashSet<_>>();
 
-    let content: String = messages
+    let mut messages = MESSAGES.write().unwrap();
+    // without the seeded reactions of the bot, ranked like the leaderboard
+    let mut tallies = messages
         .values()
         .collect::<Vec<_>>()
         .into_par_iter()
-        .filter_map(|emsg: &EmoteMessage| {
-            emsg.messages[1]
-                .channel_id
-                .message(&http, emsg.messages[1].id)
-                .ok()
-                .map(|m| (emsg, m))
-        })
-        .map(|(emsg, umsg)| {
-            let (pos, neg) =
-                umsg.reactions
-                    .iter()
-                    .fold((0, 0), |(pos, neg), r| match &r.reaction_type {
-                        ReactionType::Unicode(n) if n == "👍" => (r.count, neg),
-                        ReactionType::Unicode(n) if n == "👎" => (pos, r.count),
-                        _ => (pos, neg),
-                    });
+        .map(|emsg| {
+            votes(http, &emsg.messages[1]).map(|(pos, neg)| {
+                (
+                    emsg.messages[1].id,
+                    pos.saturating_sub(1) + emsg.carried.0,
+                    neg.saturating_sub(1) + emsg.carried.1,
+                )
+            })
+        })
+        .collect::<serenity::Result<Vec<_>>>()?;
+    tallies.sort_by_key(|(_, pos, neg)| -(*pos as i64 - *neg as i64));
+    // with only a few suggestions everyone would be on top
+    let ranked = tallies.len() > top;
+
+    let mut dms = Vec::new();
+    for (rank, (id, pos, neg)) in tallies.into_iter().enumerate() {
+        let emsg = match messages.get_mut(&id) {
+            Some(x) => x,
+            None => continue,
+        };
+        let mut reached = Vec::new();
+        if pos >= upvotes && emsg.milestones.insert(Milestone::Upvotes) {
+            reached.push(format!("got its first {} 👍", upvotes));
+        }
+        if let Some(quorum) = quorum.filter(|q| pos + neg >= *q) {
+            if emsg.milestones.insert(Milestone::Quorum) {
+                reached.push(format!("reached the quorum of {} votes", quorum));
+            }
+        }
+        if ranked && rank < top && pos > neg && emsg.milestones.insert(Milestone::Top) {
+            reached.push(format!("is in the top {} at rank {}", top, rank + 1));
+        }
+        if reached.is_empty() {
+            continue;
+        }
+
+        store::log(Op::Suggestion(Box::new(emsg.clone())));
+        if !quiet.contains(&emsg.emote.author_id) {
+            dms.push((
+                emsg.emote.author_id,
+                format!(
+                    "Your suggestion **{}** {}: https://discord.com/channels/{}/{}/{}",
+                    emsg.emote.name,
+                    reached.join(" and "),
+                    GUILD,
+                    emsg.messages[1].channel_id,
+                    id
+                ),
+            ));
+        }
+    }
+    drop(messages);
+
+    let reached = !dms.is_empty();
+    for (author, content) in dms {
+        let sent = rest::post("Sending milestone", || {
+            author.create_dm_channel(http)?.say(http, &content)
+        });
+        if let Err(why) = sent {
+            log!("Could not DM {}: {:?}", author, why);
+        }
+    }
+
+    Ok(reached)
+}
+
+/// Slash command versions of `remove` and `accept`, the autocomplete lists the pending
+/// suggestions so nobody has to copy message IDs.
+fn on_interaction(
+    ctx: &Context,
+    interaction: slash::Interaction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if interaction.guild_id != Some(GUILD) {
+        return Ok(());
+    }
+
+    match interaction.kind {
+        slash::AUTOCOMPLETE => {
+            let query = names::fold(interaction.focused().unwrap_or(""));
+            let messages = MESSAGES.read().unwrap();
+            let mut choices = messages
+                .iter()
+                .filter(|(_, m)| names::fold(&m.emote.name).contains(&query))
+                .map(|(id, m)| {
+                    // the end of the id is enough to tell suggestions with the same name apart
+                    let id = id.0.to_string();
+                    let short = &id[id.len().saturating_sub(6)..];
+                    (format!("{} (…{})", m.emote.name, short), id)
+                })
+                .collect::<Vec<_>>();
+            choices.sort();
+
+            slash::autocomplete(&interaction, choices)
+        }
+        slash::COMMAND => {
+            let member = match &interaction.member {
+                Some(x) => x,
+                None => return Ok(()),
+            };
+            log!(
+                "{} ({})   Slash command {:?}: {:?}",
+                member.user.username,
+                member.user.id,
+                interaction.name(),
+                interaction.option("id")
+            );
+
+            let command = interaction.name().unwrap_or_default();
+            if !permissions::allowed(command, member.user.id, &member.roles) {
+                return slash::reply(&interaction, "You may not do this.");
+            }
+            slash::defer(&interaction)?;
+
+            let id = interaction.option("id").and_then(parse_id);
+            let mut messages = MESSAGES.write().unwrap();
+            let result = match (interaction.name(), id) {
+                (_, None) => Err("Missing id."),
+                (Some("remove"), Some(id)) => {
+                    let name = messages.get(&id).map(|m| m.emote.name.clone());
+                    trash_suggestion(&ctx.http, &mut messages, id).map(|_| {
+                        audit(
+                            ctx.http.clone(),
+                            &member.user.username,
+                            member.user.id,
+                            "removed",
+                            format!("{} ({})", name.unwrap_or_default(), short_id(id)),
+                        )
+                    })
+                }
+                (Some("accept"), Some(id)) => {
+                    let name = messages.get(&id).map(|m| m.emote.name.clone());
+                    accept_suggestion(&ctx.http, &mut messages, id).map(|_| {
+                        audit(
+                            ctx.http.clone(),
+                            &member.user.username,
+                            member.user.id,
+                            "accepted",
+                            format!("{} ({})", name.unwrap_or_default(), short_id(id)),
+                        )
+                    })
+                }
+                _ => Err("Unknown command."),
+            };
+
+            slash::edit_response(&interaction, result.err().unwrap_or("Done"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[group]
+#[commands(
+    add,
+    add_variants,
+    add_sticker,
+    add_sound,
+    rename,
+    withdraw,
+    status,
+    dms,
+    mine,
+    search,
+    list,
+    phase,
+    ranking,
+    guild_config,
+    perm,
+    stats,
+    export,
+    remove,
+    undo,
+    restore_suggestion,
+    remove_many,
+    remove_author,
+    import_history,
+    revote,
+    resync,
+    close,
+    modlog,
+    simulate,
+    preview_announcement,
+    diag,
+    botstats,
+    accept,
+    pick
+)]
+struct General;
+
+#[help]
+#[individual_command_tip = "If you want more information about a specific command, just pass the command as argument."]
+#[command_not_found_text = "Could not find: `{}`."]
+#[max_levenshtein_distance(3)]
+#[lacking_permissions = "Hide"]
+fn my_help(
+    context: &mut Context,
+    msg: &Message,
+    args: Args,
+    help_options: &'static HelpOptions,
+    groups: &[&'static CommandGroup],
+    owners: HashSet<UserId>,
+) -> CommandResult {
+    help_commands::with_embeds(context, msg, args, help_options, groups, owners)
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("FeelsGoodMan [image as attachment]")]
+#[example("FeelsGoodMan filter:nearest [image as attachment]")]
+#[example("FeelsGoodMan FeelsBadMan [two images as attachments]")]
+#[example("FeelsGoodMan --force-suggested [image as attachment]")]
+fn add(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut users = USERS.write().unwrap();
+
+    log!("{}   Args for stats: {}", msg.author.name, &args.message());
+    let user = users.entry(msg.author.id).or_insert(User {
+        name: msg.author.name.clone(),
+        counter: 0,
+        quiet: false,
+    });
+
+    check_slots(http.clone(), msg, user)?;
+
+    if let Err(why) = validate::user(&msg.author) {
+        return dm_user_err(http, msg, &why);
+    }
+
+    let force_suggested = take_flag(&mut args, FORCE_SUGGESTED);
+    // check for the names, every attachment gets the name at the same position
+    let mut names = Vec::new();
+    while let Some(name) = args.current().filter(|a| !a.contains(':')) {
+        names.push(name.to_string());
+        args.advance();
+    }
+
+    if names.is_empty() {
+        return dm_user_err(http, msg, "No name found.");
+    }
+
+    if let Some(why) = names
+        .iter()
+        .find_map(|n| validate::name(Kind::Emoji, n).err())
+    {
+        return dm_user_err(http, msg, &why);
+    }
+
+    // a second emote of the same name would be ambiguous in the picker
+    free_names(http.clone(), msg, Kind::Emoji, &mut names, force_suggested)?;
+
+    let remaining = slots_left(user) as usize;
+    if names.len() > remaining {
+        return dm_user_err(
+            http,
+            msg,
+            &format!("You can only post {} more suggestions.", remaining),
+        );
+    }
+
+    if msg.attachments.is_empty() {
+        return dm_user_err(http, msg, "No attachment found.");
+    }
+
+    if msg.attachments.len() != names.len() {
+        return dm_user_err(http, msg, "Every name needs exactly one attachment.");
+    }
+
+    // animated WebPs only turn into GIFs after the download, until then they count as static
+    let gifs = msg
+        .attachments
+        .iter()
+        .filter(|a| extension(&a.filename).map(str::to_lowercase).as_deref() == Some("gif"))
+        .count();
+    for (animated, adding) in [(true, gifs), (false, names.len() - gifs)].iter() {
+        if *adding > 0 {
+            check_pool(http.clone(), msg, *animated, *adding)?;
+        }
+    }
+
+    let mut options = match Options::parse(&mut args, msg.guild_id) {
+        Ok(x) => x,
+        Err(why) => return dm_user_err(http, msg, &why),
+    };
+
+    if options.adjust && msg.attachments.len() != 1 {
+        return dm_user_err(http, msg, "mode:adjust works with one image at a time.");
+    }
+
+    for name in names.iter() {
+        let submission = validate::Submission {
+            kind: Kind::Emoji,
+            name,
+            author: &msg.author,
+            category: options.category.as_deref(),
+            tally: user.counter,
+        };
+        if let Err(why) = validate::submission(&submission) {
+            return dm_user_err(http, msg, &why);
+        }
+    }
+
+    // check for best size of emotes (128x128px), downloading happens in the job
+    for attachment in msg.attachments.iter() {
+        check_attachment(http.clone(), msg, attachment, min_image_size())?;
+        warn_upscale(http.clone(), msg, attachment, min_image_size());
+    }
+
+    // the slots are taken right away, a failed job gives them back
+    user.counter += names.len() as u64;
+    store::add(Counter::Slots(msg.author.id), names.len() as i64);
+    store::log(Op::User(msg.author.id, user.clone()));
+    drop(users);
+
+    if options.adjust {
+        match adjust_crop(http.clone(), msg, &msg.attachments[0], &options) {
+            Ok(window) => options.window = window,
+            Err(why) => {
+                give_back(msg.author.id, 1);
+                return Err(why);
+            }
+        }
+    }
+
+    let ahead = jobs::push(msg.clone(), names, options);
+    if ahead > 0 {
+        dm_user(
+            http,
+            msg,
+            &format!("Queued, {} submissions are processed before yours.", ahead),
+        );
+    }
+
+    Ok(())
+}
+
+/// Refuses `names` that are `taken_names` with free variations, with `force` they get the first
+/// one instead.
+fn free_names(
+    http: Arc<Http>,
+    msg: &Message,
+    kind: Kind,
+    names: &mut [String],
+    force: bool,
+) -> CommandResult {
+    let mut taken = taken_names(&http, kind);
+    let mut renamed = Vec::new();
+    for name in names.iter_mut() {
+        if !taken.contains(&names::fold(name)) {
+            taken.insert(names::fold(name));
+            continue;
+        }
+
+        let free = name_suggestions(name, kind, &taken);
+        match (force, free.first()) {
+            (true, Some(first)) => {
+                renamed.push(format!("{} as {}", name, first));
+                *name = first.clone();
+                taken.insert(names::fold(name));
+            }
+            (_, Some(_)) => {
+                return dm_user_err(
+                    http,
+                    msg,
+                    &format!(
+                        "{} is already taken, free would be {}. Add {} to submit it as {}.",
+                        name,
+                        free.join(", "),
+                        FORCE_SUGGESTED,
+                        free[0]
+                    ),
+                )
+            }
+            (_, None) => {
+                return dm_user_err(http, msg, &format!("{} is already taken.", name));
+            }
+        }
+    }
+    if !renamed.is_empty() {
+        dm_user(
+            http.clone(),
+            msg,
+            &format!("Taken names, submitted {}.", renamed.join(", ")),
+        );
+    }
+    Ok(())
+}
+
+/// Folded names of the pending suggestions of `kind` and, for emotes, of the emojis of the
+/// guild.
+fn taken_names(http: &Http, kind: Kind) -> HashSet<String> {
+    let mut taken = QUEUE
+        .read()
+        .unwrap()
+        .values()
+        .chain(MESSAGES.read().unwrap().values())
+        .filter(|m| m.emote.kind == kind)
+        .map(|m| names::fold(&m.emote.name))
+        .collect::<HashSet<_>>();
+    taken.extend(
+        VARIANTS
+            .read()
+            .unwrap()
+            .values()
+            .map(|p| names::fold(&p.name)),
+    );
+
+    if kind == Kind::Emoji {
+        match rest::call("Fetching guild", || GUILD.to_partial_guild(http)) {
+            Ok(guild) => taken.extend(guild.emojis.values().map(|e| names::fold(&e.name))),
+            Err(why) => log!("Could not fetch the emojis: {:?}", why),
+        }
+    }
+    taken
+}
+
+/// Free variations of `name` that pass `validate`: the other casing style, then numbers.
+fn name_suggestions(name: &str, kind: Kind, taken: &HashSet<String>) -> Vec<String> {
+    let camel = name
+        .split('_')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let mut chars = p.chars();
+            chars.next().map_or_else(String::new, |c| {
+                c.to_uppercase().chain(chars).collect::<String>()
+            })
+        })
+        .collect::<String>();
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if i > 0 && c.is_uppercase() && !snake.ends_with('_') {
+            snake.push('_');
+        }
+        snake.push(c);
+    }
+
+    let mut candidates = vec![camel.clone(), snake.clone()];
+    for base in [name, &camel[..], &snake[..]].iter() {
+        candidates.extend((2..10).map(|n| format!("{}{}", base, n)));
+        candidates.extend((2..10).map(|n| format!("{}_{}", base, n)));
+    }
+
+    let mut free = Vec::new();
+    for candidate in candidates {
+        if free.len() == NAME_SUGGESTIONS {
+            break;
+        }
+        let key = names::fold(&candidate);
+        if candidate != name
+            && !taken.contains(&key)
+            && !free.iter().any(|f: &String| names::fold(f) == key)
+            && validate::name(kind, &candidate).is_ok()
+        {
+            free.push(candidate);
+        }
+    }
+    free
+}
+
+/// Identifies a submitted file of an author by its `file_hash`, so a resent `add` is caught
+/// whatever Discord named or sized the attachment.
+fn submission_key(author: UserId, data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    author.hash(&mut hasher);
+    file_hash(data).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Points the author to the suggestions they just submitted, or tells them they are still being
+/// processed.
+fn already_submitted(author: UserId, names: &[String]) -> String {
+    let messages = MESSAGES.read().unwrap();
+
+    let found = names
+        .iter()
+        .map(|name| {
+            match messages
+                .values()
+                .find(|m| m.emote.author_id == author && &m.emote.name == name)
+            {
+                Some(emsg) => format!(
+                    "\n{}: https://discord.com/channels/{}/{}/{}",
+                    name, GUILD.0, emsg.messages[1].channel_id.0, emsg.messages[1].id.0
+                ),
+                None => format!("\n{}: still being processed", name),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    format!("You already submitted this a moment ago:{}", found.concat())
+}
+
+/// Checks the downloaded `images` of an `add` against the submissions of the last
+/// `DUPLICATE_SECONDS`, new ones are remembered.
+fn check_duplicates(
+    http: &Arc<Http>,
+    msg: &Message,
+    names: &[String],
+    images: &[Vec<u8>],
+) -> CommandResult {
+    let mut recent = RECENT.write().unwrap();
+    recent.retain(|_, r| r.at > Utc::now() - Duration::seconds(DUPLICATE_SECONDS));
+    let keys = images
+        .iter()
+        .map(|img| submission_key(msg.author.id, img))
+        .collect::<Vec<_>>();
+    let known = keys
+        .iter()
+        .filter_map(|k| recent.get(k))
+        .collect::<Vec<_>>();
+    let resent = known.iter().any(|r| r.message == msg.id);
+    let known = known
+        .into_iter()
+        .map(|r| r.name.clone())
+        .collect::<Vec<_>>();
+
+    if !known.is_empty() && known.len() == keys.len() {
+        drop(recent);
+        // an event Discord sent again leaves the message to the first job
+        if !resent {
+            delete_original(http.clone(), msg)?;
+        }
+        return dm_user_err(http.clone(), msg, &already_submitted(msg.author.id, &known));
+    }
+    if !known.is_empty() {
+        return dm_user_err(
+            http.clone(),
+            msg,
+            &format!(
+                "{} was just submitted, post the other images without it.",
+                known.join(", ")
+            ),
+        );
+    }
+    for (key, name) in keys.into_iter().zip(names.iter()) {
+        recent.insert(
+            key,
+            Recent {
+                name: name.clone(),
+                message: msg.id,
+                at: Utc::now(),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Worker side of `add`, picks up at the last finished step when the bot was restarted.
+fn run_job(http: &Arc<Http>, job: &mut jobs::Job) -> CommandResult {
+    let msg = job.msg.clone();
+    let dir = jobs::files(job);
+
+    if !job.downloaded {
+        if let Err(why) = fs::create_dir_all(&dir) {
+            dm_user(http.clone(), &msg, "Internal error, pls DM Infi#8527.");
+            return Err(CommandError(format!("Creating job dir: {:?}", why)));
+        }
+
+        let mut images = Vec::new();
+        for attachment in msg.attachments.iter() {
+            images.push(download_image(http.clone(), &msg, attachment, min_image_size(), 128)?.0);
+        }
+        // a duplicate fails the job, that gives its slots back
+        check_duplicates(http, &msg, &job.names, &images)?;
+
+        // the slots of the job are taken already
+        let tally = USERS
+            .read()
+            .unwrap()
+            .get(&msg.author.id)
+            .map_or(0, |u| u.counter)
+            .saturating_sub(job.names.len() as u64);
+        for (n, (name, img)) in job.names.iter().zip(images.iter()).enumerate() {
+            check_cooldown(http.clone(), &msg, name, img)?;
+            let submission = validate::Submission {
+                kind: Kind::Emoji,
+                name,
+                author: &msg.author,
+                category: job.options.category.as_deref(),
+                tally,
+            };
+            if let Err(why) = validate::image(&submission, img) {
+                return dm_user_err(http.clone(), &msg, &why);
+            }
+
+            if let Err(why) = fs::write(dir.join(n.to_string()), img) {
+                dm_user(http.clone(), &msg, "Internal error, pls DM Infi#8527.");
+                return Err(CommandError(format!("Saving download: {:?}", why)));
+            }
+        }
+
+        delete_original(http.clone(), &msg)?;
+        job.downloaded = true;
+        jobs::update(job);
+    }
+
+    for n in job.posted.len() + job.queued.len()..job.names.len() {
+        let name = job.names[n].clone();
+        let img = match fs::read(dir.join(n.to_string())) {
+            Ok(x) => x,
+            Err(why) => {
+                dm_user(http.clone(), &msg, "Internal error, pls DM Infi#8527.");
+                return Err(CommandError(format!("Reading download: {:?}", why)));
+            }
+        };
+        // the format was checked after the download
+        let format = image::guess_format(&img).unwrap_or(ImageFormat::Png);
+
+        let buf = process_emote(http.clone(), &msg, &name, &img, format, &job.options)?;
+        let emote = Emote {
+            name,
+            author: msg.author.name.to_string(),
+            author_id: msg.author.id,
+            kind: Kind::Emoji,
+            animated: format == ImageFormat::Gif,
+            hash: file_hash(&img),
+            legacy: false,
+            category: job.options.category.clone(),
+            image: None,
+        };
+
+        let filename = format!(
+            "{}.{}",
+            emote.name,
+            if emote.animated { "gif" } else { "png" }
+        );
+        match submit(http, &msg, emote, &buf, &filename)? {
+            Submitted::Voting(emsg) => {
+                receipt(http, &emsg);
+                job.posted.push(emsg);
+            }
+            Submitted::Queued(emsg) => {
+                dm_user(
+                    http.clone(),
+                    &msg,
+                    &format!(
+                        "{} waits for the mods, you get its ID once it's in the voting.",
+                        emsg.emote.name
+                    ),
+                );
+                job.queued.push(emsg);
+            }
+        }
+        jobs::update(job);
+    }
+
+    Ok(())
+}
+
+/// Turns one downloaded image into the emote file, a GIF for animated and a PNG for static
+/// emotes.
+fn process_emote(
+    http: Arc<Http>,
+    msg: &Message,
+    name: &str,
+    img: &[u8],
+    format: ImageFormat,
+    options: &Options,
+) -> Result<Vec<u8>, CommandError> {
+    let buf = if format == ImageFormat::Gif {
+        let auto_trim = CONFIG.read().unwrap().auto_trim;
+        let data = img.to_vec();
+        let (filter, aspect, window) = (options.filter, options.aspect, options.window);
+        match pool::run(move || animated::optimize(&data, 128, auto_trim, filter, aspect, window)) {
+            Ok(Ok(buf)) => buf,
+            Ok(Err(animated::Error::TooLarge(size))) => {
+                log!("Animated emote {} stays at {} bytes", name, size);
+                return dm_user_err(
+                    http,
+                    msg,
+                    "Animated emote can't be shrunk below 256KB, try fewer frames or colors.",
+                );
+            }
+            Ok(Err(why)) => {
+                dm_user(http, msg, "Error processing image.");
+                return Err(CommandError(format!("Processing gif: {}", why)));
+            }
+            Err(why) => return pool_err(http, msg, why),
+        }
+    } else {
+        render_static(
+            http.clone(),
+            msg,
+            img,
+            format,
+            128,
+            animated::MAX_BYTES,
+            options,
+        )?
+    };
+
+    // only a warning, the vote decides in the end
+    if let Ok(img) = image::load_from_memory(&buf) {
+        let issues = legibility::check(&img.to_rgba8());
+        if !issues.is_empty() {
+            dm_user(
+                http.clone(),
+                msg,
+                &format!(
+                    "Heads-up, {} might be hard to read in chat: {}.",
+                    name,
+                    issues.join(", ")
+                ),
+            );
+        }
+    }
+
+    Ok(buf)
+}
+
+/// Decodes, trims, resizes and compresses a static image on the processing pool, a palette is
+/// only used when the lossless PNG is above `max_bytes` or `png_quality` asks for it.
+fn render_static(
+    http: Arc<Http>,
+    msg: &Message,
+    img: &[u8],
+    format: ImageFormat,
+    size: u32,
+    max_bytes: usize,
+    options: &Options,
+) -> Result<Vec<u8>, CommandError> {
+    let data = img.to_vec();
+    let (filter, aspect, window) = (options.filter, options.aspect, options.window);
+    let (auto_trim, quality) = {
+        let config = CONFIG.read().unwrap();
+        (config.auto_trim, config.png_quality)
+    };
+
+    let result = pool::run(move || {
+        let img = image::load_from_memory_with_format(&data, format)
+            .map_err(|why| format!("Processing image: {:?}", why))?;
+        let img = match auto_trim {
+            true => trim::trim(&img),
+            false => img,
+        };
+        compress::optimize(
+            &resize::square(&img, size, filter, aspect, window).to_rgba8(),
+            quality,
+            max_bytes,
+        )
+        .map_err(|why| format!("Compressing image: {:?}", why))
+    });
+
+    match result {
+        Ok(Ok(buf)) => Ok(buf),
+        Ok(Err(why)) => {
+            dm_user(http, msg, "Error processing image.");
+            Err(CommandError(why))
+        }
+        Err(why) => pool_err(http, msg, why),
+    }
+}
+
+fn pool_err<T>(http: Arc<Http>, msg: &Message, why: pool::Error) -> Result<T, CommandError> {
+    match why {
+        pool::Error::Busy => dm_user_err(
+            http,
+            msg,
+            "A lot of images are being processed right now, try again in a few minutes.",
+        ),
+        pool::Error::Timeout => {
+            dm_user_err(http, msg, "Processing took too long, try a smaller image.")
+        }
+        pool::Error::Panicked => {
+            dm_user(http, msg, "Error processing image.");
+            Err(CommandError(why.to_string()))
+        }
+    }
+}
+
+/// Returns `n` suggestion slots to `author`.
+fn give_back(author: UserId, n: u64) {
+    let mut users = USERS.write().unwrap();
+    if let Some(user) = users.get_mut(&author) {
+        let counter = user.counter.saturating_sub(n);
+        store::add(Counter::Slots(author), counter as i64 - user.counter as i64);
+        user.counter = counter;
+        store::log(Op::User(author, user.clone()));
+    }
+}
+
+/// Lets the author move the crop of a submission with reactions on a preview in their DMs.
+/// ✅, or no reaction within `ADJUST_SECONDS`, keeps the current crop.
+fn adjust_crop(
+    http: Arc<Http>,
+    msg: &Message,
+    attachment: &Attachment,
+    options: &Options,
+) -> Result<Window, CommandError> {
+    let data = fetch(http.clone(), msg, attachment)?;
+    let img = match image::load_from_memory(&data) {
+        Ok(x) => x,
+        Err(_) => return dm_user_err(http, msg, "Attachment is not an image."),
+    };
+    // the preview has to match what the job renders later
+    let img = Arc::new(match CONFIG.read().unwrap().auto_trim {
+        true => trim::trim(&img),
+        false => img,
+    });
+
+    let dm = match rest::call("Opening DM", || msg.author.create_dm_channel(&http)) {
+        Ok(x) => x,
+        Err(why) => {
+            send(
+                http,
+                msg.channel_id,
+                "mode:adjust needs DMs from server members.",
+            );
+            return Err(CommandError(format!("Opening DM: {:?}", why)));
+        }
+    };
+
+    let mut window = Window::default();
+    for _ in 0..ADJUST_ROUNDS {
+        let (img, filter) = (img.clone(), options.filter);
+        let preview = pool::run(move || {
+            let mut buf = Vec::new();
+            resize::square(&img, 128, filter, Aspect::Crop, window)
+                .write_to(&mut buf, ImageOutputFormat::Png)
+                .map(|_| buf)
+        });
+        let preview = match preview {
+            Ok(Ok(buf)) => buf,
+            Ok(Err(why)) => {
+                dm_user(http, msg, "Error processing image.");
+                return Err(CommandError(format!("Rendering preview: {:?}", why)));
+            }
+            Err(why) => return pool_err(http, msg, why),
+        };
+
+        let prompt = discord_post(&http, msg, "Sending preview", || {
+            dm.send_message(&http, |m| {
+                m.content(format!(
+                    "Move the crop with the arrows, 🔍 zooms in or out and ✅ keeps it. Without a \
+                 reaction within {} seconds the current crop is used.",
+                    ADJUST_SECONDS
+                ));
+                m.add_file((&preview[..], "preview.png"));
+                m.reactions(ADJUST.iter().map(|e| ReactionType::from(*e)))
+            })
+        })?;
+
+        // only the author and the bot can react in DMs, the bot's own reaction counts as one
+        let choice = (0..ADJUST_SECONDS / 2).find_map(|_| {
+            thread::sleep(time::Duration::from_secs(2));
+            let reactions = rest::call("Fetching message", || dm.message(&http, prompt.id))
+                .ok()?
+                .reactions;
+            let picked = reactions.iter().find(|r| r.count > 1)?;
+            let picked = picked.reaction_type.to_string();
+            ADJUST
+                .iter()
+                .position(|e| e.trim_end_matches('\u{fe0f}') == picked.trim_end_matches('\u{fe0f}'))
+        });
+        if let Err(why) = rest::call("Deleting message", || prompt.delete(&http)) {
+            log!("Could not delete preview {}: {:?}", prompt.id, why);
+        }
+
+        // a tenth of the image per step, `Window` keeps the square inside
+        let step = 0.1;
+        match choice {
+            Some(0) => window.x = (window.x - step).max(0.0),
+            Some(1) => window.x = (window.x + step).min(1.0),
+            Some(2) => window.y = (window.y - step).max(0.0),
+            Some(3) => window.y = (window.y + step).min(1.0),
+            Some(4) => window.zoom = if window.zoom > 1.0 { 1.0 } else { 1.5 },
+            _ => break,
+        }
+    }
+
+    Ok(window)
+}
+
+/// Gateway side of `add`, the finished suggestions go into the voting and the author gets the
+/// slots of the failed ones back.
+fn finish_job(job: jobs::Job) {
+    if job.failed {
+        give_back(
+            job.msg.author.id,
+            (job.names.len() - job.posted.len() - job.queued.len()) as u64,
+        );
+    }
+
+    let mut queue = QUEUE.write().unwrap();
+    for emsg in job.queued {
+        enqueue(&mut queue, emsg);
+    }
+    drop(queue);
+
+    let mut messages = MESSAGES.write().unwrap();
+    for emsg in job.posted {
+        add_suggestion(&mut messages, emsg);
+    }
+    drop(messages);
+    store::save(snapshot);
+}
+
+/// Everything `store` keeps, taken in the usual lock order.
+fn snapshot() -> store::State {
+    store::State {
+        users: USERS.read().unwrap().clone(),
+        queue: QUEUE.read().unwrap().clone(),
+        suggestions: MESSAGES.read().unwrap().clone(),
+        trash: REMOVED.read().unwrap().clone(),
+        variants: VARIANTS.read().unwrap().clone(),
+        runoffs: RUNOFFS.read().unwrap().clone(),
+        replacements: REPLACEMENTS.read().unwrap().clone(),
+        pair: PAIR.read().unwrap().clone(),
+        round: ROUND.read().unwrap().clone(),
+        history: HISTORY.read().unwrap().clone(),
+        crowned: CROWNED.read().unwrap().clone(),
+        guilds: GUILDS.read().unwrap().clone(),
+        rotation: ROTATION.read().unwrap().clone(),
+        trials: TRIALS.read().unwrap().clone(),
+        emoji_uses: EMOJI_USES.read().unwrap().clone(),
+        modlog: MODLOG.read().unwrap().clone(),
+        version: store::VERSION,
+    }
+}
+
+fn restore(state: store::State) {
+    *USERS.write().unwrap() = state.users;
+    *QUEUE.write().unwrap() = state.queue;
+    *MESSAGES.write().unwrap() = state.suggestions;
+    *REMOVED.write().unwrap() = state.trash;
+    *VARIANTS.write().unwrap() = state.variants;
+    *RUNOFFS.write().unwrap() = state.runoffs;
+    *REPLACEMENTS.write().unwrap() = state.replacements;
+    *PAIR.write().unwrap() = state.pair;
+    *ROUND.write().unwrap() = state.round;
+    *HISTORY.write().unwrap() = state.history;
+    *CROWNED.write().unwrap() = state.crowned;
+    *GUILDS.write().unwrap() = state.guilds;
+    *ROTATION.write().unwrap() = state.rotation;
+    *TRIALS.write().unwrap() = state.trials;
+    *EMOJI_USES.write().unwrap() = state.emoji_uses;
+    *MODLOG.write().unwrap() = state.modlog;
+}
+
+/// Where `submit` put a new suggestion.
+enum Submitted {
+    Voting(EmoteMessage),
+    /// waits in the `mod_channel` for ✅ or ❌
+    Queued(EmoteMessage),
+}
+
+/// Posts a processed suggestion into the voting, or into the approval queue with
+/// `approval_queue` or when the `nsfw` check flags it. The caller keeps it in `MESSAGES` or
+/// `QUEUE`.
+fn submit(
+    http: &dyn Discord,
+    msg: &Message,
+    emote: Emote,
+    buf: &[u8],
+    filename: &str,
+) -> Result<Submitted, CommandError> {
+    let (approval_queue, mod_channel) = {
+        let config = CONFIG.read().unwrap();
+        (config.approval_queue, config.mod_channel)
+    };
+    let reason = match emote.kind {
+        Kind::Sound => None,
+        _ => screen(buf),
+    }
+    .or_else(|| Some(String::from("approval queue")).filter(|_| approval_queue));
+
+    let posted = match (reason, mod_channel) {
+        (Some(reason), Some(channel)) => {
+            queue(http, channel, emote, buf, filename, &reason).map(Submitted::Queued)
+        }
+        (Some(reason), None) => {
+            log!(
+                "{} needs approval ({}) but there is no mod_channel",
+                emote.name,
+                reason
+            );
+            let content = "This can't be posted, pls ask a mod.";
+            tell(http, msg, content);
+            return Err(CommandError(content.to_string()));
+        }
+        (None, _) => publish(http, emote, buf, filename).map(Submitted::Voting),
+    };
+
+    posted.map_err(|why| {
+        tell(http, msg, "Discord error, pls try again later.");
+        CommandError(why)
+    })
+}
+
+/// `dm_user` through `Discord`, for the pipeline that also runs against the mock.
+fn tell(http: &dyn Discord, msg: &Message, content: &str) {
+    if let Err(why) = http.dm(&msg.author, content) {
+        log!("Could not send message to {}: {:?}", msg.author, why);
+        if let Err(why) = http.post(msg.channel_id, content, Vec::new(), Vec::new()) {
+            log!("Could not answer in {}: {:?}", msg.channel_id, why);
+        }
+    }
+}
+
+/// Why an image has to wait for the mods according to the `nsfw` check, a failed check counts as
+/// flagged so nothing unchecked gets out.
+fn screen(buf: &[u8]) -> Option<String> {
+    let nsfw = CONFIG.read().unwrap().nsfw.clone()?;
+
+    match nsfw.check(buf) {
+        Ok(score) if score >= nsfw.threshold => Some(format!("NSFW score {:.2}", score)),
+        Ok(_) => None,
+        Err(why) => {
+            log!("NSFW check failed: {}", why);
+            Some(String::from("the NSFW check failed"))
+        }
+    }
+}
+
+/// Posts a suggestion into its voting channel. Emotes get uploaded for a moment, so the vote
+/// message can show them.
+fn publish(
+    http: &dyn Discord,
+    emote: Emote,
+    buf: &[u8],
+    filename: &str,
+) -> Result<EmoteMessage, String> {
+    let emote = Emote {
+        image: images::put(buf),
+        ..emote
+    };
+    let preview = match emote.kind {
+        Kind::Emoji => Some(
+            http.upload_emoji(
+                GUILD,
+                &emote.name,
+                &format!(
+                    "data:image/{};base64,{}",
+                    if emote.animated { "gif" } else { "png" },
+                    base64::encode(buf)
+                ),
+            )
+            .map_err(|why| format!("Creating emote: {:?}", why))?,
+        ),
+        _ => None,
+    };
+
+    let channel = voting_channel(emote.category.as_deref());
+    let bot_msg1 = http
+        .post(channel, &emote.name, vec![(buf, filename)], Vec::new())
+        .map_err(|why| format!("Sending msg one: {:?}", why))?;
+
+    let content = match (&preview, emote.kind) {
+        (Some(em), _) if em.animated => format!("<a:{}:{}>", em.name, em.id),
+        (Some(em), _) => format!("<:{}:{}>", em.name, em.id),
+        (None, Kind::Sticker) => format!("Sticker: {}", emote.name),
+        (None, _) => format!("Sound: {}", emote.name),
+    };
+    let bot_msg2 = http
+        .post(channel, &content, Vec::new(), seed_votes())
+        .map_err(|why| format!("Sending msg two: {:?}", why))?;
+
+    if let Some(em) = preview {
+        http.remove_emoji(GUILD, em.id)
+            .map_err(|why| format!("Deleting emote: {:?}", why))?;
+    }
+
+    Ok(EmoteMessage::new([bot_msg1, bot_msg2], emote))
+}
+
+/// Posts a suggestion for the mods into `channel` behind a spoiler, ✅ on the second message puts
+/// it into the voting and ❌ rejects it.
+fn queue(
+    http: &dyn Discord,
+    channel: ChannelId,
+    emote: Emote,
+    buf: &[u8],
+    filename: &str,
+    reason: &str,
+) -> Result<EmoteMessage, String> {
+    let emote = Emote {
+        image: images::put(buf),
+        ..emote
+    };
+    let file_msg = http
+        .post(
+            channel,
+            &emote.name,
+            vec![(buf, &*format!("{}{}", SPOILER, filename))],
+            Vec::new(),
+        )
+        .map_err(|why| format!("Sending the queued file: {:?}", why))?;
+
+    let content = format!(
+        "**{}** from {} waits for approval ({}), {} puts it into the voting and {} rejects it.",
+        emote.name, emote.author, reason, APPROVE, REJECT
+    );
+    let decision_msg = http
+        .post(
+            channel,
+            &content,
+            Vec::new(),
+            vec![ReactionType::from(APPROVE), ReactionType::from(REJECT)],
+        )
+        .map_err(|why| format!("Sending the decision msg: {:?}", why))?;
+
+    Ok(EmoteMessage::new([file_msg, decision_msg], emote))
+}
+
+/// Puts a suggestion into the approval queue, the change is logged first.
+fn enqueue(queue: &mut HashMap<MessageId, EmoteMessage>, emsg: EmoteMessage) {
+    store::log(Op::Queued(Box::new(emsg.clone())));
+    queue.insert(emsg.messages[1].id, emsg);
+}
+
+/// ✅ or ❌ of a mod on a queued suggestion, the author gets a DM either way.
+fn decide(ctx: &Context, reaction: &Reaction) {
+    if reaction.user_id == ctx.cache.read().user.id {
+        return;
+    }
+    let mut queue = QUEUE.write().unwrap();
+    let id = reaction.message_id;
+    if !queue.contains_key(&id) {
+        return;
+    }
+
+    let member = match rest::call("Fetching member", || GUILD.member(ctx, reaction.user_id)) {
+        Ok(x) => x,
+        Err(why) => return log!("Could not fetch member {}: {:?}", reaction.user_id, why),
+    };
+    if !permissions::allowed("approve", reaction.user_id, &member.roles) {
+        if let Err(why) = rest::call("Deleting reaction", || reaction.delete(ctx)) {
+            log!("Could not delete reaction: {:?}", why);
+        }
+        return;
+    }
+
+    let emsg = &queue[&id];
+    let approved = matches!(&reaction.emoji, ReactionType::Unicode(n) if n == APPROVE);
+    let published = match approved {
+        true => match approve(&ctx.http, emsg) {
+            Ok(x) => Some(x),
+            Err(why) => {
+                log!("Could not approve {}: {}", emsg.emote.name, why);
+                send(
+                    ctx.http.clone(),
+                    reaction.channel_id,
+                    &format!(
+                        "Could not put {} into the voting, try again later.",
+                        emsg.emote.name
+                    ),
+                );
+                return;
+            }
+        },
+        false => None,
+    };
+
+    for m in emsg.messages.iter() {
+        if let Err(why) = rest::call("Deleting message", || m.delete(&ctx.http)) {
+            log!("Could not delete message {}: {:?}", m.id, why);
+        }
+    }
+    store::log(Op::Dequeued(id));
+    let emsg = match queue.remove(&id) {
+        Some(x) => x,
+        None => return,
+    };
+    audit(
+        ctx.http.clone(),
+        &member.user.read().name,
+        reaction.user_id,
+        if approved { "approved" } else { "rejected" },
+        format!("{} ({})", emsg.emote.name, short_id(id)),
+    );
+
+    let content = match published {
+        Some(published) => {
+            receipt(&ctx.http, &published);
+            add_suggestion(&mut MESSAGES.write().unwrap(), published);
+            templates::render(
+                "approved",
+                "{name} got approved and is in the voting now.",
+                &[("name", emsg.emote.name.clone())],
+            )
+        }
+        None => {
+            let content = templates::render(
+                "rejected",
+                "{name} got rejected by the mods.",
+                &[("name", emsg.emote.name.clone())],
+            );
+            finish(id, emsg.emote.clone(), (0, 0), false);
+            content
+        }
+    };
+    if let Err(why) = rest::post("Sending DM", || {
+        emsg.emote
+            .author_id
+            .create_dm_channel(ctx)
+            .and_then(|dm| dm.say(ctx, &content))
+    }) {
+        log!("Could not DM {}: {:?}", emsg.emote.author_id, why);
+    }
+    drop(queue);
+    store::save(snapshot);
+}
+
+/// Posts a queued suggestion into the voting, with the file the mods saw but without the
+/// spoiler.
+fn approve(http: &Http, emsg: &EmoteMessage) -> Result<EmoteMessage, String> {
+    let attachment = emsg.messages[0]
+        .attachments
+        .first()
+        .ok_or("the queued suggestion has no attachment")?;
+    let file =
+        file_of(emsg, attachment).map_err(|why| format!("Attachment download: {:?}", why))?;
+
+    publish(
+        http,
+        emsg.emote.clone(),
+        &file,
+        attachment.filename.trim_start_matches(SPOILER),
+    )
+}
+
+#[command("add-variants")]
+#[only_in(guilds)]
+#[example("FeelsGoodMan [two images as attachments]")]
+#[example("FeelsGoodMan --force-suggested [two images as attachments]")]
+fn add_variants(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut users = USERS.write().unwrap();
+
+    log!(
+        "{}   Args for add-variants: {}",
+        msg.author.name,
+        &args.message()
+    );
+    let user = users.entry(msg.author.id).or_insert(User {
+        name: msg.author.name.clone(),
+        counter: 0,
+        quiet: false,
+    });
+
+    check_slots(http.clone(), msg, user)?;
+
+    if let Err(why) = validate::user(&msg.author) {
+        return dm_user_err(http, msg, &why);
+    }
+
+    let force_suggested = take_flag(&mut args, FORCE_SUGGESTED);
+    // check for the name
+    let name = match args.single::<String>() {
+        Ok(x) => x,
+        Err(_) => return dm_user_err(http, msg, "No name found."),
+    };
+
+    if let Err(why) = validate::name(Kind::Emoji, &name) {
+        return dm_user_err(http, msg, &why);
+    }
+    let mut names = [name];
+    free_names(http.clone(), msg, Kind::Emoji, &mut names, force_suggested)?;
+    let [name] = names;
+
+    if msg.attachments.len() != 2 {
+        return dm_user_err(http, msg, "Exactly two attachments are needed.");
+    }
+
+    // either version can win, so both pools need a slot
+    let animated = msg
+        .attachments
+        .iter()
+        .map(|a| extension(&a.filename).map(str::to_lowercase).as_deref() == Some("gif"))
+        .collect::<HashSet<_>>();
+    for animated in animated {
+        check_pool(http.clone(), msg, animated, 1)?;
+    }
+
+    let options = match Options::parse(&mut args, msg.guild_id) {
+        Ok(x) => x,
+        Err(why) => return dm_user_err(http, msg, &why),
+    };
+    if options.adjust {
+        return dm_user_err(http, msg, "mode:adjust only works with add.");
+    }
+    // the poll shows both right away
+    if CONFIG.read().unwrap().approval_queue {
+        return dm_user_err(
+            http,
+            msg,
+            "Suggestions wait for approval first, use add with the image you like more.",
+        );
+    }
+
+    let mut images = Vec::new();
+    for attachment in msg.attachments.iter() {
+        images.push(download_image(
+            http.clone(),
+            msg,
+            attachment,
+            min_image_size(),
+            128,
+        )?);
+        warn_upscale(http.clone(), msg, attachment, min_image_size());
+    }
+
+    let hashes = [file_hash(&images[0].0), file_hash(&images[1].0)];
+    for (img, _) in images.iter() {
+        check_cooldown(http.clone(), msg, &name, img)?;
+    }
+    let submission = validate::Submission {
+        kind: Kind::Emoji,
+        name: &name,
+        author: &msg.author,
+        category: options.category.as_deref(),
+        tally: user.counter,
+    };
+    if let Err(why) = validate::submission(&submission) {
+        return dm_user_err(http, msg, &why);
+    }
+    if let Some(why) = images
+        .iter()
+        .find_map(|(img, _)| validate::image(&submission, img).err())
+    {
+        return dm_user_err(http, msg, &why);
+    }
+    delete_original(http.clone(), msg)?;
+
+    let mut bufs = Vec::new();
+    for (img, format) in images.iter() {
+        bufs.push(process_emote(
+            http.clone(),
+            msg,
+            &name,
+            img,
+            *format,
+            &options,
+        )?);
+    }
+
+    if bufs.iter().any(|buf| screen(buf).is_some()) {
+        return dm_user_err(
+            http,
+            msg,
+            "A mod has to check this first, use add with the image you like more.",
+        );
+    }
+
+    let animated = [
+        images[0].1 == ImageFormat::Gif,
+        images[1].1 == ImageFormat::Gif,
+    ];
+    let filenames = [
+        format!("{}_a.{}", name, if animated[0] { "gif" } else { "png" }),
+        format!("{}_b.{}", name, if animated[1] { "gif" } else { "png" }),
+    ];
+
+    let poll = discord_post(&http, msg, "Sending variants", || {
+        voting_channel(options.category.as_deref()).send_message(&ctx.http, |m| {
+            m.content(format!(
+                "Which version of {} should go into the voting? {} or {}",
+                name, VARIANT_A, VARIANT_B
+            ));
+            m.add_files(vec![
+                (&*bufs[0], &*filenames[0]),
+                (&*bufs[1], &*filenames[1]),
+            ]);
+            m.reactions(vec![
+                ReactionType::from(VARIANT_A),
+                ReactionType::from(VARIANT_B),
+            ])
+        })
+    })?;
+
+    let mut variants = VARIANTS.write().unwrap();
+    let poll = VariantPoll {
+        message: poll,
+        name,
+        author: msg.author.name.to_string(),
+        author_id: msg.author.id,
+        animated,
+        hashes,
+        legacy: false,
+        category: options.category.clone(),
+        images: [images::put(&bufs[0]), images::put(&bufs[1])],
+    };
+    store::log(Op::Variant(poll.message.id, Box::new(poll.clone())));
+    variants.insert(poll.message.id, poll);
+    // both variants only cost one suggestion
+    user.counter += 1;
+    store::add(Counter::Slots(msg.author.id), 1);
+    store::log(Op::User(msg.author.id, user.clone()));
+
+    Ok(())
+}
+
+#[command("add-sticker")]
+#[only_in(guilds)]
+#[example("FeelsGoodMan [image as attachment]")]
+#[example("FeelsGoodMan filter:nearest [image as attachment]")]
+#[example("FeelsGoodMan --force-suggested [image as attachment]")]
+fn add_sticker(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut users = USERS.write().unwrap();
+
+    log!(
+        "{}   Args for add-sticker: {}",
+        msg.author.name,
+        &args.message()
+    );
+    let user = users.entry(msg.author.id).or_insert(User {
+        name: msg.author.name.clone(),
+        counter: 0,
+        quiet: false,
+    });
+
+    check_slots(http.clone(), msg, user)?;
+
+    if let Err(why) = validate::user(&msg.author) {
+        return dm_user_err(http, msg, &why);
+    }
+
+    let force_suggested = take_flag(&mut args, FORCE_SUGGESTED);
+    // check for the name, discord allows 2-30 characters for stickers
+    let name = match args.single::<String>() {
+        Ok(x) => x,
+        Err(_) => return dm_user_err(http, msg, "No name found."),
+    };
+
+    if let Err(why) = validate::name(Kind::Sticker, &name) {
+        return dm_user_err(http, msg, &why);
+    }
+    let mut names = [name];
+    free_names(
+        http.clone(),
+        msg,
+        Kind::Sticker,
+        &mut names,
+        force_suggested,
+    )?;
+    let [name] = names;
+
+    let options = match Options::parse(&mut args, msg.guild_id) {
+        Ok(x) => x,
+        Err(why) => return dm_user_err(http, msg, &why),
+    };
+    if options.adjust {
+        return dm_user_err(http, msg, "mode:adjust only works with add.");
+    }
+
+    // no point in voting if the winner could never be uploaded
+    match slots::free_stickers(&http, GUILD) {
+        Ok(0) => return dm_user_err(http, msg, "There are no free sticker slots."),
+        Ok(_) => {}
+        Err(why) => {
+            dm_user(http, msg, "Discord error, pls try again later.");
+            return Err(CommandError(format!("Counting sticker slots: {:?}", why)));
+        }
+    }
+
+    // check if there is exactly one attachment
+    if msg.attachments.len() != 1 {
+        return dm_user_err(http, msg, "No attachment found.");
+    }
+
+    let (raw, format) = download_image(http.clone(), msg, &msg.attachments[0], 300, sticker::SIZE)?;
+    warn_upscale(http.clone(), msg, &msg.attachments[0], 300);
+    let hash = file_hash(&raw);
+    check_cooldown(http.clone(), msg, &name, &raw)?;
+    let submission = validate::Submission {
+        kind: Kind::Sticker,
+        name: &name,
+        author: &msg.author,
+        category: options.category.as_deref(),
+        tally: user.counter,
+    };
+    if let Err(why) = validate::submission(&submission) {
+        return dm_user_err(http, msg, &why);
+    }
+    if let Err(why) = validate::image(&submission, &raw) {
+        return dm_user_err(http, msg, &why);
+    }
+    delete_original(http.clone(), msg)?;
+
+    // keep the pixels of fitting PNGs untouched, decoding would throw away the frames of an APNG
+    let fits = match format {
+        ImageFormat::Png => match image::load_from_memory_with_format(&raw, format) {
+            Ok(img) => img.dimensions() == (sticker::SIZE, sticker::SIZE),
+            Err(_) => false,
+        },
+        _ => false,
+    };
+
+    let stripped = match fits {
+        true => compress::strip_metadata(&raw),
+        false => None,
+    };
+
+    let buf = match stripped.filter(|b| b.len() <= sticker::MAX_BYTES) {
+        Some(buf) => buf,
+        None => render_static(
+            http.clone(),
+            msg,
+            &raw,
+            format,
+            sticker::SIZE,
+            sticker::MAX_BYTES,
+            &options,
+        )?,
+    };
+
+    if buf.len() > sticker::MAX_BYTES {
+        return dm_user_err(http, msg, "Sticker is above 512KB after resizing.");
+    }
+
+    let emote = Emote {
+        name: name.clone(),
+        author: msg.author.name.to_string(),
+        author_id: msg.author.id,
+        kind: Kind::Sticker,
+        animated: false,
+        hash,
+        legacy: false,
+        category: options.category.clone(),
+        image: None,
+    };
+
+    let filename = format!("{}.png", name);
+    match submit(&ctx.http, msg, emote, &buf, &filename)? {
+        Submitted::Voting(emsg) => {
+            receipt(&ctx.http, &emsg);
+            add_suggestion(&mut MESSAGES.write().unwrap(), emsg)
+        }
+        Submitted::Queued(emsg) => enqueue(&mut QUEUE.write().unwrap(), emsg),
+    }
+    user.counter += 1;
+    store::add(Counter::Slots(msg.author.id), 1);
+    store::log(Op::User(msg.author.id, user.clone()));
+
+    Ok(())
+}
+
+#[command("add-sound")]
+#[only_in(guilds)]
+#[example("Bonk [mp3 or ogg as attachment]")]
+#[example("Bonk --force-suggested [mp3 or ogg as attachment]")]
+fn add_sound(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut users = USERS.write().unwrap();
+
+    log!(
+        "{}   Args for add-sound: {}",
+        msg.author.name,
+        &args.message()
+    );
+    let user = users.entry(msg.author.id).or_insert(User {
+        name: msg.author.name.clone(),
+        counter: 0,
+        quiet: false,
+    });
+
+    check_slots(http.clone(), msg, user)?;
+
+    if let Err(why) = validate::user(&msg.author) {
+        return dm_user_err(http, msg, &why);
+    }
+
+    let force_suggested = take_flag(&mut args, FORCE_SUGGESTED);
+    // check for the name, discord allows 2-32 characters for sounds
+    let name = match args.single::<String>() {
+        Ok(x) => x,
+        Err(_) => return dm_user_err(http, msg, "No name found."),
+    };
+
+    if let Err(why) = validate::name(Kind::Sound, &name) {
+        return dm_user_err(http, msg, &why);
+    }
+    let mut names = [name];
+    free_names(http.clone(), msg, Kind::Sound, &mut names, force_suggested)?;
+    let [name] = names;
+    let submission = validate::Submission {
+        kind: Kind::Sound,
+        name: &name,
+        author: &msg.author,
+        category: None,
+        tally: user.counter,
+    };
+    if let Err(why) = validate::submission(&submission) {
+        return dm_user_err(http, msg, &why);
+    }
+
+    // check if there is exactly one attachment
+    if msg.attachments.len() != 1 {
+        return dm_user_err(http, msg, "No attachment found.");
+    }
+
+    let attachment = msg.attachments.first().unwrap();
+
+    if attachment.size > sound::MAX_BYTES {
+        return dm_user_err(http, msg, "512KB is the size limit for sounds.");
+    }
+
+    let filetype = match extension(&attachment.filename) {
+        Some(x) => x.to_lowercase(),
+        None => return dm_user_err(http, msg, "Filename is not processable."),
+    };
+
+    if !(["mp3", "ogg"].contains(&filetype.as_str())) {
+        return dm_user_err(http, msg, "MP3 or OGG, nothing else is allowed.");
+    }
+
+    match slots::free_sounds(&http, GUILD) {
+        Ok(0) => return dm_user_err(http, msg, "There are no free soundboard slots."),
+        Ok(_) => {}
+        Err(why) => {
+            dm_user(http, msg, "Discord error, pls try again later.");
+            return Err(CommandError(format!("Counting sound slots: {:?}", why)));
+        }
+    }
+
+    let data = fetch(http.clone(), msg, attachment)?;
+
+    match sound::duration(&filetype, &data) {
+        Some(x) if x <= sound::MAX_DURATION => {}
+        Some(_) => return dm_user_err(http, msg, "Sounds can be at most 5.2 seconds long."),
+        None => return dm_user_err(http, msg, "Attachment is not a valid MP3 or OGG."),
+    }
+
+    let hash = file_hash(&data);
+    check_cooldown(http.clone(), msg, &name, &data)?;
+
+    // delete original message after download is finished!
+    discord(&http, msg, "Deleting org. msg", || msg.delete(http.clone()))?;
+
+    let emote = Emote {
+        name: name.clone(),
+        author: msg.author.name.to_string(),
+        author_id: msg.author.id,
+        kind: Kind::Sound,
+        animated: false,
+        hash,
+        legacy: false,
+        category: None,
+        image: None,
+    };
+
+    let filename = format!("{}.{}", name, filetype);
+    match submit(&ctx.http, msg, emote, &data, &filename)? {
+        Submitted::Voting(emsg) => {
+            receipt(&ctx.http, &emsg);
+            add_suggestion(&mut MESSAGES.write().unwrap(), emsg)
+        }
+        Submitted::Queued(emsg) => enqueue(&mut QUEUE.write().unwrap(), emsg),
+    }
+    user.counter += 1;
+    store::add(Counter::Slots(msg.author.id), 1);
+    store::log(Op::User(msg.author.id, user.clone()));
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("FeelsGodMan FeelsGoodMan")]
+fn rename(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+
+    log!("{}   Args for rename: {}", msg.author.name, &args.message());
+    let (old, new) = match (args.single::<String>(), args.single::<String>()) {
+        (Ok(old), Ok(new)) => (old, new),
+        _ => return dm_user_err(http, msg, "Old and new name are needed."),
+    };
+
+    // `taken_names` reads the suggestions, so it runs before they get locked
+    let kind = MESSAGES
+        .read()
+        .unwrap()
+        .values()
+        .find(|m| names::same(&m.emote.name, &old))
+        .map(|m| m.emote.kind);
+    let taken = match kind {
+        Some(kind) => taken_names(&http, kind),
+        None => return dm_user_err(http, msg, "No suggestion with that name."),
+    };
+
+    let mut messages = MESSAGES.write().unwrap();
+    let emsg = match messages
+        .values_mut()
+        .find(|m| names::same(&m.emote.name, &old))
+    {
+        Some(x) => x,
+        None => return dm_user_err(http, msg, "No suggestion with that name."),
+    };
+
+    // a new casing of its own name is fine
+    if taken.contains(&names::fold(&new)) && !names::same(&emsg.emote.name, &new) {
+        return dm_user_err(http, msg, "That name is already taken.");
+    }
+
+    if emsg.emote.author_id != msg.author.id && !is_mod(ctx, msg) {
+        return dm_user_err(http, msg, "Only the author or a mod can rename this.");
+    }
+
+    if let Err(why) = validate::name(emsg.emote.kind, &new) {
+        return dm_user_err(http, msg, &why);
+    }
+
+    // the emote preview keeps the id of the deleted emoji, only the name changes
+    let preview = match emsg.emote.kind {
+        Kind::Emoji => emsg.messages[1].content.replacen(
+            &format!(":{}:", emsg.emote.name),
+            &format!(":{}:", new),
+            1,
+        ),
+        Kind::Sticker => format!("Sticker: {}", new),
+        Kind::Sound => format!("Sound: {}", new),
+    };
+
+    discord(&http, msg, "Editing messages", || {
+        emsg.messages[0]
+            .edit(&*ctx, |m| m.content(&new))
+            .and_then(|_| emsg.messages[1].edit(&*ctx, |m| m.content(&preview)))
+    })?;
+    emsg.emote.name = new;
+    store::log(Op::Suggestion(Box::new(emsg.clone())));
+
+    dm_user(http, msg, "Done");
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("3kx9b2m1qz0")]
+#[example("PepeLaugh")]
+fn status(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let query = args.rest().trim();
+    if query.is_empty() {
+        return dm_user_err(http, msg, "Missing id or name.");
+    }
+
+    let queue = QUEUE.read().unwrap();
+    let messages = MESSAGES.read().unwrap();
+    let found = parse_id(query)
+        .filter(|id| messages.contains_key(id))
+        .or_else(|| {
+            messages
+                .iter()
+                .find(|(_, m)| names::same(&m.emote.name, query))
+                .map(|(id, _)| *id)
+        });
+    let id = match found {
+        Some(x) => x,
+        None => {
+            let waiting = queue.values().find(|m| names::same(&m.emote.name, query));
+            let content = match waiting {
+                Some(m) => format!("{} waits for the approval of the mods.", m.emote.name),
+                None => match HISTORY
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .rev()
+                    .find(|f| parse_id(query) == Some(f.id) || names::same(&f.name, query))
+                {
+                    Some(f) => format!(
+                        "{} from {} got {} on {} with 👍 {} 👎 {}.",
+                        f.name,
+                        f.author,
+                        if f.accepted { "accepted" } else { "rejected" },
+                        f.at.format("%Y-%m-%d"),
+                        f.votes.0,
+                        f.votes.1
+                    ),
+                    None => String::from("No suggestion with that id or name."),
+                },
+            };
+            dm_user(http, msg, &content);
+            return Ok(());
+        }
+    };
+    drop(queue);
+    let emsg = &messages[&id];
+
+    let mut content = format!(
+        "**{}** from {} (ID `{}`)",
+        emsg.emote.name,
+        emsg.emote.author,
+        short_id(id)
+    );
+    if !voting_open() {
+        content += &match ROUND.read().unwrap().voting_at {
+            Some(at) => format!("\nNomination, the voting starts in {}.", time_left(at)),
+            None => String::from("\nNomination, the voting starts when a mod opens it."),
+        };
+        dm_user(http, msg, &content);
+        return Ok(());
+    }
+
+    // ranked like the leaderboard
+    let tallies = messages
+        .values()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|emsg| suggestion_votes(&http, emsg).map(|tally| (emsg.messages[1].id, tally)))
+        .collect::<serenity::Result<Vec<_>>>();
+    let mut tallies = match tallies {
+        Ok(x) => x,
+        Err(why) => {
+            dm_user(http, msg, "Discord error, pls try again later.");
+            return Err(CommandError(format!("Fetching votes: {:?}", why)));
+        }
+    };
+    tallies.sort_by_key(|(_, (pos, neg))| -(*pos as i64 - *neg as i64));
+    if let Some(rank) = tallies.iter().position(|(i, _)| *i == id) {
+        let (pos, neg) = tallies[rank].1;
+        content += &format!(
+            "\n👍 {} 👎 {}, rank {} of {}",
+            pos,
+            neg,
+            rank + 1,
+            tallies.len()
+        );
+    }
+
+    let runoff = RUNOFFS
+        .read()
+        .unwrap()
+        .values()
+        .find(|r| r.candidates.contains(&id))
+        .map(|r| r.ends);
+    content += &match (emsg.won, runoff) {
+        (true, _) => String::from("\nIt won and waits for its upload."),
+        (false, Some(ends)) => format!(
+            "\nIt's in the runoff for the last winner slot, which ends in {}.",
+            time_left(ends)
+        ),
+        (false, None) => {
+            let closes_at = ROUND.read().unwrap().closes_at;
+            let end = closes_at.map_or(deadline(emsg), |at| at.min(deadline(emsg)));
+            format!("\nVoting, it ends in {}.", time_left(end))
+        }
+    };
+
+    dm_user(http, msg, &content);
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("off")]
+#[example("on")]
+fn dms(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut users = USERS.write().unwrap();
+
+    log!("{}   Args for dms: {}", msg.author.name, &args.message());
+    let quiet = match args.single::<String>().as_deref() {
+        Ok("off") => true,
+        Ok("on") => false,
+        _ => return dm_user_err(http, msg, "Use dms on or dms off."),
+    };
+
+    let user = users.entry(msg.author.id).or_insert(User {
+        name: msg.author.name.clone(),
+        counter: 0,
+        quiet: false,
+    });
+    user.quiet = quiet;
+    store::log(Op::User(msg.author.id, user.clone()));
+    drop(users);
+
+    dm_user(
+        http,
+        msg,
+        match quiet {
+            true => "You won't get DMs about the milestones of your suggestions anymore.",
+            false => "You get DMs about the milestones of your suggestions again.",
+        },
+    );
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("3kx9b2m1qz0")]
+fn withdraw(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut messages = MESSAGES.write().unwrap();
+
+    log!(
+        "{}   Args for withdraw: {}",
+        msg.author.name,
+        &args.message()
+    );
+    let id = match args.single::<String>().ok().and_then(|a| parse_id(&a)) {
+        Some(x) => x,
+        None => {
+            return dm_user_err(
+                http,
+                msg,
+                "Missing id, it's in the receipt of the suggestion.",
+            )
+        }
+    };
+
+    match messages.get(&id) {
+        Some(m) if m.emote.author_id == msg.author.id => {}
+        Some(_) => return dm_user_err(http, msg, "Only the author can withdraw this."),
+        None => return dm_user_err(http, msg, "ID is not in messages."),
+    }
+
+    if let Err(why) = remove_suggestion(&http, &mut messages, id) {
+        return dm_user_err(http, msg, why);
+    }
+
+    dm_user(http, msg, "Done");
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("pepe")]
+fn list(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let messages = MESSAGES.read().unwrap();
+    let category = in_category(args.rest());
+
+    let mut pending = messages
+        .values()
+        .filter(|emsg| category(emsg))
+        .collect::<Vec<_>>();
+    pending.sort_by_key(|emsg| deadline(emsg));
+    let content = pending
+        .iter()
+        .take(LIST_LIMIT)
+        .map(|emsg| {
+            format!(
+                "\n{} ({}){} from {}, voting ends {}",
+                emsg.emote.name,
+                emsg.messages[1].id,
+                emsg.emote
+                    .category
+                    .as_ref()
+                    .map_or(String::new(), |c| format!(" [{}]", c)),
+                emsg.emote.author,
+                local_time(deadline(emsg))
+            )
+        })
+        .collect::<String>();
+
+    let content = match pending.len() {
+        0 => String::from("No pending suggestions."),
+        n if n > LIST_LIMIT => format!("Pending:{}\nand {} more", content, n - LIST_LIMIT),
+        _ => format!("Pending:{}", content),
+    };
+    dm_user(http, msg, &content);
+    Ok(())
+}
+
+/// Filter for an optional category argument, an empty one lets everything through.
+fn in_category(arg: &str) -> impl Fn(&EmoteMessage) -> bool {
+    let wanted = arg.trim().to_lowercase();
+    move |emsg| wanted.is_empty() || emsg.emote.category.as_deref() == Some(&*wanted)
+}
+
+#[command]
+#[only_in(guilds)]
+fn ranking(ctx: &mut Context, msg: &Message) -> CommandResult {
+    let http = ctx.http.clone();
+    let messages = MESSAGES.read().unwrap();
+
+    let mut ranked = messages.values().collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.rating.partial_cmp(&a.rating).unwrap_or(Ordering::Equal));
+    let content = ranked
+        .iter()
+        .enumerate()
+        .map(|(n, emsg)| {
+            format!(
+                "\n{}. {}: {:.0} from: {}",
+                n + 1,
+                emsg.emote.name,
+                emsg.rating,
+                emsg.emote.author
+            )
+        })
+        .collect::<String>();
+
+    let content = match content.is_empty() {
+        true => String::from("Nothing to rank."),
+        false => format!("Ranking from the pairwise comparisons:{}", content),
+    };
+    dm_user(http, msg, &content);
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+fn mine(ctx: &mut Context, msg: &Message) -> CommandResult {
+    let http = ctx.http.clone();
+    let users = USERS.read().unwrap();
+    let messages = MESSAGES.read().unwrap();
+
+    let left = users
+        .get(&msg.author.id)
+        .map_or(CONFIG.read().unwrap().max_suggestions, slots_left);
+    let mut content = format!("You can post {} more suggestions.", left);
+
+    let pending = messages
+        .values()
+        .filter(|m| m.emote.author_id == msg.author.id)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|emsg| {
+            let tally = match suggestion_votes(&http, emsg) {
+                Ok((pos, neg)) => format!("👍 {} 👎 {}", pos, neg),
+                Err(_) => String::from("votes unavailable"),
+            };
+            format!(
+                "\n{} ({}): {}, voting ends {}",
+                emsg.emote.name,
+                emsg.messages[1].id,
+                tally,
+                local_time(deadline(emsg))
+            )
+        })
+        .reduce(String::new, |acc, s| acc + &s);
+
+    if !pending.is_empty() {
+        content += &format!("\nPending:{}", pending);
+    }
+
+    let (won, lost) = HISTORY
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|f| f.author_id == msg.author.id)
+        .fold((0, 0), |(won, lost), f| match f.accepted {
+            true => (won + 1, lost),
+            false => (won, lost + 1),
+        });
+    content += &format!("\nAccepted: {}, rejected: {}", won, lost);
+
+    dm_user(http, msg, &content);
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("pepe")]
+fn search(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let messages = MESSAGES.read().unwrap();
+
+    log!("{}   Args for search: {}", msg.author.name, &args.message());
+    let query = names::fold(args.rest().trim());
+    if query.is_empty() {
+        return dm_user_err(http, msg, "Nothing to search for.");
+    }
+
+    let matches = |name: &str, author: &str| {
+        names::fold(name).contains(&query) || names::fold(author).contains(&query)
+    };
+
+    let mut results = messages
+        .values()
+        .filter(|m| matches(&m.emote.name, &m.emote.author))
+        .take(SEARCH_LIMIT)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|emsg| {
+            let tally = match suggestion_votes(&http, emsg) {
+                Ok((pos, neg)) => format!("👍 {} 👎 {}", pos, neg),
+                Err(_) => String::from("votes unavailable"),
+            };
+            format!(
+                "\n{} ({}) from {}: pending, {}",
+                emsg.emote.name, emsg.messages[1].id, emsg.emote.author, tally
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // newest verdicts first, older ones are the least likely to matter
+    let history = HISTORY.read().unwrap();
+    let remaining = SEARCH_LIMIT.saturating_sub(results.len());
+    results.extend(
+        history
+            .iter()
+            .rev()
+            .filter(|f| matches(&f.name, &f.author))
+            .take(remaining)
+            .map(|f| {
+                format!(
+                    "\n{} ({}) from {}: {} on {}, 👍 {} 👎 {}",
+                    f.name,
+                    f.id,
+                    f.author,
+                    if f.accepted { "accepted" } else { "rejected" },
+                    f.at.format("%Y-%m-%d"),
+                    f.votes.0,
+                    f.votes.1
+                )
+            }),
+    );
+
+    match results.is_empty() {
+        true => dm_user(http, msg, "No suggestions found."),
+        false => dm_user(http, msg, &format!("Found:{}", results.concat())),
+    }
+    Ok(())
+}
+
+/// The results of `HISTORY` newest first as Markdown table, the columns padded so it reads as
+/// well in a code block.
+fn markdown_table(history: &[Finished]) -> String {
+    let mut rows = vec![["Name", "Author", "👍", "👎", "Score", "Verdict"].map(String::from)];
+    for f in history.iter().rev() {
+        rows.push([
+            f.name.replace('|', "\\|"),
+            f.author.replace('|', "\\|"),
+            f.votes.0.to_string(),
+            f.votes.1.to_string(),
+            (f.votes.0 as i64 - f.votes.1 as i64).to_string(),
+            format!(
+                "{} on {}",
+                if f.accepted { "accepted" } else { "rejected" },
+                f.at.format("%Y-%m-%d")
+            ),
+        ]);
+    }
+
+    let mut widths = [3; 6];
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    // the votes and the score are numbers and right aligned
+    let right = |column: usize| (2..5).contains(&column);
+    let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+
+    let mut table = String::new();
+    for (n, row) in rows.iter().enumerate() {
+        table += &line(
+            row.iter()
+                .enumerate()
+                .map(|(column, cell)| {
+                    let pad = " ".repeat(widths[column] - cell.chars().count());
+                    match right(column) {
+                        true => pad + cell,
+                        false => cell.clone() + &pad,
+                    }
+                })
+                .collect(),
+        );
+        if n == 0 {
+            table += &line(
+                widths
+                    .iter()
+                    .enumerate()
+                    .map(|(column, width)| match right(column) {
+                        true => "-".repeat(width - 1) + ":",
+                        false => "-".repeat(*width),
+                    })
+                    .collect(),
+            );
+        }
+    }
+    table
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("md")]
+#[example("card 5")]
+#[checks(Permitted)]
+fn export(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+
+    log!("{}   Args for export: {}", msg.author.name, &args.message());
+    let (content, file, filename) = match args.single::<String>().as_deref() {
+        Ok("md") => {
+            let history = HISTORY.read().unwrap();
+            if history.is_empty() {
+                return dm_user_err(http, msg, "There are no results yet.");
+            }
+            let table = markdown_table(&history);
+            drop(history);
+
+            // a table that fits into a message comes as code block
+            let block = format!("```md\n{}```", table);
+            if block.chars().count() <= 2000 {
+                dm_user(http, msg, &block);
+                return Ok(());
+            }
+            (
+                "The results, newest first:",
+                table.into_bytes(),
+                "results.md",
+            )
+        }
+        Ok("card") => {
+            let count = args.single::<usize>().unwrap_or(LEADERBOARD_SIZE);
+            if !(1..=CARD_LIMIT).contains(&count) {
+                let why = format!("A card shows 1 to {} suggestions.", CARD_LIMIT);
+                return dm_user_err(http, msg, &why);
+            }
+            let card = match leaderboard_card(&http, count) {
+                Ok(Some(x)) => x,
+                Ok(None) => return dm_user_err(http, msg, "There are no suggestions to show."),
+                Err(why) => {
+                    dm_user(http, msg, "Discord error, pls try again later.");
+                    return Err(CommandError(format!("Rendering the card: {:?}", why)));
+                }
+            };
+            ("The current standings:", card, "standings.png")
+        }
+        _ => return dm_user_err(http, msg, "Use export md or export card [COUNT]."),
+    };
+
+    let dm = discord(&http, msg, "Opening DM", || {
+        msg.author.create_dm_channel(&http)
+    })?;
+    discord_post(&http, msg, "Sending export", || {
+        dm.send_message(&http, |m| {
+            m.content(content).add_file((&file[..], filename))
+        })
+    })?;
+    Ok(())
+}
+
+/// PNG of the `count` pending suggestions with the best 👍 minus 👎, see `collage::card`.
+/// `None` when there are none.
+fn leaderboard_card(
+    http: &Http,
+    count: usize,
+) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let messages = MESSAGES.read().unwrap();
+    let mut tallies = messages
+        .values()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|emsg| suggestion_votes(http, emsg).map(|tally| (emsg, tally)))
+        .collect::<serenity::Result<Vec<_>>>()?;
+    tallies.sort_by_key(|(_, (pos, neg))| -(*pos as i64 - *neg as i64));
+    tallies.truncate(count);
+    if tallies.is_empty() {
+        return Ok(None);
+    }
+
+    let standings = tallies
+        .par_iter()
+        .map(|(emsg, votes)| collage::Standing {
+            image: Some(emsg)
+                .filter(|e| e.emote.kind != Kind::Sound)
+                .and_then(|e| file_of(e, e.messages[0].attachments.first()?).ok())
+                .and_then(|data| image::load_from_memory(&data).ok()),
+            name: emsg.emote.name.clone(),
+            author: emsg.emote.author.clone(),
+            votes: *votes,
+        })
+        .collect::<Vec<_>>();
+    drop(messages);
+
+    let today = Utc::now().with_timezone(&zone());
+    let subtitle = match ROUND.read().unwrap().closes_at {
+        Some(at) => format!(
+            "{}, the voting closes {}",
+            today.format("%Y-%m-%d"),
+            at.with_timezone(&zone()).format("%Y-%m-%d")
+        ),
+        None => today.format("%Y-%m-%d").to_string(),
+    };
+    let title = format!("Top {} of the voting", standings.len());
+    let rendered = pool::run(move || {
+        let mut buf = Vec::new();
+        DynamicImage::ImageRgba8(collage::card(&title, &subtitle, &standings))
+            .write_to(&mut buf, ImageOutputFormat::Png)
+            .map(|_| buf)
+    });
+    Ok(Some(rendered??))
+}
+
+#[command("config")]
+#[only_in(guilds)]
+#[example("prefix !")]
+#[checks(Permitted)]
+fn guild_config(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut guilds = GUILDS.write().unwrap();
+
+    log!("{}   Args for config: {}", msg.author.name, &args.message());
+    let guild = match msg.guild_id {
+        Some(x) => x,
+        None => return dm_user_err(http, msg, "Only works in a guild."),
+    };
+
+    let (key, value) = match (args.single::<String>(), args.single::<String>()) {
+        (Ok(key), Ok(value)) => (key, value),
+        _ => return dm_user_err(http, msg, "Setting and value are needed."),
+    };
+
+    let config = guilds.entry(guild).or_default();
+    match key.as_str() {
+        "prefix" => config.prefix = value,
+        "mode" => match value.parse() {
+            Ok(aspect) => config.aspect = aspect,
+            Err(why) => return dm_user_err(http, msg, &why),
+        },
+        "timezone" => match time_zone(&value) {
+            Some(zone) => config.timezone = zone,
+            None => {
+                return dm_user_err(
+                    http,
+                    msg,
+                    "Give a time zone like Europe/Berlin or an offset from UTC like +02:00.",
+                )
+            }
+        },
+        "federate" => match value.as_str() {
+            "on" => config.federate = true,
+            "off" => config.federate = false,
+            _ => return dm_user_err(http, msg, "Use on or off."),
+        },
+        "collisions" => match value.parse() {
+            Ok(collisions) => config.collisions = collisions,
+            Err(why) => return dm_user_err(http, msg, &why),
+        },
+        _ => return dm_user_err(http, msg, &format!("Unknown setting {}.", key)),
+    }
+
+    drop(guilds);
+    if !store::save(snapshot) {
+        dm_user(http, msg, "Internal error, pls DM Infi#8527.");
+        return Err(CommandError(String::from("Saving guild config failed")));
+    }
+
+    dm_user(http, msg, "Done");
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("grant stats @Helpers")]
+#[checks(Permitted)]
+fn perm(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+
+    log!("{}   Args for perm: {}", msg.author.name, &args.message());
+    let action = args.single::<String>().unwrap_or_default();
+    if action == "list" {
+        let guilds = GUILDS.read().unwrap();
+        let lines = guilds
+            .get(&GUILD)
+            .map(|g| {
+                g.permissions
+                    .iter()
+                    .filter(|(_, grant)| !grant.is_empty())
+                    .map(|(command, grant)| {
+                        let mentions = grant
+                            .roles
+                            .iter()
+                            .map(|r| Target::Role(*r).mention())
+                            .chain(grant.users.iter().map(|u| Target::User(*u).mention()))
+                            .collect::<Vec<_>>();
+                        format!("\n{}: {}", command, mentions.join(", "))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let content = match lines.is_empty() {
+            true => String::from("Only the mods can run the mod commands."),
+            false => format!("Besides the mods:{}", lines.concat()),
+        };
+        dm_user(http, msg, &content);
+        return Ok(());
+    }
+    if action != "grant" && action != "revoke" {
+        return dm_user_err(http, msg, "Use grant, revoke or list.");
+    }
+
+    // a grant is stored under the first name, aliases are found too
+    let name = args.single::<String>().unwrap_or_default();
+    let command = mod_commands()
+        .into_iter()
+        .find(|names| names.contains(&name.as_str()))
+        .map(|names| names[0])
+        .or_else(|| permissions::ACTIONS.iter().copied().find(|a| *a == name));
+    let command = match command {
+        Some("perm") => return dm_user_err(http, msg, "perm stays with the mods."),
+        Some(x) => x,
+        None => {
+            let why = format!("{} is no mod command.", name);
+            return dm_user_err(http, msg, &why);
+        }
+    };
+
+    let mut targets = Vec::new();
+    for arg in args.iter::<String>().filter_map(Result::ok) {
+        match permissions::target(ctx, &arg) {
+            Some(x) => targets.push(x),
+            None => {
+                let why = format!("{} is no role or user.", arg);
+                return dm_user_err(http, msg, &why);
+            }
+        }
+    }
+    if targets.is_empty() {
+        return dm_user_err(http, msg, "Missing role or user.");
+    }
+
+    let mut guilds = GUILDS.write().unwrap();
+    let grant = guilds
+        .entry(GUILD)
+        .or_default()
+        .permissions
+        .entry(command.to_string())
+        .or_default();
+    for target in targets.iter() {
+        match (target, action.as_str()) {
+            (Target::Role(id), "grant") => grant.roles.insert(*id),
+            (Target::Role(id), _) => grant.roles.remove(id),
+            (Target::User(id), "grant") => grant.users.insert(*id),
+            (Target::User(id), _) => grant.users.remove(id),
+        };
+    }
+    drop(guilds);
+    if !store::save(snapshot) {
+        dm_user(http, msg, "Internal error, pls DM Infi#8527.");
+        return Err(CommandError(String::from("Saving permissions failed")));
+    }
+
+    let mentions = targets.iter().map(Target::mention).collect::<Vec<_>>();
+    let target = format!("{} for {}", command, mentions.join(", "));
+    let done = match action.as_str() {
+        "grant" => "granted",
+        _ => "revoked",
+    };
+    audit(http.clone(), &msg.author.name, msg.author.id, done, target);
+    dm_user(http, msg, "Done");
+    Ok(())
+}
+
+/// Names and aliases of the commands behind the `Permitted` check.
+fn mod_commands() -> Vec<&'static [&'static str]> {
+    GENERAL_GROUP
+        .options
+        .commands
+        .iter()
+        .filter(|c| {
+            c.options
+                .checks
+                .iter()
+                .any(|check| check.name == "Permitted")
+        })
+        .map(|c| c.options.names)
+        .collect()
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("pepe")]
+#[checks(Permitted)]
+fn stats(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let messages = MESSAGES.read().unwrap();
+    let category = in_category(args.rest());
+    let budget = CONFIG.read().unwrap().vote_budget.is_some();
+
+    // with a `vote_budget` every voter splits one vote between their 👍
+    let mut used = HashMap::new();
+    for user in messages.values().flat_map(|e| e.upvoted.iter()) {
+        *used.entry(*user).or_insert(0) += 1;
+    }
+    let normalized = |emsg: &EmoteMessage| {
+        emsg.upvoted
+            .iter()
+            .map(|u| 1.0 / used[u] as f64)
+            .sum::<f64>()
+    };
+
+    let content: String = messages
+        .values()
+        .filter(|emsg| category(emsg))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter_map(|emsg: &EmoteMessage| suggestion_votes(&http, emsg).ok().map(|v| (emsg, v)))
+        .map(|(emsg, (pos, neg))| {
             if pos * neg == 0 {
                 return String::from("Error, could not retrieve votes");
             }
-            format!(
-                "\n{}: {:.6} from: {}",
+            let share = match budget {
+                true => format!(", {:.2} budget-normalized", normalized(emsg)),
+                false => String::new(),
+            };
+            format!(
+                "\n{}: {:.6}{} from: {}",
+                emsg.emote.name,
+                pos as f64 / neg as f64,
+                share,
+                emsg.emote.author
+            )
+        })
+        .reduce(String::new, |acc, s| acc + &s);
+
+    discord_post(&http, msg, "Sending stats", || {
+        msg.channel_id.say(&http, &content)
+    })?;
+
+    Ok(())
+}
+
+/// Takes `--dry-run` out of `args`, `dry_run` in the config makes every run a dry one.
+fn dry_run(args: &mut Args) -> bool {
+    take_flag(args, DRY_RUN) || CONFIG.read().unwrap().dry_run
+}
+
+/// Whether `flag` is one of `args`, it's taken out so the other arguments parse as usual.
+fn take_flag(args: &mut Args, flag: &str) -> bool {
+    let flagged = args.raw().any(|a| a == flag);
+    if flagged {
+        let rest = args.raw().filter(|a| *a != flag).collect::<Vec<_>>();
+        *args = Args::new(&rest.join(" "), &[Delimiter::Single(' ')]);
+    }
+    flagged
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("123456789")]
+#[example("PepeLaugh")]
+#[example("[as reply to the suggestion]")]
+#[example("123456789 --dry-run")]
+#[checks(Permitted)]
+fn remove(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut messages = MESSAGES.write().unwrap();
+
+    log!("{}   Args for remove: {}", msg.author.name, &args.message());
+    let dry = dry_run(&mut args);
+    // without an id the suggestion is the message the command replies to
+    let parsed = match args.is_empty() {
+        true => replied_suggestion(msg, &messages).ok_or("Missing id, or reply to a suggestion."),
+        // a name works too, compared like all names
+        false => {
+            let arg = args.rest().trim();
+            parse_id(arg)
+                .filter(|id| messages.contains_key(id))
+                .or_else(|| {
+                    messages
+                        .iter()
+                        .find(|(_, m)| names::same(&m.emote.name, arg))
+                        .map(|(id, _)| *id)
+                })
+                .ok_or("No suggestion with that id or name.")
+        }
+    };
+
+    if let (true, Ok(id)) = (dry, parsed) {
+        let content = match messages.get(&id) {
+            Some(m) => format!(
+                "Dry run, nothing was changed. Remove would take {} from {} out of the voting, delete its 2 messages after {} minutes and record it as rejected.",
+                m.emote.name, m.emote.author, UNDO_MINUTES
+            ),
+            None => String::from("Dry run: ID is not in messages."),
+        };
+        dm_user(http, msg, &content);
+        return Ok(());
+    }
+    let parsed = parsed.and_then(|id| {
+        let name = messages[&id].emote.name.clone();
+        trash_suggestion(&http, &mut messages, id).map(|_| (id, name))
+    });
+
+    let id = match parsed {
+        Ok((id, name)) => {
+            let target = format!("{} ({})", name, short_id(id));
+            audit(
+                http.clone(),
+                &msg.author.name,
+                msg.author.id,
+                "removed",
+                target,
+            );
+            id
+        }
+        Err(mess) => return dm_user_err(http, msg, mess),
+    };
+
+    let content = format!(
+        "Removed, `undo {}` brings it back within {} minutes.",
+        short_id(id),
+        UNDO_MINUTES
+    );
+    dm_user(http, msg, &content);
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("123456789")]
+#[checks(Permitted)]
+fn undo(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut messages = MESSAGES.write().unwrap();
+    let mut trash = REMOVED.write().unwrap();
+
+    log!("{}   Args for undo: {}", msg.author.name, &args.message());
+    let id = match args.single::<String>().ok().and_then(|a| parse_id(&a)) {
+        Some(x) => x,
+        None => return dm_user_err(http, msg, "Missing id."),
+    };
+    let trashed = match trash.get(&id) {
+        Some(x) if x.purged.is_some() => {
+            let why = format!(
+                "Its messages are deleted already, `restore {}` posts it again.",
+                short_id(id)
+            );
+            return dm_user_err(http, msg, &why);
+        }
+        Some(x) => x.clone(),
+        None => {
+            let why = format!(
+                "ID is not removed, or longer than {} minutes ago.",
+                UNDO_MINUTES
+            );
+            return dm_user_err(http, msg, &why);
+        }
+    };
+
+    // the stored messages still have the content from before the removal
+    for m in trashed.emsg.messages.iter() {
+        discord(&http, msg, "Editing message", || {
+            m.channel_id
+                .edit_message(&http, m.id, |e| e.content(&m.content))
+        })?;
+    }
+    store::log(Op::TrashDone(id));
+    trash.remove(&id);
+    store::log(Op::Suggestion(Box::new(trashed.emsg.clone())));
+    STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+    let target = format!("{} ({})", trashed.emsg.emote.name, short_id(id));
+    messages.insert(id, trashed.emsg);
+    drop(trash);
+    drop(messages);
+    audit(
+        http.clone(),
+        &msg.author.name,
+        msg.author.id,
+        "undid the removal of",
+        target,
+    );
+    store::save(snapshot);
+
+    dm_user(http, msg, "It's back in the voting with its votes.");
+    Ok(())
+}
+
+#[command("restore")]
+#[only_in(guilds)]
+#[example("123456789")]
+#[checks(Permitted)]
+fn restore_suggestion(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut messages = MESSAGES.write().unwrap();
+    let mut trash = REMOVED.write().unwrap();
+
+    log!(
+        "{}   Args for restore: {}",
+        msg.author.name,
+        &args.message()
+    );
+    let id = match args.single::<String>().ok().and_then(|a| parse_id(&a)) {
+        Some(x) => x,
+        None => return dm_user_err(http, msg, "Missing id."),
+    };
+    let (old, purged) = match trash.get(&id) {
+        Some(Trashed {
+            emsg,
+            purged: Some(purged),
+            ..
+        }) => (emsg.clone(), purged.clone()),
+        Some(_) => {
+            let why = format!(
+                "It still has its messages, `undo {}` brings it back.",
+                short_id(id)
+            );
+            return dm_user_err(http, msg, &why);
+        }
+        None => {
+            let why = format!(
+                "ID is not removed, or longer than {} days ago.",
+                RESTORE_DAYS
+            );
+            return dm_user_err(http, msg, &why);
+        }
+    };
+    let file = match base64::decode(&purged.file) {
+        Ok(x) => x,
+        Err(why) => {
+            dm_user(http, msg, "The stored file is broken.");
+            return Err(CommandError(format!("Decoding file of {}: {}", id, why)));
+        }
+    };
+
+    let posted = match publish(&http, old.emote.clone(), &file, &purged.filename) {
+        Ok(x) => x,
+        Err(why) => {
+            dm_user(http, msg, "Discord error, pls try again later.");
+            return Err(CommandError(why));
+        }
+    };
+    // everything but the messages is the old entry, the votes of the old messages are carried
+    let emsg = EmoteMessage {
+        messages: posted.messages,
+        carried: purged.votes,
+        ..old
+    };
+    let new_id = emsg.messages[1].id;
+    let target = format!(
+        "{} ({}, was {})",
+        emsg.emote.name,
+        short_id(new_id),
+        short_id(id)
+    );
+
+    store::log(Op::TrashDone(id));
+    trash.remove(&id);
+    store::log(Op::Suggestion(Box::new(emsg.clone())));
+    STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+    messages.insert(new_id, emsg);
+    drop(trash);
+    drop(messages);
+    // it was recorded as rejected when its messages were deleted
+    HISTORY.write().unwrap().retain(|f| f.id != id);
+    audit(
+        http.clone(),
+        &msg.author.name,
+        msg.author.id,
+        "restored",
+        target,
+    );
+    store::save(snapshot);
+
+    let content = format!(
+        "It's back in the voting as {} with {} 👍 and {} 👎 from before.",
+        short_id(new_id),
+        purged.votes.0,
+        purged.votes.1
+    );
+    dm_user(http, msg, &content);
+    Ok(())
+}
+
+#[command("remove-many")]
+#[only_in(guilds)]
+#[example("123456789 987654321")]
+#[example("123456789 987654321 --dry-run")]
+#[checks(Permitted)]
+fn remove_many(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+
+    log!(
+        "{}   Args for remove-many: {}",
+        msg.author.name,
+        &args.message()
+    );
+    let dry = dry_run(&mut args);
+    let mut ids = Vec::new();
+    for arg in args.raw() {
+        match parse_id(arg) {
+            Some(id) => ids.push(id),
+            None => return dm_user_err(http, msg, &format!("{} is not an id.", arg)),
+        }
+    }
+
+    remove_confirmed(ctx, msg, ids, dry)
+}
+
+#[command("remove-author")]
+#[only_in(guilds)]
+#[example("@Infi")]
+#[example("@Infi --dry-run")]
+#[checks(Permitted)]
+fn remove_author(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+
+    log!(
+        "{}   Args for remove-author: {}",
+        msg.author.name,
+        &args.message()
+    );
+    let dry = dry_run(&mut args);
+    let author = match args.single::<UserId>() {
+        Ok(x) => x,
+        Err(_) => return dm_user_err(http, msg, "Missing user."),
+    };
+
+    let ids = MESSAGES
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(_, m)| m.emote.author_id == author)
+        .map(|(id, _)| *id)
+        .collect();
+
+    remove_confirmed(ctx, msg, ids, dry)
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("--dry-run")]
+#[checks(Permitted)]
+fn resync(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+
+    log!("{}   Args for resync: {}", msg.author.name, &args.message());
+    let dry = dry_run(&mut args);
+    let me = ctx.cache.read().user.id;
+    let (decay, budget) = {
+        let config = CONFIG.read().unwrap();
+        (config.vote_half_life_days.is_some(), config.vote_budget)
+    };
+
+    // the reactions are fetched without the lock, votes keep coming in meanwhile
+    let tracked = MESSAGES
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(id, emsg)| (*id, emsg.messages[1].clone()))
+        .collect::<Vec<_>>();
+    let fetched = tracked
+        .par_iter()
+        .map(|(id, vote)| {
+            let ids = |users: Vec<user::User>| {
+                users
+                    .into_iter()
+                    .map(|u| u.id)
+                    .filter(|u| *u != me)
+                    .collect::<HashSet<_>>()
+            };
+            let up = ids(voters(&http, vote, "👍")?);
+            let down = ids(voters(&http, vote, "👎")?);
+            Ok((*id, up, down))
+        })
+        .collect::<serenity::Result<Vec<_>>>();
+    let fetched = match fetched {
+        Ok(x) => x,
+        Err(why) => {
+            dm_user(http, msg, "Discord error, pls try again later.");
+            return Err(CommandError(format!("Fetching votes: {:?}", why)));
+        }
+    };
+
+    let mut messages = MESSAGES.write().unwrap();
+    let (mut fixed, mut missed, mut taken_back, mut stale) = (0, 0, 0, 0);
+    for (id, up, down) in fetched {
+        let emsg = match messages.get_mut(&id) {
+            Some(x) => x,
+            None => continue,
+        };
+        let mut synced = emsg.clone();
+        if budget.is_some() {
+            missed += up.difference(&emsg.upvoted).count();
+            taken_back += emsg.upvoted.difference(&up).count();
+            synced.upvoted = up.clone();
+        }
+        // votes the bot missed count from the start of the voting, like the ones before there
+        // were vote times
+        if decay {
+            synced
+                .voted
+                .retain(|user, _| up.contains(user) || down.contains(user));
+            stale += emsg.voted.len() - synced.voted.len();
+        }
+
+        if synced.upvoted != emsg.upvoted || synced.voted.len() != emsg.voted.len() {
+            fixed += 1;
+            if !dry {
+                store::log(Op::Suggestion(Box::new(synced.clone())));
+                *emsg = synced;
+            }
+        }
+    }
+
+    let mut content = format!(
+        "{}Checked {} suggestions, {} {} out of sync.",
+        if dry { "Dry run: " } else { "" },
+        messages.len(),
+        fixed,
+        if dry { "are" } else { "were" }
+    );
+    if budget.is_some() {
+        content += &format!(
+            " {} 👍 were missing and {} taken back ones still counted against the vote budget.",
+            missed, taken_back
+        );
+    }
+    if decay {
+        content += &format!(" {} taken back votes still had a vote time.", stale);
+    }
+    if let Some(budget) = budget {
+        let mut used = HashMap::new();
+        for user in messages.values().flat_map(|e| e.upvoted.iter()) {
+            *used.entry(*user).or_insert(0) += 1;
+        }
+        let over = used.values().filter(|n| **n > budget).count();
+        if over > 0 {
+            content += &format!(" {} voters have more 👍 than the vote budget.", over);
+        }
+    }
+    drop(messages);
+
+    if !dry {
+        // the leaderboard and the milestones read the reactions again
+        STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+        VOTES_CHANGED.store(true, AtomicOrdering::Relaxed);
+        if fixed > 0 && !store::save(snapshot) {
+            dm_user(http, msg, "Internal error, pls DM Infi#8527.");
+            return Err(CommandError(String::from("Saving the resync failed")));
+        }
+    }
+    dm_user(http, msg, &content);
+    Ok(())
+}
+
+#[command("import-history")]
+#[only_in(guilds)]
+#[example("#emote-votes")]
+#[example("#emote-votes --dry-run")]
+#[checks(Permitted)]
+fn import_history(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+
+    log!(
+        "{}   Args for import-history: {}",
+        msg.author.name,
+        &args.message()
+    );
+    let dry = dry_run(&mut args);
+    let channel = match args.single::<ChannelId>() {
+        Ok(x) => x,
+        Err(_) => return dm_user_err(http, msg, "Missing channel."),
+    };
+
+    // results that are already in the history or still pending don't come twice
+    let mut known = HISTORY
+        .read()
+        .unwrap()
+        .iter()
+        .map(|f| f.id)
+        .collect::<HashSet<_>>();
+    known.extend(MESSAGES.read().unwrap().keys());
+
+    let (mut found, mut nameless) = (Vec::new(), 0);
+    let mut before = None;
+    loop {
+        let page = discord(&http, msg, "Fetching history", || {
+            channel.messages(&http, |r| {
+                r.limit(100);
+                if let Some(id) = before {
+                    r.before(id);
+                }
+                r
+            })
+        })?;
+        before = match page.last() {
+            Some(m) => Some(m.id),
+            None => break,
+        };
+
+        let voted = page
+            .into_iter()
+            .filter(|m| !known.contains(&m.id) && has_votes(m))
+            .collect::<Vec<_>>();
+        let imported = voted.par_iter().filter_map(imported).collect::<Vec<_>>();
+        nameless += voted.len() - imported.len();
+        found.extend(imported);
+    }
+
+    if found.is_empty() {
+        return dm_user_err(
+            http,
+            msg,
+            &format!(
+                "Found no votes to import, {} messages with votes had no name.",
+                nameless
+            ),
+        );
+    }
+    found.sort_by_key(|f| f.at);
+
+    if dry {
+        let listed = found
+            .iter()
+            .rev()
+            .take(SEARCH_LIMIT)
+            .map(|f| {
+                format!(
+                    "\n{} from {}: 👍 {} 👎 {}, {} on {}",
+                    f.name,
+                    f.author,
+                    f.votes.0,
+                    f.votes.1,
+                    if f.accepted { "accepted" } else { "rejected" },
+                    f.at.format("%Y-%m-%d")
+                )
+            })
+            .collect::<String>();
+        let content = format!(
+            "Dry run: would import {} results, {} messages with votes had no name. The newest:{}",
+            found.len(),
+            nameless,
+            listed
+        );
+        dm_user(http, msg, &content);
+        return Ok(());
+    }
+
+    let count = found.len();
+    let mut history = HISTORY.write().unwrap();
+    for finished in found {
+        store::log(Op::Finished(finished.clone()));
+        history.push(finished);
+    }
+    history.sort_by_key(|f| f.at);
+    drop(history);
+    store::save(snapshot);
+
+    let content = format!(
+        "Imported {} results, {} messages with votes had no name.",
+        count, nameless
+    );
+    dm_user(http, msg, &content);
+    Ok(())
+}
+
+fn has_votes(message: &Message) -> bool {
+    message
+        .reactions
+        .iter()
+        .any(|r| matches!(&r.reaction_type, ReactionType::Unicode(n) if n == "👍" || n == "👎"))
+}
+
+/// A vote of the time before the bot as result: the name is the first emote, `:name:`,
+/// `**name**`, file name or single word of the message that is a valid emote name, the author
+/// the first mentioned user or whoever posted it. More 👍 than 👎 counts as accepted.
+fn imported(message: &Message) -> Option<Finished> {
+    let valid = |n: &&str| {
+        (2..=32).contains(&n.chars().count())
+            && n.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let content = message.content.as_str();
+    let emotes = content.split('<').skip(1).filter_map(|rest| {
+        let rest = rest.strip_prefix('a').unwrap_or(rest).strip_prefix(':')?;
+        rest.split(':').next()
+    });
+    let colons = content.split(':').skip(1).step_by(2);
+    let bold = content.split("**").skip(1).step_by(2);
+    // the names Discord gives pasted images say nothing
+    let file = message
+        .attachments
+        .first()
+        .and_then(|a| a.filename.rsplit_once('.'))
+        .map(|(stem, _)| stem)
+        .filter(|stem| !["image", "unknown"].contains(&&*stem.to_lowercase()));
+    let word = Some(content.trim()).filter(|c| !c.contains(char::is_whitespace));
+    let name = emotes
+        .chain(colons)
+        .chain(bold)
+        .chain(file)
+        .chain(word)
+        .map(str::trim)
+        .find(valid)?;
+
+    // the bot's own reaction is no vote
+    let count = |vote: &str| {
+        message
+            .reactions
+            .iter()
+            .find(|r| matches!(&r.reaction_type, ReactionType::Unicode(n) if n == vote))
+            .map_or(0, |r| r.count - r.me as u64)
+    };
+    let votes = (count("👍"), count("👎"));
+    let author = message.mentions.first().unwrap_or(&message.author);
+    let hash = message
+        .attachments
+        .first()
+        .and_then(|a| a.download().ok())
+        .map_or(0, |data| file_hash(&data));
+
+    Some(Finished {
+        id: message.id,
+        name: name.to_string(),
+        author: author.name.clone(),
+        author_id: author.id,
+        hash,
+        legacy: false,
+        votes,
+        accepted: votes.0 > votes.1,
+        at: message.timestamp.with_timezone(&Utc),
+    })
+}
+
+/// Removes several suggestions at once after the mod confirmed the list with ✅, a dry run only
+/// lists them. They go into the trash like with `remove`, so `undo` works.
+fn remove_confirmed(ctx: &Context, msg: &Message, ids: Vec<MessageId>, dry: bool) -> CommandResult {
+    let http = ctx.http.clone();
+
+    if ids.is_empty() {
+        return dm_user_err(http, msg, "No suggestions to remove.");
+    }
+
+    let names = {
+        let messages = MESSAGES.read().unwrap();
+        let mut names = Vec::new();
+        for id in ids.iter() {
+            match messages.get(id) {
+                Some(m) => names.push(m.emote.name.clone()),
+                None => return dm_user_err(http, msg, &format!("{} is not in messages.", id)),
+            }
+        }
+        names
+    };
+    if dry {
+        let content = format!(
+            "Dry run, nothing was changed. Would remove {} suggestions: {}",
+            ids.len(),
+            names.join(", ")
+        );
+        dm_user(http, msg, &content);
+        return Ok(());
+    }
+
+    let mut prompt = discord_post(&http, msg, "Sending prompt", || {
+        msg.channel_id.send_message(&http, |m| {
+            m.content(format!(
+                "Remove {} suggestions: {}? React with ✅ within {} seconds to confirm.",
+                ids.len(),
+                names.join(", "),
+                CONFIRM_SECONDS
+            ));
+            m.reactions(vec![ReactionType::from("✅")])
+        })
+    })?;
+
+    // serenity 0.8 has no reaction collector, the command thread just polls
+    let confirmed = (0..CONFIRM_SECONDS / 2).any(|_| {
+        thread::sleep(time::Duration::from_secs(2));
+        rest::call("Fetching voters", || {
+            prompt.reaction_users(&http, "✅", None, None)
+        })
+        .map(|users| users.iter().any(|u| u.id == msg.author.id))
+        .unwrap_or(false)
+    });
+
+    let summary = if confirmed {
+        let mut messages = MESSAGES.write().unwrap();
+        let mut removed = Vec::new();
+        let mut failed = Vec::new();
+        for (id, name) in ids.into_iter().zip(names) {
+            match trash_suggestion(&http, &mut messages, id) {
+                Ok(_) => {
+                    let target = format!("{} ({})", name, short_id(id));
+                    audit(
+                        http.clone(),
+                        &msg.author.name,
+                        msg.author.id,
+                        "removed",
+                        target,
+                    );
+                    removed.push(name)
+                }
+                Err(why) => failed.push(format!("{} ({})", name, why)),
+            }
+        }
+
+        let mut summary = format!("Removed: {}", removed.join(", "));
+        if !failed.is_empty() {
+            summary += &format!("\nFailed: {}", failed.join(", "));
+        }
+        summary
+    } else {
+        String::from("Not confirmed, nothing was removed.")
+    };
+
+    if let Err(why) = rest::call("Editing message", || {
+        prompt.edit(&*ctx, |m| m.content(&summary))
+    }) {
+        log!("Could not edit prompt: {:?}", why);
+    }
+
+    Ok(())
+}
+
+/// Key of the suggestion whose file or vote message `msg` replies to.
+fn replied_suggestion(
+    msg: &Message,
+    messages: &HashMap<MessageId, EmoteMessage>,
+) -> Option<MessageId> {
+    suggestion_of(msg.message_reference.as_ref()?.message_id?, messages)
+}
+
+/// Key of the suggestion that `message` belongs to, it can be the file or the vote message.
+fn suggestion_of(
+    message: MessageId,
+    messages: &HashMap<MessageId, EmoteMessage>,
+) -> Option<MessageId> {
+    messages
+        .iter()
+        .find(|(_, m)| m.messages.iter().any(|m| m.id == message))
+        .map(|(id, _)| *id)
+}
+
+/// Records what a mod did and posts it in the `modlog_channel`.
+fn audit(http: Arc<Http>, actor: &str, actor_id: UserId, action: &str, target: String) {
+    let entry = ModAction {
+        actor: actor.to_string(),
+        actor_id,
+        action: action.to_string(),
+        target,
+        at: Utc::now(),
+    };
+    log!(
+        "{} ({}) {} {}",
+        entry.actor,
+        actor_id,
+        entry.action,
+        entry.target
+    );
+
+    let content = format!(
+        "{} ({}) {} {}, {}",
+        entry.actor,
+        actor_id,
+        entry.action,
+        entry.target,
+        local_time(entry.at)
+    );
+    let mut log = MODLOG.write().unwrap();
+    store::log(Op::ModAction(entry.clone()));
+    log.push(entry);
+    drop(log);
+
+    if let Some(channel) = CONFIG.read().unwrap().modlog_channel {
+        send(http, channel, &content);
+    }
+}
+
+/// Takes a suggestion out of the voting into the `REMOVED`, its messages say so until they get
+/// deleted after `UNDO_MINUTES`.
+fn trash_suggestion(
+    http: &Http,
+    messages: &mut HashMap<MessageId, EmoteMessage>,
+    id: MessageId,
+) -> Result<(), &'static str> {
+    let emsg = messages.get(&id).ok_or("ID is not in messages.")?;
+
+    let until = Utc::now() + Duration::minutes(UNDO_MINUTES);
+    let notice = [
+        format!("~~{}~~", emsg.messages[0].content),
+        format!(
+            "🗑️ **{}** was removed, a mod can bring it back with `undo {}` until {}.",
+            emsg.emote.name,
+            short_id(id),
+            local_time(until)
+        ),
+    ];
+    for (m, content) in emsg.messages.iter().zip(notice.iter()) {
+        rest::call("Editing message", || {
+            m.channel_id
+                .edit_message(http, m.id, |e| e.content(content))
+        })
+        .map_err(|_| "Internal error, pls try again later.")?;
+    }
+
+    if let Some(emsg) = take_suggestion(messages, id) {
+        let trashed = Trashed {
+            emsg,
+            at: Utc::now(),
+            purged: None,
+        };
+        let mut trash = REMOVED.write().unwrap();
+        store::log(Op::Trash(id, Box::new(trashed.clone())));
+        trash.insert(id, trashed);
+    }
+    Ok(())
+}
+
+/// Deletes the messages of a removed suggestion once its `UNDO_MINUTES` are over and records
+/// it as rejected.
+fn purge(http: &Http, id: MessageId) {
+    let mut trash = REMOVED.write().unwrap();
+    let mut trashed = match trash.remove(&id) {
+        Some(x) => x,
+        None => return,
+    };
+
+    // the votes and the file are gone with the messages, the seeded ones come back with `restore`
+    let found = cast_votes(http, &trashed.emsg.messages[1]).unwrap_or((0, 0));
+    let tally = (
+        found.0 + trashed.emsg.carried.0,
+        found.1 + trashed.emsg.carried.1,
+    );
+    let kept = trashed.emsg.messages[0].attachments.first().and_then(|a| {
+        let stored = trashed.emsg.emote.image.and_then(images::get).map(Ok);
+        stored
+            .unwrap_or_else(|| download::fetch(a))
+            .map_err(|why| log!("Could not keep the file of {}: {}", id, why))
+            .ok()
+            .map(|file| Purged {
+                file: base64::encode(&file),
+                filename: a.filename.clone(),
+                votes: tally,
+            })
+    });
+    for m in trashed.emsg.messages.iter() {
+        if let Err(why) = http.remove(m.channel_id, m.id) {
+            log!("Could not delete message {}: {:?}", m.id, why);
+        }
+    }
+    finish(id, trashed.emsg.emote.clone(), tally, false);
+
+    match kept {
+        Some(purged) => {
+            trashed.purged = Some(purged);
+            store::log(Op::Trash(id, Box::new(trashed.clone())));
+            trash.insert(id, trashed);
+        }
+        None => store::log(Op::TrashDone(id)),
+    }
+}
+
+/// Deletes the messages of a suggestion and records it as rejected.
+fn remove_suggestion(
+    http: &dyn Discord,
+    messages: &mut HashMap<MessageId, EmoteMessage>,
+    id: MessageId,
+) -> Result<(), &'static str> {
+    let m = messages.get(&id).ok_or("ID is not in messages.")?;
+
+    // the votes are gone with the messages
+    let tally = suggestion_votes(http, m).unwrap_or((0, 0));
+    if !m
+        .messages
+        .iter()
+        .map(|m| http.remove(m.channel_id, m.id))
+        .all(|r| r.is_ok())
+    {
+        return Err("Internal error, pls try again later.");
+    }
+
+    if let Some(emsg) = take_suggestion(messages, id) {
+        finish(id, emsg.emote, tally, false);
+    }
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("123456789")]
+#[example("123456789 --dry-run")]
+#[checks(Permitted)]
+fn revote(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut messages = MESSAGES.write().unwrap();
+
+    log!("{}   Args for revote: {}", msg.author.name, &args.message());
+    let dry = dry_run(&mut args);
+    let id = match args.single::<String>().ok().and_then(|a| parse_id(&a)) {
+        Some(x) => x,
+        None => return dm_user_err(http, msg, "Missing id."),
+    };
+
+    let emsg = match messages.get_mut(&id) {
+        Some(x) => x,
+        None => return dm_user_err(http, msg, "ID is not in messages."),
+    };
+
+    // the reactions are the votes, so clearing them resets everything
+    let vote_msg = &emsg.messages[1];
+    if dry {
+        let (pos, neg) = votes(&http, vote_msg).unwrap_or((0, 0));
+        let content = format!(
+            "Dry run, nothing was changed. Revote would clear {} 👍 and {} 👎 on {}.",
+            pos, neg, emsg.emote.name
+        );
+        dm_user(http, msg, &content);
+        return Ok(());
+    }
+    let open = voting_open();
+    discord(&http, msg, "Resetting reactions", || {
+        vote_msg.delete_reactions(&*ctx).and_then(|_| match open {
+            true => vote_msg
+                .react(&*ctx, "👍")
+                .and_then(|_| vote_msg.react(&*ctx, "👎")),
+            false => Ok(()),
+        })
+    })?;
+    emsg.since = Utc::now();
+    emsg.voted.clear();
+    emsg.upvoted.clear();
+    emsg.alerted.clear();
+    emsg.milestones.clear();
+    emsg.won = false;
+    emsg.rating = elo::START;
+    emsg.carried = (0, 0);
+    store::log(Op::Suggestion(Box::new(emsg.clone())));
+    STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+
+    dm_user(http, msg, "Done");
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("--dry-run")]
+#[checks(Permitted)]
+fn close(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    if !voting_open() {
+        return dm_user_err(
+            http,
+            msg,
+            "The round is still in its nomination, open the voting with phase voting first.",
+        );
+    }
+
+    let dry = dry_run(&mut args);
+    log!("{}   Closing the round, dry run: {}", msg.author.name, dry);
+    if let Err(why) = close_round(&http, &ctx.cache, msg.channel_id, dry) {
+        dm_user(http, msg, "Discord error, pls try again later.");
+        return Err(why);
+    }
+    if !dry {
+        let round = String::from("the round");
+        audit(http, &msg.author.name, msg.author.id, "closed", round);
+    }
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("10 remove")]
+#[checks(Permitted)]
+fn modlog(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let log = MODLOG.read().unwrap();
+
+    log!("{}   Args for modlog: {}", msg.author.name, &args.message());
+    let count = match args.single::<usize>() {
+        Ok(x) => x.min(MODLOG_LIMIT),
+        Err(_) => MODLOG_LIMIT,
+    };
+    // the rest filters by mod, action or target
+    let query = names::fold(args.rest().trim());
+
+    let lines = log
+        .iter()
+        .rev()
+        .filter(|a| {
+            query.is_empty()
+                || [&a.actor, &a.action, &a.target]
+                    .iter()
+                    .any(|t| names::fold(t).contains(&query))
+        })
+        .take(count)
+        .map(|a| {
+            format!(
+                "\n{} {} ({}) {} {}",
+                a.at.format("%Y-%m-%d %H:%M"),
+                a.actor,
+                a.actor_id,
+                a.action,
+                a.target
+            )
+        })
+        .collect::<Vec<_>>();
+
+    match lines.is_empty() {
+        true => dm_user(http, msg, "No mod actions found."),
+        false => dm_user(
+            http,
+            msg,
+            &format!("Mod actions, newest first:{}", lines.concat()),
+        ),
+    }
+    Ok(())
+}
+
+#[command("preview-announcement")]
+#[only_in(guilds)]
+#[example("round_closed")]
+#[checks(Permitted)]
+fn preview_announcement(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let messages = MESSAGES.read().unwrap();
+
+    // the pending suggestions stand in for the winners
+    let winners = messages
+        .iter()
+        .take(3)
+        .map(|(id, m)| format!("\n{} ({}) from: {}", m.emote.name, id, m.emote.author))
+        .collect::<String>();
+    let days = CONFIG.read().unwrap().nomination_days.unwrap_or(3);
+    let at = local_time(Utc::now() + Duration::days(days));
+    let vars = match args.rest().trim() {
+        "round_started" => vec![("voting_at", at)],
+        "voting_opened" => vec![("count", messages.len().to_string())],
+        "round_closed" => vec![
+            ("losers", messages.len().saturating_sub(3).to_string()),
+            ("winners", winners),
+            ("winner_count", messages.len().min(3).to_string()),
+            ("per_category", String::new()),
+            ("runoff", String::new()),
+            ("rotation", String::new()),
+            (
+                "next",
+                format!(
+                    "\nNominations for the next round are open, the voting starts {}.",
+                    at
+                ),
+            ),
+            ("date", Utc::now().format("%Y-%m-%d").to_string()),
+        ],
+        _ => {
+            return dm_user_err(
+                http,
+                msg,
+                "Pick round_started, voting_opened or round_closed.",
+            )
+        }
+    };
+    let announcement = templates::announcement(args.rest().trim(), &vars);
+    drop(messages);
+
+    discord_post(&http, msg, "Sending preview", || {
+        templates::post(&http, msg.channel_id, &announcement, None, true)
+    })?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[checks(Permitted)]
+fn simulate(ctx: &mut Context, msg: &Message) -> CommandResult {
+    let http = ctx.http.clone();
+
+    log!("{}   Simulating the close", msg.author.name);
+    let messages = MESSAGES.read().unwrap();
+    let Verdict {
+        in_runoff,
+        mut tallies,
+        passed,
+        runoff,
+    } = match judge(&http, &ctx.cache, &messages) {
+        Ok(x) => x,
+        Err(why) => {
+            dm_user(http, msg, "Discord error, pls try again later.");
+            return Err(why);
+        }
+    };
+    let (max_winners, epsilon) = {
+        let config = CONFIG.read().unwrap();
+        (config.max_winners, config.runoff_epsilon)
+    };
+
+    // same order as the ranking of `judge`, losers are sorted in as well
+    let score = |(id, tally): &(MessageId, Tally)| score(&messages[id], tally);
+    tallies.sort_by(|a, b| score(b).partial_cmp(&score(a)).unwrap_or(Ordering::Equal));
+    let mut content = format!(
+        "If the round closed now ({}, runoff within {}):",
+        max_winners.map_or_else(
+            || String::from("no limit of winners"),
+            |n| format!("{} winners at most", n)
+        ),
+        epsilon
+    );
+    for (place, entry) in tallies.iter().take(LIST_LIMIT).enumerate() {
+        let (id, ((pos, neg), _)) = entry;
+        let points = score(entry);
+        let outcome = if passed.iter().any(|(w, _)| w == id) {
+            "wins"
+        } else if runoff.is_some_and(|r| r.contains(id)) {
+            "runoff"
+        } else if points > 0.0 {
+            "no slot left"
+        } else {
+            "rejected"
+        };
+        content += &format!(
+            "\n{}. {}: {:.2} (👍 {} 👎 {}), {}",
+            place + 1,
+            messages[id].emote.name,
+            points,
+            pos,
+            neg,
+            outcome
+        );
+    }
+    if tallies.len() > LIST_LIMIT {
+        content += &format!("\n... and {} more", tallies.len() - LIST_LIMIT);
+    }
+    if !in_runoff.is_empty() {
+        content += &format!(
+            "\n{} suggestions are in a runoff already and wait for it.",
+            in_runoff.len()
+        );
+    }
+
+    discord_post(&http, msg, "Sending simulation", || {
+        msg.channel_id.say(&http, &content)
+    })?;
+
+    Ok(())
+}
+
+/// 👍 and 👎 of a suggestion, then weighted like `close` counts them.
+type Tally = ((u64, u64), (f64, f64));
+
+/// Outcome of closing the round now, see `judge`.
+struct Verdict {
+    /// suggestions in a runoff are decided there
+    in_runoff: HashSet<MessageId>,
+    tallies: Vec<(MessageId, Tally)>,
+    /// the winners with their weighted score, best first
+    passed: Vec<(MessageId, f64)>,
+    runoff: Option<[MessageId; 2]>,
+}
+
+/// Counts the valid votes of every suggestion and ranks them like `close` does, without changing
+/// anything.
+fn judge(
+    http: &Http,
+    cache: &CacheRwLock,
+    messages: &HashMap<MessageId, EmoteMessage>,
+) -> Result<Verdict, CommandError> {
+    let in_runoff = RUNOFFS
+        .read()
+        .unwrap()
+        .values()
+        .flat_map(|r| r.candidates.to_vec())
+        .collect::<HashSet<_>>();
+    let results = messages
+        .iter()
+        .filter(|(id, _)| !in_runoff.contains(id))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|(id, emsg)| (*id, valid_votes(http, cache, emsg)))
+        .collect::<Vec<_>>();
+
+    // a single failed fetch would otherwise throw away a possible winner
+    let mut tallies = Vec::new();
+    for (id, result) in results {
+        match result {
+            Ok(tally) => tallies.push((id, tally)),
+            Err(why) => return Err(CommandError(format!("Fetching votes: {:?}", why))),
+        }
+    }
+
+    let (max_winners, epsilon) = {
+        let config = CONFIG.read().unwrap();
+        (config.max_winners, config.runoff_epsilon)
+    };
+
+    // the best scores win, a close race for the last of `max_winners` goes into a runoff
+    let mut passed = tallies
+        .iter()
+        .map(|(id, tally)| (*id, score(&messages[id], tally)))
+        .filter(|(_, score)| *score > 0.0)
+        .collect::<Vec<_>>();
+    passed.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    let mut runoff = None;
+    if let Some(max) = max_winners.filter(|max| passed.len() > *max) {
+        if max > 0 && passed[max - 1].1 - passed[max].1 <= epsilon {
+            runoff = Some([passed[max - 1].0, passed[max].0]);
+            passed.truncate(max - 1);
+        } else {
+            passed.truncate(max);
+        }
+    }
+
+    Ok(Verdict {
+        in_runoff,
+        tallies,
+        passed,
+        runoff,
+    })
+}
+
+/// What `close` would delete and post, for a dry run.
+fn dry_close(
+    messages: &HashMap<MessageId, EmoteMessage>,
+    tallies: &[(MessageId, Tally)],
+    passed: &[(MessageId, f64)],
+    runoff: Option<[MessageId; 2]>,
+) -> String {
+    let line = |id: &MessageId| {
+        let emsg = &messages[id];
+        format!("\n{} ({}) from {}", emsg.emote.name, id, emsg.emote.author)
+    };
+    let winners = passed.iter().map(|(id, _)| line(id)).collect::<String>();
+    let lost = tallies
+        .iter()
+        .map(|(id, _)| id)
+        .filter(|id| {
+            !passed.iter().any(|(w, _)| w == *id) && !runoff.is_some_and(|r| r.contains(id))
+        })
+        .collect::<Vec<_>>();
+
+    let mut content = format!(
+        "Dry run, nothing was changed. Closing now would keep {} winners for accept:{}\nand delete {} suggestions:{}",
+        passed.len(),
+        winners,
+        lost.len(),
+        lost.iter().take(LIST_LIMIT).map(|id| line(id)).collect::<String>()
+    );
+    if lost.len() > LIST_LIMIT {
+        content += &format!("\n... and {} more", lost.len() - LIST_LIMIT);
+    }
+    if let Some(candidates) = runoff {
+        content += &format!(
+            "\nA runoff between {} and {} would decide the last slot.",
+            messages[&candidates[0]].emote.name, messages[&candidates[1]].emote.name
+        );
+    }
+    content
+}
+
+/// Decides the round and posts the results to `channel`, for `close` and the end of a round of
+/// `round_cron`.
+fn close_round(
+    http: &Arc<Http>,
+    cache: &CacheRwLock,
+    channel: ChannelId,
+    dry_run: bool,
+) -> CommandResult {
+    let mut messages = MESSAGES.write().unwrap();
+    let Verdict {
+        in_runoff,
+        tallies,
+        passed,
+        runoff,
+    } = judge(http, cache, &messages)?;
+    if dry_run {
+        let content = dry_close(&messages, &tallies, &passed, runoff);
+        rest::post("Sending message", || channel.say(http, &content))?;
+        return Ok(());
+    }
+
+    let mut round = ROUND.write().unwrap();
+    if round.closes_at.take().is_some() {
+        store::log(Op::Round(round.clone()));
+    }
+    drop(round);
+    let hall_of_fame = CONFIG.read().unwrap().hall_of_fame_channel;
+    let won = passed.iter().map(|(id, _)| *id).collect::<HashSet<_>>();
+
+    // the files are fetched before the losers get deleted
+    let (results_collage, collage_rankings) = {
+        let config = CONFIG.read().unwrap();
+        (config.results_collage, config.collage_rankings)
+    };
+    let collage = match results_collage {
+        true => {
+            let mut ranked = tallies
+                .iter()
+                .filter(|(id, _)| collage_rankings || won.contains(id))
+                .map(|(id, tally)| (*id, tally.0, score(&messages[id], tally)))
+                .collect::<Vec<_>>();
+            ranked.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(Ordering::Equal));
+            results_graphic(&messages, &ranked)
+        }
+        false => None,
+    };
+
+    let today = Utc::now().format("%Y-%m-%d");
+    let mut winners = String::new();
+    let mut authors = HashSet::new();
+    let mut winner_data = Vec::new();
+    let mut losers = 0;
+    let mut runoff_votes = HashMap::new();
+    for (id, (tally, _)) in tallies {
+        let (pos, neg) = tally;
+        if runoff.is_some_and(|r| r.contains(&id)) {
+            runoff_votes.insert(id, tally);
+            continue;
+        }
+
+        if won.contains(&id) {
+            let emsg = &messages[&id];
+            winners += &format!("\n{} ({}) from: {}", emsg.emote.name, id, emsg.emote.author);
+            authors.insert(emsg.emote.author_id);
+            winner_data.push(json!({
+                "id": id,
+                "name": emsg.emote.name,
+                "author": emsg.emote.author,
+                "category": emsg.emote.category,
+                "votes": [pos, neg],
+            }));
+
+            if let Some(channel) = hall_of_fame {
+                let content = templates::render(
+                    "hall_of_fame",
+                    "**{name}** from {author}\n👍 {up} 👎 {down}, {verdict}",
+                    &result_vars(emsg, (pos, neg), &format!("won on {}", today)),
+                );
+                if let Err(why) = repost_later(http, channel, emsg, &content) {
+                    log!("Could not post {} to the hall of fame: {:?}", id, why);
+                }
+            }
+            mark_won(&mut messages, id);
+            continue;
+        }
+
+        // without a record the suggestion stays, deleting it would lose it for good
+        if let Err(why) = archive(http, &messages[&id], (pos, neg), "rejected") {
+            log!("Could not archive {}: {:?}", id, why);
+            continue;
+        }
+
+        if let Some(emsg) = take_suggestion(&mut messages, id) {
+            for m in emsg.messages.iter() {
+                if let Err(why) = rest::call("Deleting message", || m.delete(http)) {
+                    log!("Could not delete message {}: {:?}", m.id, why);
+                }
+            }
+            finish(id, emsg.emote, (pos, neg), false);
+            losers += 1;
+        }
+    }
+
+    // the rotation makes its own room, its winners need no replacement poll
+    let mut rotation = String::new();
+    let rotation_slots = CONFIG.read().unwrap().rotation_slots;
+    if let Some(slots) = rotation_slots {
+        let rotating = passed
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| {
+                messages
+                    .get(id)
+                    .is_some_and(|m| m.emote.kind == Kind::Emoji)
+            })
+            .take(slots)
+            .collect::<Vec<_>>();
+        if !rotating.is_empty() {
+            rotation = format!("\n{}", rotate(http, &mut messages, &rotating, true));
+        }
+    }
+    poll_replacements(http, &messages);
+
+    let mut per_category = BTreeMap::new();
+    for emsg in won.iter().filter_map(|id| messages.get(id)) {
+        if let Some(category) = &emsg.emote.category {
+            *per_category.entry(category).or_insert(0) += 1;
+        }
+    }
+    let per_category = match per_category.is_empty() {
+        true => String::new(),
+        false => format!(
+            "\nWinners per category: {}",
+            per_category
+                .iter()
+                .map(|(category, n)| format!("{} {}", category, n))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut runoff_text = String::new();
+    if let Some(candidates) = runoff {
+        let votes = [runoff_votes[&candidates[0]], runoff_votes[&candidates[1]]];
+        runoff_text = match start_runoff(http, &messages, candidates, votes) {
+            Ok(()) => format!(
+                "\nThe last slot goes to the winner of the runoff between {} and {}.",
+                messages[&candidates[0]].emote.name, messages[&candidates[1]].emote.name
+            ),
+            Err(why) => {
+                log!("Could not start a runoff: {:?}", why);
+                String::from(
+                    "\nCould not start the runoff for the last slot, both stay in the voting.",
+                )
+            }
+        };
+    }
+    let (nomination_days, round_cron) = {
+        let config = CONFIG.read().unwrap();
+        (config.nomination_days, config.round_cron.clone())
+    };
+    let mut next = String::new();
+    // with `round_cron` the next round starts on its own time
+    if let Some(at) = round_cron.and_then(|cron| cron.next(Utc::now().with_timezone(&zone()))) {
+        next = format!(
+            "\nThe next round starts {}.",
+            local_time(at.with_timezone(&Utc))
+        );
+    } else if let Some(days) = nomination_days {
+        let at = Utc::now() + Duration::days(days);
+        start_nomination(Some(at));
+        next = format!(
+            "\nNominations for the next round are open, the voting starts {}.",
+            local_time(at)
+        );
+    }
+
+    let announcement = templates::announcement(
+        "round_closed",
+        &[
+            ("losers", losers.to_string()),
+            ("winners", winners.clone()),
+            ("winner_count", winner_data.len().to_string()),
+            ("per_category", per_category),
+            ("runoff", runoff_text),
+            ("next", next),
+            ("rotation", rotation),
+            ("date", today.to_string()),
+        ],
+    );
+    let content = announcement.text();
+    let file = collage.as_ref().map(|png| (&png[..], "results.png"));
+    let sent = match rest::post("Sending results", || {
+        templates::post(http, channel, &announcement, file, false)
+    }) {
+        Ok(x) => x,
+        Err(why) => return Err(CommandError(format!("Sending msg: {:?}", why))),
+    };
+    // the collage goes to Telegram straight from Discord
+    let collage_url = sent.attachments.first().map(|a| telegram::Media {
+        url: a.url.clone(),
+        animated: false,
+    });
+    webhooks::fire(
+        Event::Closed,
+        content.clone(),
+        json!({ "rejected": losers, "winners": winner_data.len(), "runoff": runoff.is_some() }),
+    );
+    telegram::post(content.clone(), collage_url);
+    if !winner_data.is_empty() {
+        webhooks::fire(
+            Event::Winners,
+            format!("Winners of the round closed on {}:{}", today, winners),
+            json!({ "winners": winner_data }),
+        );
+    }
+
+    // the next round starts with a fresh `vote_budget` for everyone
+    for (id, emsg) in messages.iter_mut() {
+        if !emsg.upvoted.is_empty() && !in_runoff.contains(id) {
+            emsg.upvoted.clear();
+            store::log(Op::Suggestion(Box::new(emsg.clone())));
+        }
+    }
+
+    if let Some(role) = CONFIG.read().unwrap().winner_role {
+        crown(http, role, authors, true);
+    }
+
+    if let Some(channel) = hall_of_fame.filter(|_| !winners.is_empty()) {
+        let content = format!("Winners of the round closed on {}:{}", today, winners);
+        let summary = rest::post("Sending message", || channel.say(http, &content))
+            .and_then(|m| rest::call("Pinning message", || m.pin(http)));
+        if let Err(why) = summary {
+            log!("Could not pin the round summary: {:?}", why);
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("nomination 3")]
+#[checks(Permitted)]
+fn phase(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+
+    log!("{}   Args for phase: {}", msg.author.name, &args.message());
+    match args.single::<String>().as_deref() {
+        Ok("nomination") => {
+            let rest = args.rest().trim();
+            let at = match rest.parse::<i64>() {
+                Ok(days) => Some(Utc::now() + Duration::days(days)),
+                Err(_) if rest.is_empty() => None,
+                Err(_) => match NaiveDateTime::parse_from_str(rest, "%Y-%m-%d %H:%M")
+                    .ok()
+                    .and_then(|at| zone().from_local_datetime(&at).earliest())
+                {
+                    Some(at) => Some(at.with_timezone(&Utc)),
+                    None => {
+                        return dm_user_err(
+                            http,
+                            msg,
+                            "Give the days or a local time like 2021-05-01 18:00.",
+                        )
+                    }
+                },
+            };
+            start_nomination(at);
+        }
+        Ok("voting") => open_voting(&http),
+        _ => return dm_user_err(http, msg, "Pick nomination [DAYS | TIME] or voting."),
+    }
+
+    dm_user(http, msg, "Done");
+    Ok(())
+}
+
+#[command]
+#[owners_only]
+fn botstats(ctx: &mut Context, msg: &Message) -> CommandResult {
+    let http = ctx.http.clone();
+
+    let content = usage::all()
+        .iter()
+        .map(|(command, u)| {
+            format!(
+                "\n{}: {} calls, {:.0}% failed, {} ms on average",
+                command,
+                u.calls,
+                u.failures as f64 * 100.0 / u.calls as f64,
+                u.total.as_millis() as u64 / u.calls
+            )
+        })
+        .collect::<String>();
+    let content = match content.is_empty() {
+        true => String::from("No commands since the start."),
+        false => format!("Commands since the start:{}", content),
+    };
+
+    discord_post(&http, msg, "Sending msg", || {
+        msg.channel_id.say(&http, &content)
+    })?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[checks(Permitted)]
+fn diag(ctx: &mut Context, msg: &Message) -> CommandResult {
+    let http = ctx.http.clone();
+
+    // one line per shard, the one that runs this command is marked
+    let shards = match ctx.data.read().get::<ShardManagerContainer>() {
+        Some(manager) => {
+            let manager = manager.lock();
+            let runners = manager.runners.lock();
+            let mut ids = runners.keys().collect::<Vec<_>>();
+            ids.sort_by_key(|id| id.0);
+
+            ids.into_iter()
+                .map(|id| {
+                    let runner = &runners[id];
+                    format!(
+                        "\nShard {}{}: {:?}, latency {}",
+                        id.0,
+                        if id.0 == ctx.shard_id { " (this)" } else { "" },
+                        runner.stage,
+                        runner
+                            .latency
+                            .map_or(String::from("unknown"), |l| format!("{}ms", l.as_millis()))
+                    )
+                })
+                .collect::<String>()
+        }
+        None => String::from(" unknown"),
+    };
+
+    let cache = {
+        let cache = ctx.cache.read();
+        format!(
+            "{} guilds, {} channels, {} users",
+            cache.guilds.len(),
+            cache.channels.len(),
+            cache.users.len()
+        )
+    };
+
+    let saved = |last: Option<(DateTime<Utc>, bool)>| match last {
+        Some((at, true)) => format!("ok, last saved {}", at.format("%Y-%m-%d %H:%M:%S UTC")),
+        Some((at, false)) => format!("failing, last try {}", at.format("%Y-%m-%d %H:%M:%S UTC")),
+        None => String::from("nothing saved since the start"),
+    };
+    let storage = match CONFIG.read().unwrap().store {
+        config::Backend::Memory => String::from("Memory, nothing gets saved"),
+        backend => format!("{:?} {}", backend, saved(store::last_save())),
+    };
+
+    let errors = ERRORS.read().unwrap();
+    let hour = errors
+        .iter()
+        .filter(|t| **t > Utc::now() - Duration::hours(1))
+        .count();
+
+    let calls = rest::metrics()
+        .iter()
+        .take(DIAG_CALLS)
+        .map(|(name, m)| {
+            format!(
+                "\n{}: {} calls, {} failed, {} retries, {} ms on average",
+                name,
+                m.calls,
+                m.errors,
+                m.retries,
+                m.millis / m.calls.max(1)
+            )
+        })
+        .collect::<String>();
+
+    // the two emoji pools fill up apart from each other
+    let pools = [false, true]
+        .iter()
+        .map(|animated| {
+            let waiting = MESSAGES
+                .read()
+                .unwrap()
+                .values()
+                .filter(|m| m.won && m.emote.kind == Kind::Emoji && m.emote.animated == *animated)
+                .count();
+            let free = slots::free_emojis(&http, GUILD, *animated)
+                .map_or_else(|_| String::from("?"), |n| n.to_string());
+            format!(
+                "{} {} free ({} winners waiting)",
+                free,
+                if *animated { "animated" } else { "static" },
+                waiting
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let content = format!(
+        "Shards:{}\nCache: {}\nPending: {} suggestions, {} variant polls, {} jobs\n\
+         Emote slots: {}\nJob queue: {}\nStorage: {}\nOutbox: {} messages waiting\nFailed commands: {} in \
+         the last hour, {} in the last day\nDiscord calls:{}",
+        shards,
+        cache,
+        MESSAGES.read().unwrap().len(),
+        VARIANTS.read().unwrap().len(),
+        jobs::len(),
+        pools.join(", "),
+        saved(jobs::last_save()),
+        storage,
+        outbox::len(),
+        hour,
+        errors.len(),
+        calls
+    );
+
+    discord_post(&http, msg, "Sending msg", || {
+        msg.channel_id.say(&http, &content)
+    })?;
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("123456789")]
+#[checks(Permitted)]
+fn accept(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut messages = MESSAGES.write().unwrap();
+
+    log!("{}   Args for accept: {}", msg.author.name, &args.message());
+    let id = match args.single::<String>().ok().and_then(|a| parse_id(&a)) {
+        Some(x) => x,
+        None => return dm_user_err(http, msg, "Missing id."),
+    };
+
+    let name = messages.get(&id).map(|m| m.emote.name.clone());
+    if let Err(why) = accept_suggestion(&http, &mut messages, id) {
+        return dm_user_err(http, msg, why);
+    }
+    let target = format!("{} ({})", name.unwrap_or_default(), short_id(id));
+    audit(
+        http.clone(),
+        &msg.author.name,
+        msg.author.id,
+        "accepted",
+        target,
+    );
+
+    dm_user(http, msg, "Done");
+    Ok(())
+}
+
+/// Uploads a suggestion as emote, sticker or sound and takes it out of the voting. The error is
+/// meant for the user, details are printed.
+fn accept_suggestion(
+    http: &Http,
+    messages: &mut HashMap<MessageId, EmoteMessage>,
+    id: MessageId,
+) -> Result<(), &'static str> {
+    let emsg = match messages.get(&id) {
+        Some(x) => x,
+        None => return Err("ID is not in messages."),
+    };
+
+    // the first bot message carries the already processed file
+    let attachment = match emsg.messages[0].attachments.first() {
+        Some(x) => x,
+        None => return Err("Suggestion has no attachment."),
+    };
+
+    let img = match file_of(emsg, attachment) {
+        Ok(x) => x,
+        Err(why) => {
+            log!("Attachment download: {:?}", why);
+            return Err("Attachment download failed, try again later.");
+        }
+    };
+
+    let free = match emsg.emote.kind {
+        Kind::Emoji => slots::free_emojis(http, GUILD, emsg.emote.animated),
+        Kind::Sticker => slots::free_stickers(http, GUILD),
+        Kind::Sound => slots::free_sounds(http, GUILD),
+    };
+
+    match free {
+        Ok(0) => return Err("There are no free slots left."),
+        Ok(_) => {}
+        Err(why) => {
+            log!("Counting slots: {:?}", why);
+            return Err("Discord error, pls try again later.");
+        }
+    }
+
+    let data = format!(
+        "data:image/{};base64,{}",
+        if emsg.emote.animated { "gif" } else { "png" },
+        base64::encode(&img)
+    );
+    let uploaded = match emsg.emote.kind {
+        Kind::Emoji => rest::post("Creating emoji", || {
+            GUILD.create_emoji(http, &emsg.emote.name, &data)
+        })
+        .map(|e| {
+            log!("Created emote {} ({})", e.name, e.id);
+            start_trial(&e);
+        })
+        .map_err(|why| format!("Creating emote: {:?}", why)),
+        Kind::Sticker => sticker::create(
+            http,
+            GUILD,
+            &emsg.emote.name,
+            &format!("Suggested by {}", emsg.emote.author),
+            img,
+        )
+        .map(|s| log!("Created sticker {} ({})", s.name, s.id))
+        .map_err(|why| format!("Creating sticker: {:?}", why)),
+        Kind::Sound => sound::create(
+            http,
+            GUILD,
+            &emsg.emote.name,
+            extension(&attachment.filename).unwrap_or("mp3"),
+            &img,
+        )
+        .map(|s| log!("Created sound {} ({})", s.name, s.sound_id))
+        .map_err(|why| format!("Creating sound: {:?}", why)),
+    };
+
+    if let Err(why) = uploaded {
+        log!("{}", why);
+        return Err("Discord error, pls try again later.");
+    }
+
+    if emsg.emote.kind == Kind::Emoji {
+        let peers = federate(http, &emsg.emote.name, &data, emsg.emote.animated);
+        let channel = CONFIG.read().unwrap().mod_channel.unwrap_or(CHANNEL);
+        if !peers.is_empty() {
+            let content = format!(
+                "{} went out to the partner servers: {}.",
+                emsg.emote.name,
+                peers.join(", ")
+            );
+            if let Err(why) = rest::post("Sending message", || channel.say(http, &content)) {
+                log!(
+                    "Could not report the federation of {}: {:?}",
+                    emsg.emote.name,
+                    why
+                );
+            }
+        }
+    }
+
+    // the upload already happened, a missing record is no reason to fail anymore
+    let tally = suggestion_votes(http, emsg).unwrap_or_else(|why| {
+        log!("Could not fetch the votes of {}: {:?}", id, why);
+        (0, 0)
+    });
+    if let Err(why) = archive(http, emsg, tally, "accepted") {
+        log!("Could not archive {}: {:?}", id, why);
+    }
+    if let Err(why) = news(http, emsg, tally) {
+        log!("Could not post {} in the news channel: {:?}", id, why);
+    }
+
+    for m in emsg.messages.iter() {
+        if let Err(why) = rest::call("Deleting message", || m.delete(http)) {
+            log!("Could not delete message {}: {:?}", m.id, why);
+        }
+    }
+    if let Some(emsg) = take_suggestion(messages, id) {
+        finish(id, emsg.emote, tally, true);
+    }
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[example("123456789")]
+#[checks(Permitted)]
+fn pick(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let http = ctx.http.clone();
+    let mut variants = VARIANTS.write().unwrap();
+
+    log!("{}   Args for pick: {}", msg.author.name, &args.message());
+    let id = match args.single::<u64>() {
+        Ok(id) => MessageId(id),
+        Err(_) => return dm_user_err(http, msg, "Missing id."),
+    };
+
+    let poll = match variants.get(&id) {
+        Some(x) => x,
+        None => return dm_user_err(http, msg, "ID is not in the variant polls."),
+    };
+
+    // the stored message has no up to date reactions
+    let current = discord(&http, msg, "Fetching variants", || {
+        poll.message.channel_id.message(&http, poll.message.id)
+    })?;
+
+    let (a, b) = current
+        .reactions
+        .iter()
+        .fold((0, 0), |(a, b), r| match &r.reaction_type {
+            ReactionType::Unicode(n) if n == VARIANT_A => (r.count, b),
+            ReactionType::Unicode(n) if n == VARIANT_B => (a, r.count),
+            _ => (a, b),
+        });
+
+    let winner = match a.cmp(&b) {
+        Ordering::Greater => 0,
+        Ordering::Less => 1,
+        Ordering::Equal => {
+            return dm_user_err(http, msg, "It's a tie, let the poll run a bit longer.")
+        }
+    };
+
+    let stored = poll.images[winner].and_then(images::get).map(Ok);
+    let buf = match stored.or_else(|| poll.message.attachments.get(winner).map(|a| a.download())) {
+        Some(Ok(x)) => x,
+        Some(Err(why)) => {
+            dm_user(http, msg, "Attachment download failed, try again later.");
+            return Err(CommandError(format!("Attachment download: {:?}", why)));
+        }
+        None => return dm_user_err(http, msg, "Variant poll has no attachment."),
+    };
+
+    let emote = Emote {
+        name: poll.name.clone(),
+        author: poll.author.clone(),
+        author_id: poll.author_id,
+        kind: Kind::Emoji,
+        animated: poll.animated[winner],
+        hash: poll.hashes[winner],
+        legacy: poll.legacy,
+        category: poll.category.clone(),
+        image: None,
+    };
+    let filename = format!(
+        "{}.{}",
+        emote.name,
+        if emote.animated { "gif" } else { "png" }
+    );
+    let emsg = match publish(&http, emote, &buf, &filename) {
+        Ok(x) => x,
+        Err(why) => {
+            dm_user(http, msg, "Discord error, pls try again later.");
+            return Err(CommandError(why));
+        }
+    };
+    add_suggestion(&mut MESSAGES.write().unwrap(), emsg);
+
+    if let Err(why) = rest::call("Deleting message", || poll.message.delete(&http)) {
+        log!("Could not delete message {}: {:?}", poll.message.id, why);
+    }
+    store::log(Op::VariantDone(id));
+    variants.remove(&id);
+
+    dm_user(http, msg, "Done");
+    Ok(())
+}
+
+/// PNG of the `ranked` suggestions with their votes, see `collage`. Sounds and files that can't
+/// be fetched are left out, `None` if nothing is left.
+fn results_graphic(
+    messages: &HashMap<MessageId, EmoteMessage>,
+    ranked: &[(MessageId, (u64, u64), f64)],
+) -> Option<Vec<u8>> {
+    let entries = ranked
+        .par_iter()
+        .filter_map(|(id, (pos, neg), _)| {
+            let emsg = messages.get(id).filter(|e| e.emote.kind != Kind::Sound)?;
+            let data = file_of(emsg, emsg.messages[0].attachments.first()?).ok()?;
+            Some(collage::Entry {
+                image: image::load_from_memory(&data).ok()?,
+                name: emsg.emote.name.clone(),
+                score: format!("+{} -{}", pos, neg),
+            })
+        })
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        return None;
+    }
+
+    let rendered = pool::run(move || {
+        let mut buf = Vec::new();
+        DynamicImage::ImageRgba8(collage::render(&entries))
+            .write_to(&mut buf, ImageOutputFormat::Png)
+            .map(|_| buf)
+    });
+    match rendered {
+        Ok(Ok(buf)) => Some(buf),
+        Ok(Err(why)) => {
+            log!("Could not encode the results graphic: {:?}", why);
+            None
+        }
+        Err(why) => {
+            log!("Could not render the results graphic: {:?}", why);
+            None
+        }
+    }
+}
+
+/// Posts the runoff between two suggestions of `messages`, it ends after `RUNOFF_HOURS`.
+fn start_runoff(
+    http: &Http,
+    messages: &HashMap<MessageId, EmoteMessage>,
+    candidates: [MessageId; 2],
+    votes: [(u64, u64); 2],
+) -> serenity::Result<()> {
+    let [a, b] = [&messages[&candidates[0]], &messages[&candidates[1]]];
+    let link = |emsg: &EmoteMessage| {
+        format!(
+            "https://discord.com/channels/{}/{}/{}",
+            GUILD, emsg.messages[1].channel_id, emsg.messages[1].id
+        )
+    };
+
+    let content = format!(
+        "Runoff for the last winner slot of the round, vote for one within {} hours:\n\
+         {} **{}** from {} {}\n{} **{}** from {} {}",
+        RUNOFF_HOURS,
+        VARIANT_A,
+        a.emote.name,
+        a.emote.author,
+        link(a),
+        VARIANT_B,
+        b.emote.name,
+        b.emote.author,
+        link(b)
+    );
+    let message = rest::post("Sending runoff", || CHANNEL.say(http, &content))?;
+    rest::call("Adding reactions", || {
+        message
+            .react(http, VARIANT_A)
+            .and_then(|_| message.react(http, VARIANT_B))
+    })?;
+
+    let runoff = Runoff {
+        message,
+        candidates,
+        votes,
+        ends: Utc::now() + Duration::hours(RUNOFF_HOURS),
+    };
+    let mut runoffs = RUNOFFS.write().unwrap();
+    store::log(Op::Runoff(runoff.message.id, Box::new(runoff.clone())));
+    runoffs.insert(runoff.message.id, runoff);
+
+    Ok(())
+}
+
+/// Does what's due every minute: opens the voting after a scheduled nomination, settles the
+/// runoffs that ended and keeps the countdown and the leaderboard up to date. Waiting for the
+/// next minute bundles the votes in between into one edit of the leaderboard.
+fn schedule(http: Arc<Http>, cache: CacheRwLock) {
+    thread::spawn(move || {
+        // a pass takes longer than the minute, so every minute since the last one is checked
+        let mut checked = Utc::now();
+        for minute in 1.. {
+            thread::sleep(time::Duration::from_secs(60));
+            let mut changed = false;
+
+            let round_cron = CONFIG.read().unwrap().round_cron.clone();
+            let now = Utc::now();
+            let due = round_cron
+                .and_then(|cron| cron.next(checked.with_timezone(&zone())))
+                .is_some_and(|at| at <= now);
+            checked = now;
+            if due {
+                changed |= start_round(&http);
+            }
+
+            if CONFIG.read().unwrap().auto_expire && voting_open() {
+                changed |= expire(&http, &cache);
+            }
+
+            let voted = std::mem::take(&mut *VOTED.write().unwrap());
+            for id in voted {
+                alert(&http, id);
+            }
+
+            let closes_at = ROUND.read().unwrap().closes_at;
+            if closes_at.is_some_and(|at| at <= Utc::now()) && voting_open() {
+                if let Err(why) = close_round(&http, &cache, CHANNEL, false) {
+                    log!("Could not close the round: {:?}", why);
+                }
+                changed = true;
+            }
+
+            let voting_at = ROUND.read().unwrap().voting_at;
+            if voting_at.is_some_and(|at| at <= Utc::now()) {
+                open_voting(&http);
+                changed = true;
+            }
+
+            let ended = RUNOFFS
+                .read()
+                .unwrap()
+                .values()
+                .filter(|r| r.ends <= Utc::now())
+                .map(|r| r.message.id)
+                .collect::<Vec<_>>();
+            for id in ended {
+                if let Err(why) = settle_runoff(&http, id) {
+                    log!("Could not settle runoff {}: {:?}", id, why);
+                }
+                changed = true;
+            }
+
+            // removed suggestions lose their messages first and their file later
+            let expired = REMOVED
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|(_, t)| match t.purged {
+                    None => t.at + Duration::minutes(UNDO_MINUTES) <= Utc::now(),
+                    Some(_) => t.at + Duration::days(RESTORE_DAYS) <= Utc::now(),
+                })
+                .map(|(id, t)| (*id, t.purged.is_some()))
+                .collect::<Vec<_>>();
+            for (id, purged) in expired {
+                if purged {
+                    store::log(Op::TrashDone(id));
+                    REMOVED.write().unwrap().remove(&id);
+                } else {
+                    purge(&http, id);
+                }
+                changed = true;
+            }
+
+            let ended = REPLACEMENTS
+                .read()
+                .unwrap()
+                .values()
+                .filter(|r| r.ends <= Utc::now())
+                .map(|r| r.message.id)
+                .collect::<Vec<_>>();
+            for id in ended {
+                if let Err(why) = settle_replacement(&http, id) {
+                    log!("Could not settle replacement poll {}: {:?}", id, why);
+                }
+                changed = true;
+            }
+            match confirm_trials(&http, &cache) {
+                Ok(ran) => changed |= ran,
+                Err(why) => log!("Could not run the emote trials: {:?}", why),
+            }
+            changed |= USES_CHANGED.swap(false, AtomicOrdering::Relaxed);
+
+            if CONFIG.read().unwrap().milestone_dms
+                && voting_open()
+                && VOTES_CHANGED.swap(false, AtomicOrdering::Relaxed)
+            {
+                match milestones(&http) {
+                    Ok(reached) => changed |= reached,
+                    Err(why) => {
+                        log!("Could not check the milestones: {:?}", why);
+                        VOTES_CHANGED.store(true, AtomicOrdering::Relaxed);
+                    }
+                }
+            }
+
+            let (show_countdown, show_leaderboard) = {
+                let config = CONFIG.read().unwrap();
+                (config.countdown, config.leaderboard)
+            };
+            if minute % COUNTDOWN_MINUTES == 0 && show_countdown {
+                match countdown(&http) {
+                    Ok(posted) => changed |= posted,
+                    Err(why) => log!("Could not update the countdown: {:?}", why),
+                }
+            }
+            if show_leaderboard && STANDINGS_CHANGED.swap(false, AtomicOrdering::Relaxed) {
+                match standings(&http) {
+                    Ok(posted) => changed |= posted,
+                    Err(why) => {
+                        log!("Could not update the leaderboard: {:?}", why);
+                        STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+                    }
+                }
+            }
+
+            if changed {
+                store::save(snapshot);
+            }
+        }
+    });
+}
+
+/// A zone of the time zone database like `Europe/Berlin` or `UTC`, or a whole hour offset like
+/// `+02:00` or `-0500`.
+fn time_zone(value: &str) -> Option<Tz> {
+    if let Ok(zone) = value.parse::<Tz>() {
+        return Some(zone);
+    }
+    if value.eq_ignore_ascii_case("utc") {
+        return Some(Tz::UTC);
+    }
+
+    let sign = match value.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits = value[1..].replace(':', "");
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = (
+        digits[..2].parse::<i32>().ok()?,
+        digits[2..].parse::<i32>().ok()?,
+    );
+    fixed_zone(sign * (hours * 60 + minutes))
+}
+
+/// The `Etc/GMT` zone that is `minutes` east of UTC, those only exist for whole hours.
+pub fn fixed_zone(minutes: i32) -> Option<Tz> {
+    if minutes % 60 != 0 || !(-12 * 60..=14 * 60).contains(&minutes) {
+        return None;
+    }
+
+    // the signs of the `Etc` zones are the other way around
+    match minutes / 60 {
+        0 => Some(Tz::UTC),
+        hours => format!("Etc/GMT{:+}", -hours).parse().ok(),
+    }
+}
+
+/// Time zone of the guild.
+fn zone() -> Tz {
+    GUILDS
+        .read()
+        .unwrap()
+        .get(&GUILD)
+        .map_or(Tz::UTC, |config| config.timezone)
+}
+
+/// `at` in the time zone of the guild, followed by a timestamp every client shows in its own.
+fn local_time(at: DateTime<Utc>) -> String {
+    format!(
+        "{} (<t:{}:f>)",
+        at.with_timezone(&zone()).format("%Y-%m-%d %H:%M %Z"),
+        at.timestamp()
+    )
+}
+
+/// Time until `until` like `2d 3h 15m, at ...`.
+fn time_left(until: DateTime<Utc>) -> String {
+    let minutes = (until - Utc::now()).num_minutes().max(0);
+    format!(
+        "{}d {}h {}m, at {}",
+        minutes / 1440,
+        minutes / 60 % 24,
+        minutes % 60,
+        local_time(until)
+    )
+}
+
+/// Edits the countdown message, or posts and pins a new one if there is none. Returns whether it
+/// was posted.
+fn countdown(http: &Http) -> serenity::Result<bool> {
+    let messages = MESSAGES.read().unwrap();
+    let mut round = ROUND.write().unwrap();
+
+    // the round ends at `closes_at` or with the last pending suggestion
+    let content = match (round.phase, round.voting_at) {
+        (Phase::Nomination, Some(at)) => format!(
+            "Nomination, the voting starts in {}. {} suggestions so far.",
+            time_left(at),
+            messages.len()
+        ),
+        (Phase::Nomination, None) => format!(
+            "Nomination, the voting starts when a mod opens it. {} suggestions so far.",
+            messages.len()
+        ),
+        (Phase::Voting, _) => match round
+            .closes_at
+            .or_else(|| messages.values().map(deadline).max())
+        {
+            Some(end) => format!(
+                "The voting of this round ends in {}. {} suggestions.",
+                time_left(end),
+                messages.len()
+            ),
+            None => String::from("No suggestions in the voting right now."),
+        },
+    };
+    drop(messages);
+
+    let posted = pinned(http, &mut round.countdown, &content)?;
+    if posted {
+        store::log(Op::Round(round.clone()));
+    }
+
+    Ok(posted)
+}
+
+/// Edits the leaderboard with the current top suggestions, see `countdown`.
+fn standings(http: &Http) -> serenity::Result<bool> {
+    let messages = MESSAGES.read().unwrap();
+
+    let content = match voting_open() {
+        true => {
+            let mut tallies = messages
+                .values()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|emsg| suggestion_votes(http, emsg).map(|tally| (emsg, tally)))
+                .collect::<serenity::Result<Vec<_>>>()?;
+            tallies.sort_by_key(|(_, (pos, neg))| -(*pos as i64 - *neg as i64));
+
+            let top = tallies
+                .iter()
+                .take(LEADERBOARD_SIZE)
+                .enumerate()
+                .map(|(n, (emsg, (pos, neg)))| {
+                    format!(
+                        "\n{}. **{}** from {}, 👍 {} 👎 {}",
+                        n + 1,
+                        emsg.emote.name,
+                        emsg.emote.author,
+                        pos,
+                        neg
+                    )
+                })
+                .collect::<String>();
+            match top.is_empty() {
+                true => String::from("No suggestions in the voting right now."),
+                false => format!("Current standings:{}", top),
+            }
+        }
+        false => String::from("The standings show up once the voting opens."),
+    };
+    drop(messages);
+
+    let mut round = ROUND.write().unwrap();
+    let posted = pinned(http, &mut round.leaderboard, &content)?;
+    if posted {
+        store::log(Op::Round(round.clone()));
+    }
+
+    Ok(posted)
+}
+
+/// Edits the pinned message `id` of the voting channel, if it's gone a new one gets posted and
+/// pinned. Returns whether that happened.
+fn pinned(http: &Http, id: &mut Option<MessageId>, content: &str) -> serenity::Result<bool> {
+    if let Some(id) = *id {
+        if CHANNEL
+            .edit_message(http, id, |m| m.content(content))
+            .is_ok()
+        {
+            return Ok(false);
+        }
+    }
+
+    // the old one is gone, a mod deleted it
+    let message = rest::post("Sending message", || CHANNEL.say(http, content))?;
+    rest::call("Pinning message", || message.pin(http))?;
+    *id = Some(message.id);
+
+    Ok(true)
+}
+
+/// Starts the nomination, new suggestions get no votes until `open_voting`.
+fn start_nomination(voting_at: Option<DateTime<Utc>>) {
+    let mut round = ROUND.write().unwrap();
+    round.phase = Phase::Nomination;
+    round.voting_at = voting_at;
+    store::log(Op::Round(round.clone()));
+}
+
+/// Decides every suggestion whose `voting_days` are over on its own, see `auto_expire`. Returns
+/// whether one was decided.
+fn expire(http: &Arc<Http>, cache: &CacheRwLock) -> bool {
+    let mut messages = MESSAGES.write().unwrap();
+    let in_runoff = RUNOFFS
+        .read()
+        .unwrap()
+        .values()
+        .flat_map(|r| r.candidates.to_vec())
+        .collect::<HashSet<_>>();
+    let unuploaded = UNUPLOADED.read().unwrap().clone();
+
+    let now = Utc::now();
+    let expired = messages
+        .iter()
+        .filter(|(id, emsg)| {
+            deadline(emsg) <= now && !in_runoff.contains(id) && !unuploaded.contains(id)
+        })
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    let min_score = CONFIG.read().unwrap().expire_min_score;
+
+    let mut changed = false;
+    for id in expired {
+        let tally = match valid_votes(http, cache, &messages[&id]) {
+            Ok(x) => x,
+            Err(why) => {
+                log!("Could not fetch the votes of {}: {:?}", id, why);
+                continue;
+            }
+        };
+        let (pos, neg) = tally.0;
+        let name = messages[&id].emote.name.clone();
+
+        if score(&messages[&id], &tally) > min_score {
+            match accept_suggestion(http, &mut messages, id) {
+                Ok(()) => send(
+                    http.clone(),
+                    CHANNEL,
+                    &format!(
+                        "{} passed with 👍 {} 👎 {} and got uploaded.",
+                        name, pos, neg
+                    ),
+                ),
+                Err(why) => {
+                    UNUPLOADED.write().unwrap().insert(id);
+                    send(
+                        http.clone(),
+                        CHANNEL,
+                        &format!(
+                            "{} passed with 👍 {} 👎 {}, but the upload failed: {} Upload it with accept {}.",
+                            name, pos, neg, why, id
+                        ),
+                    );
+                }
+            }
+            changed = true;
+            continue;
+        }
+
+        // without a record the suggestion stays, deleting it would lose it for good
+        if let Err(why) = archive(http, &messages[&id], (pos, neg), "rejected") {
+            log!("Could not archive {}: {:?}", id, why);
+            continue;
+        }
+        if let Some(emsg) = take_suggestion(&mut messages, id) {
+            for m in emsg.messages.iter() {
+                if let Err(why) = rest::call("Deleting message", || m.delete(http)) {
+                    log!("Could not delete message {}: {:?}", m.id, why);
+                }
+            }
+            log!("{} expired with 👍 {} 👎 {}", emsg.emote.name, pos, neg);
+            finish(id, emsg.emote, (pos, neg), false);
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+/// Starts a round of `round_cron`, the scheduler closes it after `round_voting_days` of voting.
+/// A round that is still nominating or has a closing time is left alone, returns whether one
+/// started.
+fn start_round(http: &Arc<Http>) -> bool {
+    {
+        let round = ROUND.read().unwrap();
+        if round.phase == Phase::Nomination || round.closes_at.is_some() {
+            log!("round_cron is due but the last round is still open, it keeps going");
+            return false;
+        }
+    }
+    let (nomination_days, voting_days) = {
+        let config = CONFIG.read().unwrap();
+        (config.nomination_days, config.round_voting_days)
+    };
+
+    let voting_at = match nomination_days {
+        Some(days) => {
+            let at = Utc::now() + Duration::days(days);
+            start_nomination(Some(at));
+            let started =
+                templates::announcement("round_started", &[("voting_at", local_time(at))]);
+            announce(http, &started);
+            at
+        }
+        None => {
+            open_voting(http);
+            Utc::now()
+        }
+    };
+
+    let mut round = ROUND.write().unwrap();
+    round.closes_at = Some(voting_at + Duration::days(voting_days));
+    store::log(Op::Round(round.clone()));
+    true
+}
+
+/// Seeds the votes of every nominated suggestion at once, their voting starts now.
+fn open_voting(http: &Arc<Http>) {
+    let mut messages = MESSAGES.write().unwrap();
+    let mut round = ROUND.write().unwrap();
+    if round.phase == Phase::Voting {
+        return;
+    }
+
+    for emsg in messages.values_mut() {
+        let vote_msg = &emsg.messages[1];
+        let seeded = rest::call("Adding reactions", || {
+            vote_msg
+                .react(http, "👍")
+                .and_then(|_| vote_msg.react(http, "👎"))
+        });
+        if let Err(why) = seeded {
+            log!("Could not add the votes to {}: {:?}", vote_msg.id, why);
+        }
+        emsg.since = Utc::now();
+        store::log(Op::Suggestion(Box::new(emsg.clone())));
+    }
+    STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+
+    round.phase = Phase::Voting;
+    round.voting_at = None;
+    store::log(Op::Round(round.clone()));
+    let opened = templates::announcement("voting_opened", &[("count", messages.len().to_string())]);
+    drop(round);
+    drop(messages);
+    announce(http, &opened);
+}
+
+/// Posts a round announcement in the voting channel, plain ones go through the outbox.
+fn announce(http: &Arc<Http>, announcement: &templates::Announcement) {
+    if announcement.embed.is_none() {
+        return send(http.clone(), CHANNEL, &announcement.content);
+    }
+    let posted = rest::post("Sending announcement", || {
+        templates::post(http, CHANNEL, announcement, None, false)
+    });
+    if let Err(why) = posted {
+        log!("Could not post the announcement: {:?}", why);
+    }
+}
+
+/// The candidate with more votes wins the slot and the other one gets rejected. A removed or
+/// accepted candidate loses, a tie runs the runoff again.
+fn settle_runoff(http: &Http, id: MessageId) -> serenity::Result<()> {
+    let mut messages = MESSAGES.write().unwrap();
+    let mut runoffs = RUNOFFS.write().unwrap();
+    let runoff = match runoffs.get_mut(&id) {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
+    let current = rest::call("Fetching message", || {
+        runoff.message.channel_id.message(http, id)
+    })?;
+    let (a, b) = current
+        .reactions
+        .iter()
+        .fold((0, 0), |(a, b), r| match &r.reaction_type {
+            ReactionType::Unicode(n) if n == VARIANT_A => (r.count, b),
+            ReactionType::Unicode(n) if n == VARIANT_B => (a, r.count),
+            _ => (a, b),
+        });
+
+    let present = runoff.candidates.map(|c| messages.contains_key(&c));
+    let winner = match (present, a.cmp(&b)) {
+        ([true, false], _) | ([true, true], Ordering::Greater) => Some(0),
+        ([false, true], _) | ([true, true], Ordering::Less) => Some(1),
+        ([false, false], _) => None,
+        ([true, true], Ordering::Equal) => {
+            runoff.ends = Utc::now() + Duration::hours(RUNOFF_HOURS);
+            store::log(Op::Runoff(id, Box::new(runoff.clone())));
+            runoff.message.reply(
+                http,
+                format!(
+                    "Still a tie, the runoff goes on for {} hours.",
+                    RUNOFF_HOURS
+                ),
+            )?;
+            return Ok(());
+        }
+    };
+
+    let runoff = runoff.clone();
+    store::log(Op::RunoffDone(id));
+    runoffs.remove(&id);
+    drop(runoffs);
+
+    let winner = match winner {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+    let (won, lost) = (runoff.candidates[winner], runoff.candidates[1 - winner]);
+
+    mark_won(&mut messages, won);
+    poll_replacements(http, &messages);
+    let emsg = &messages[&won];
+    let content = templates::render(
+        "runoff_won",
+        "**{name}** won the runoff, upload it with accept:\n{name} ({id}) from: {author}",
+        &[
+            ("name", emsg.emote.name.clone()),
+            ("author", emsg.emote.author.clone()),
+            ("id", won.to_string()),
+        ],
+    );
+    rest::post("Sending message", || CHANNEL.say(http, &content))?;
+    telegram::post(content.clone(), None);
+    webhooks::fire(
+        Event::Winners,
+        content,
+        json!({ "winners": [{
+            "id": won,
+            "name": emsg.emote.name,
+            "author": emsg.emote.author,
+            "category": emsg.emote.category,
+            "votes": [runoff.votes[winner].0, runoff.votes[winner].1],
+        }], "runoff": true }),
+    );
+
+    let config = CONFIG.read().unwrap();
+    let (hall_of_fame, winner_role) = (config.hall_of_fame_channel, config.winner_role);
+    drop(config);
+    if let Some(channel) = hall_of_fame {
+        let content = templates::render(
+            "hall_of_fame",
+            "**{name}** from {author}\n👍 {up} 👎 {down}, {verdict}",
+            &result_vars(
+                emsg,
+                runoff.votes[winner],
+                &format!("won the runoff on {}", Utc::now().format("%Y-%m-%d")),
+            ),
+        );
+        if let Err(why) = repost(http, channel, emsg, content) {
+            log!("Could not post {} to the hall of fame: {:?}", won, why);
+        }
+    }
+    if let Some(role) = winner_role {
+        crown(
+            http,
+            role,
+            std::iter::once(emsg.emote.author_id).collect(),
+            false,
+        );
+    }
+
+    let rotation_slots = CONFIG.read().unwrap().rotation_slots;
+    let rotating = ROTATION.read().unwrap().len();
+    if rotation_slots.is_some_and(|slots| rotating < slots) && emsg.emote.kind == Kind::Emoji {
+        let content = rotate(http, &mut messages, &[won], false);
+        rest::post("Sending message", || CHANNEL.say(http, &content))?;
+    }
+
+    if !messages.contains_key(&lost) {
+        return Ok(());
+    }
+    let votes = runoff.votes[1 - winner];
+    // without a record the suggestion stays, deleting it would lose it for good
+    archive(http, &messages[&lost], votes, "lost the runoff")?;
+    if let Some(emsg) = take_suggestion(&mut messages, lost) {
+        for m in emsg.messages.iter() {
+            if let Err(why) = rest::call("Deleting message", || m.delete(http)) {
+                log!("Could not delete message {}: {:?}", m.id, why);
+            }
+        }
+        finish(lost, emsg.emote, votes, false);
+    }
+
+    Ok(())
+}
+
+/// Starts a `Replacement` poll for the winners whose pool of emote slots is full with
+/// `replacement_polls`. The oldest winners get the free slots, every open poll frees one more.
+fn poll_replacements(http: &Http, messages: &HashMap<MessageId, EmoteMessage>) {
+    if !CONFIG.read().unwrap().replacement_polls {
+        return;
+    }
+    let guild = match rest::call("Fetching guild", || GUILD.to_partial_guild(http)) {
+        Ok(x) => x,
+        Err(why) => {
+            log!("Could not count the emote slots: {:?}", why);
+            return;
+        }
+    };
+    let uses = EMOJI_USES.read().unwrap().clone();
+
+    let mut replacements = REPLACEMENTS.write().unwrap();
+    for animated in [false, true].iter() {
+        let mut waiting = messages
+            .values()
+            .filter(|m| m.won && m.emote.kind == Kind::Emoji && m.emote.animated == *animated)
+            .collect::<Vec<_>>();
+        waiting.sort_by_key(|m| m.since);
+        let polled = waiting
+            .iter()
+            .filter(|m| replacements.values().any(|r| r.winner == m.messages[1].id))
+            .count();
+        let mut pool = guild
+            .emojis
+            .values()
+            .filter(|e| e.animated == *animated && !e.managed)
+            .collect::<Vec<_>>();
+        let free = slots::max_emojis(guild.premium_tier).saturating_sub(pool.len());
+        let short = waiting.len().saturating_sub(free + polled);
+
+        // an emote only gets offered in one poll at a time
+        let offered = replacements
+            .values()
+            .flat_map(|r| r.candidates.iter().map(|(id, _)| *id))
+            .collect::<HashSet<_>>();
+        pool.retain(|e| !offered.contains(&e.id));
+        pool.sort_by_key(|e| (uses.get(&e.id).copied().unwrap_or(0), e.id));
+
+        let unpolled = waiting
+            .into_iter()
+            .filter(|m| !replacements.values().any(|r| r.winner == m.messages[1].id))
+            .collect::<Vec<_>>();
+        for (emsg, candidates) in unpolled
+            .iter()
+            .rev()
+            .take(short)
+            .zip(pool.chunks(REPLACEMENT_CHOICES.len()))
+        {
+            let mut content = format!(
+                "**{}** won, but all {} emote slots are taken. Vote within {} hours which emote \
+                 makes room for it:",
                 emsg.emote.name,
-                pos as f64 / neg as f64,
-                emsg.emote.author
-            )
+                if *animated { "animated" } else { "static" },
+                REPLACEMENT_HOURS
+            );
+            for (choice, e) in REPLACEMENT_CHOICES.iter().zip(candidates) {
+                content += &format!(
+                    "\n{} {} {}, used {} times",
+                    choice,
+                    e,
+                    e.name,
+                    uses.get(&e.id).copied().unwrap_or(0)
+                );
+            }
+            let reactions = REPLACEMENT_CHOICES
+                .iter()
+                .take(candidates.len())
+                .map(|c| ReactionType::Unicode(c.to_string()))
+                .collect::<Vec<_>>();
+            let message = match http.post(CHANNEL, &content, Vec::new(), reactions) {
+                Ok(x) => x,
+                Err(why) => {
+                    log!("Could not post a replacement poll: {:?}", why);
+                    continue;
+                }
+            };
+
+            let replacement = Replacement {
+                message,
+                winner: emsg.messages[1].id,
+                candidates: candidates.iter().map(|e| (e.id, e.name.clone())).collect(),
+                ends: Utc::now() + Duration::hours(REPLACEMENT_HOURS),
+            };
+            let id = replacement.message.id;
+            store::log(Op::Replacement(id, Box::new(replacement.clone())));
+            replacements.insert(id, replacement);
+        }
+    }
+}
+
+/// Deletes the emote with the most votes and uploads the winner in its place. A winner that got
+/// accepted or removed in the meantime leaves the emotes alone.
+fn settle_replacement(http: &Http, id: MessageId) -> serenity::Result<()> {
+    let mut messages = MESSAGES.write().unwrap();
+    let mut replacements = REPLACEMENTS.write().unwrap();
+    let poll = match replacements.get(&id) {
+        Some(x) => x.clone(),
+        None => return Ok(()),
+    };
+
+    let current = rest::call("Fetching message", || {
+        poll.message.channel_id.message(http, id)
+    })?;
+    let count = |choice: &str| {
+        current
+            .reactions
+            .iter()
+            .find(|r| matches!(&r.reaction_type, ReactionType::Unicode(n) if n == choice))
+            .map_or(0, |r| r.count)
+    };
+    // the candidates are listed least used first, that one wins a tie
+    let chosen = (0..poll.candidates.len())
+        .max_by_key(|n| (count(REPLACEMENT_CHOICES[*n]), std::cmp::Reverse(*n)))
+        .unwrap_or(0);
+
+    store::log(Op::ReplacementDone(id));
+    replacements.remove(&id);
+    drop(replacements);
+
+    let (emoji, name) = match poll.candidates.get(chosen) {
+        Some(x) => x.clone(),
+        None => return Ok(()),
+    };
+    let winner = match messages.get(&poll.winner) {
+        Some(x) => x.emote.name.clone(),
+        None => return Ok(()),
+    };
+
+    http.remove_emoji(GUILD, emoji)?;
+    EMOJI_USES.write().unwrap().remove(&emoji);
+    end_trial(emoji);
+    let content = match accept_suggestion(http, &mut messages, poll.winner) {
+        Ok(()) => format!("{} got deleted, **{}** took its slot.", name, winner),
+        Err(why) => format!(
+            "{} got deleted, but **{}** could not be uploaded: {} Upload it with accept.",
+            name, winner, why
+        ),
+    };
+    rest::post("Sending message", || CHANNEL.say(http, &content))?;
+
+    Ok(())
+}
+
+/// Puts a new upload on trial with `trial_weeks`.
+fn start_trial(emoji: &Emoji) {
+    if CONFIG.read().unwrap().trial_weeks.is_none() {
+        return;
+    }
+    let trial = Trial {
+        emoji: emoji.id,
+        name: emoji.name.clone(),
+        installed: Utc::now(),
+        message: None,
+        ends: None,
+    };
+    let mut trials = TRIALS.write().unwrap();
+    store::log(Op::Trial(emoji.id, Box::new(trial.clone())));
+    trials.insert(emoji.id, trial);
+}
+
+fn end_trial(emoji: EmojiId) {
+    let mut trials = TRIALS.write().unwrap();
+    if trials.remove(&emoji).is_some() {
+        store::log(Op::TrialDone(emoji));
+    }
+}
+
+/// Starts the confirmation vote of every `Trial` whose `trial_weeks` are over, with the uses of
+/// the emote next to the others, and deletes the emotes that get more 👎 than 👍 in theirs.
+fn confirm_trials(http: &Http, cache: &CacheRwLock) -> serenity::Result<bool> {
+    let weeks = match CONFIG.read().unwrap().trial_weeks {
+        Some(x) => x,
+        None => return Ok(false),
+    };
+    let emojis = match cache.read().guild(GUILD) {
+        Some(guild) => guild.read().emojis.clone(),
+        None => return Ok(false),
+    };
+    let uses = EMOJI_USES.read().unwrap().clone();
+    let mut trials = TRIALS.write().unwrap();
+    let mut changed = false;
+
+    // emotes the mods deleted themselves need no vote anymore
+    let gone = trials
+        .keys()
+        .filter(|id| !emojis.contains_key(id))
+        .copied()
+        .collect::<Vec<_>>();
+    for id in gone {
+        store::log(Op::TrialDone(id));
+        trials.remove(&id);
+        changed = true;
+    }
+
+    let due = Utc::now() - Duration::weeks(weeks);
+    for (id, trial) in trials.iter_mut() {
+        if trial.message.is_some() || trial.installed > due {
+            continue;
+        }
+        let used = uses.get(id).copied().unwrap_or(0);
+        let less_used = emojis
+            .keys()
+            .filter(|e| uses.get(e).copied().unwrap_or(0) < used)
+            .count();
+        let content = format!(
+            "{} **{}** is on trial since {}. It was used {} times, more than {}% of the emotes. \
+             Keep it? Vote within {} hours, more 👎 than 👍 deletes it.",
+            emojis[id],
+            trial.name,
+            trial.installed.format("%Y-%m-%d"),
+            used,
+            less_used * 100 / emojis.len(),
+            TRIAL_HOURS
+        );
+        let reactions = vec![ReactionType::from("👍"), ReactionType::from("👎")];
+        trial.message = Some(http.post(CHANNEL, &content, Vec::new(), reactions)?);
+        trial.ends = Some(Utc::now() + Duration::hours(TRIAL_HOURS));
+        store::log(Op::Trial(*id, Box::new(trial.clone())));
+        changed = true;
+    }
+
+    let ended = trials
+        .values()
+        .filter(|t| t.ends.is_some_and(|at| at <= Utc::now()))
+        .cloned()
+        .collect::<Vec<_>>();
+    for trial in ended {
+        let (pos, neg) = match &trial.message {
+            Some(message) => votes(http, message)?,
+            None => continue,
+        };
+        let content = match neg > pos {
+            true => {
+                http.remove_emoji(GUILD, trial.emoji)?;
+                EMOJI_USES.write().unwrap().remove(&trial.emoji);
+                format!(
+                    "**{}** failed its confirmation and got deleted, its slot is free for the \
+                     next winner.",
+                    trial.name
+                )
+            }
+            false => format!("**{}** passed its confirmation and stays.", trial.name),
+        };
+        store::log(Op::TrialDone(trial.emoji));
+        trials.remove(&trial.emoji);
+        rest::post("Sending message", || CHANNEL.say(http, &content))?;
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+/// Posts a random pair of suggestions every `minutes` and rates the last one, see `elo`.
+fn compare_pairs(http: Arc<Http>, minutes: u64) {
+    thread::spawn(move || loop {
+        thread::sleep(time::Duration::from_secs(minutes * 60));
+
+        if let Err(why) = settle_pair(&http) {
+            log!("Could not count the pairwise comparison: {:?}", why);
+        }
+        if let Err(why) = post_pair(&http) {
+            log!("Could not post a pairwise comparison: {:?}", why);
+        }
+        store::save(snapshot);
+    });
+}
+
+/// Moves the ratings of the open pair by its votes and deletes it.
+fn settle_pair(http: &Http) -> serenity::Result<()> {
+    let mut messages = MESSAGES.write().unwrap();
+    let mut pair = PAIR.write().unwrap();
+    let open = match pair.take() {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+    store::log(Op::Pair(None));
+
+    let current = rest::call("Fetching message", || {
+        open.message.channel_id.message(http, open.message.id)
+    })?;
+    // the bot's own reactions don't count
+    let (a, b) = current
+        .reactions
+        .iter()
+        .fold((0, 0), |(a, b), r| match &r.reaction_type {
+            ReactionType::Unicode(n) if n == VARIANT_A => (r.count.saturating_sub(1), b),
+            ReactionType::Unicode(n) if n == VARIANT_B => (a, r.count.saturating_sub(1)),
+            _ => (a, b),
+        });
+    if let Err(why) = rest::call("Deleting message", || current.delete(http)) {
+        log!("Could not delete message {}: {:?}", current.id, why);
+    }
+
+    let [first, second] = open.candidates;
+    if a + b == 0 || !messages.contains_key(&first) || !messages.contains_key(&second) {
+        return Ok(());
+    }
+    let (rating_a, rating_b) = elo::update(
+        messages[&first].rating,
+        messages[&second].rating,
+        a as f64 / (a + b) as f64,
+    );
+    for (id, rating) in [(first, rating_a), (second, rating_b)].iter() {
+        if let Some(emsg) = messages.get_mut(id) {
+            emsg.rating = *rating;
+            store::log(Op::Suggestion(Box::new(emsg.clone())));
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks which of two random pending suggestions is better, not during the nomination.
+fn post_pair(http: &Http) -> serenity::Result<()> {
+    if !voting_open() {
+        return Ok(());
+    }
+    let messages = MESSAGES.read().unwrap();
+    let mut pair = PAIR.write().unwrap();
+
+    let pending = messages.values().collect::<Vec<_>>();
+    let picked = pending
+        .choose_multiple(&mut rand::thread_rng(), 2)
+        .collect::<Vec<_>>();
+    let (a, b) = match picked[..] {
+        [a, b] => (*a, *b),
+        _ => return Ok(()),
+    };
+
+    let mut files = Vec::new();
+    for (letter, emsg) in [("a", a), ("b", b)].iter() {
+        if let Some(attachment) = emsg.messages[0].attachments.first() {
+            files.push((
+                file_of(emsg, attachment)?,
+                format!("{}_{}", letter, attachment.filename),
+            ));
+        }
+    }
+
+    let message = rest::post("Sending pair", || {
+        CHANNEL.send_message(http, |m| {
+            m.content(format!(
+                "Which one is better? {} **{}** from {} or {} **{}** from {}",
+                VARIANT_A, a.emote.name, a.emote.author, VARIANT_B, b.emote.name, b.emote.author
+            ));
+            m.add_files(files.iter().map(|(data, name)| (&data[..], &name[..])))
         })
-        .reduce(String::new, |acc, s| acc + &s);
+    })?;
+    rest::call("Adding reactions", || {
+        message
+            .react(http, VARIANT_A)
+            .and_then(|_| message.react(http, VARIANT_B))
+    })?;
+
+    let open = Pair {
+        message,
+        candidates: [a.messages[1].id, b.messages[1].id],
+    };
+    store::log(Op::Pair(Some(Box::new(open.clone()))));
+    *pair = Some(open);
+
+    Ok(())
+}
+
+/// Current 👍 and 👎 count of a vote message, the stored message has no up to date reactions.
+fn votes(http: &dyn Discord, message: &Message) -> serenity::Result<(u64, u64)> {
+    let current = http.fetch(message.channel_id, message.id)?;
+
+    Ok(current
+        .reactions
+        .iter()
+        .fold((0, 0), |(pos, neg), r| match &r.reaction_type {
+            ReactionType::Unicode(n) if n == "👍" => (r.count, neg),
+            ReactionType::Unicode(n) if n == "👎" => (pos, r.count),
+            _ => (pos, neg),
+        }))
+}
+
+/// Like `votes` without the 👍 and 👎 the bot seeded itself.
+fn cast_votes(http: &dyn Discord, message: &Message) -> serenity::Result<(u64, u64)> {
+    let current = http.fetch(message.channel_id, message.id)?;
+
+    Ok(current.reactions.iter().fold((0, 0), |(pos, neg), r| {
+        let count = r.count - r.me as u64;
+        match &r.reaction_type {
+            ReactionType::Unicode(n) if n == "👍" => (count, neg),
+            ReactionType::Unicode(n) if n == "👎" => (pos, count),
+            _ => (pos, neg),
+        }
+    }))
+}
+
+/// `votes` of a suggestion with the ones it `carried` over from before a `restore`.
+fn suggestion_votes(http: &dyn Discord, emsg: &EmoteMessage) -> serenity::Result<(u64, u64)> {
+    let (pos, neg) = votes(http, &emsg.messages[1])?;
+    Ok((pos + emsg.carried.0, neg + emsg.carried.1))
+}
+
+/// Weighted 👍 minus 👎, or what `score_script` makes of the votes. A failing script counts
+/// the plain votes, so a typo can't decide a round.
+fn score(emsg: &EmoteMessage, tally: &Tally) -> f64 {
+    let ((up, down), (pos, neg)) = *tally;
+    let source = match CONFIG.read().unwrap().score_script.clone() {
+        Some(x) => x,
+        None => return pos - neg,
+    };
+
+    let mut vars = emote_vars(&emsg.emote);
+    vars.insert("pos", pos.into());
+    vars.insert("neg", neg.into());
+    vars.insert("up", up.into());
+    vars.insert("down", down.into());
+    vars.insert(
+        "days",
+        ((Utc::now() - emsg.since).num_hours() as f64 / 24.0).into(),
+    );
+    match script::run(&source, &vars) {
+        Ok(script::Value::Num(n)) => n,
+        other => {
+            log!("score_script failed for {}: {:?}", emsg.emote.name, other);
+            pos - neg
+        }
+    }
+}
+
+/// The variables of `script` every rule gets.
+fn emote_vars(emote: &Emote) -> script::Vars {
+    let kind = match emote.kind {
+        Kind::Emoji => "emoji",
+        Kind::Sticker => "sticker",
+        Kind::Sound => "sound",
+    };
+    let mut vars = script::Vars::new();
+    vars.insert("name", emote.name.as_str().into());
+    vars.insert("author", emote.author.as_str().into());
+    vars.insert("kind", kind.into());
+    vars.insert("category", emote.category.as_deref().unwrap_or("").into());
+    vars.insert("animated", emote.animated.into());
+    vars
+}
+
+/// Like `votes`, but only counts voters that are still on the server and have one of the
+/// `voter_roles`. Slower since every voter gets looked up, so it's only used for closing.
+/// Returns the counts and the sums of their `vote_weight`s.
+fn valid_votes(http: &Http, cache: &CacheRwLock, emsg: &EmoteMessage) -> serenity::Result<Tally> {
+    let roles = CONFIG.read().unwrap().voter_roles.clone();
+    let now = Utc::now();
+    let mut tally = [0, 0];
+    let mut weighted = [0.0, 0.0];
+
+    for ((count, weight), emoji) in tally
+        .iter_mut()
+        .zip(weighted.iter_mut())
+        .zip(["👍", "👎"].iter())
+    {
+        for user in voters(http, &emsg.messages[1], emoji)? {
+            if user.bot {
+                continue;
+            }
+
+            // the lookup fails for users that left
+            let member =
+                match rest::call("Fetching member", || GUILD.member((cache, http), user.id)) {
+                    Ok(x) => x,
+                    Err(_) => continue,
+                };
+            if roles.is_empty() || member.roles.iter().any(|r| roles.contains(r)) {
+                *count += 1;
+                *weight += vote_weight(emsg, user.id, now);
+            }
+        }
+    }
+
+    // the carried votes have no voter anymore, they count as cast at the start of the voting
+    let (up, down) = emsg.carried;
+    let weight = decay(emsg.since, now);
+    Ok((
+        (tally[0] + up, tally[1] + down),
+        (
+            weighted[0] + up as f64 * weight,
+            weighted[1] + down as f64 * weight,
+        ),
+    ))
+}
 
-    if let Err(why) = msg.channel_id.say(ctx, &content) {
-        dm_user(http, msg, "Discord error, pls try again later.");
-        return Err(CommandError(format!(
-            "Sending msg: {:?}, message was: {}",
-            why, content
-        )));
+/// 1 for a fresh vote, with `vote_half_life_days` it halves every half-life down to
+/// `min_vote_weight`. Votes from before the tracking count as cast at the start of the voting.
+fn vote_weight(emsg: &EmoteMessage, user: UserId, now: DateTime<Utc>) -> f64 {
+    decay(emsg.voted.get(&user).copied().unwrap_or(emsg.since), now)
+}
+
+fn decay(cast: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let config = CONFIG.read().unwrap();
+    let half_life = match config.vote_half_life_days {
+        Some(x) if x > 0.0 => x,
+        _ => return 1.0,
+    };
+
+    let age = (now - cast).num_seconds().max(0) as f64 / 86400.0;
+    0.5f64
+        .powf(age / half_life)
+        .max(config.min_vote_weight.min(1.0))
+}
+
+/// Everyone that reacted with `emoji`, Discord hands them out 100 at a time.
+fn voters(http: &Http, message: &Message, emoji: &str) -> serenity::Result<Vec<user::User>> {
+    let mut users = Vec::new();
+    loop {
+        let after = users.last().map(|u: &user::User| u.id);
+        let page = rest::call("Fetching voters", || {
+            message.reaction_users(http, emoji, Some(100), after)
+        })?;
+        let done = page.len() < 100;
+        users.extend(page);
+        if done {
+            return Ok(users);
+        }
+    }
+}
+
+/// Reposts the file, final score and verdict of a suggestion in the archive channel, if one is
+/// configured.
+fn archive(
+    http: &Http,
+    emsg: &EmoteMessage,
+    (pos, neg): (u64, u64),
+    verdict: &str,
+) -> serenity::Result<()> {
+    match CONFIG.read().unwrap().archive_channel {
+        Some(channel) => repost(
+            http,
+            channel,
+            emsg,
+            templates::render(
+                "archive",
+                "**{name}** from {author}\n👍 {up} 👎 {down}, {verdict}",
+                &result_vars(emsg, (pos, neg), verdict),
+            ),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Posts an uploaded suggestion in the `news_channel` and publishes it to the followers when
+/// it's an announcement channel.
+fn news(
+    http: &Http,
+    emsg: &EmoteMessage,
+    tally: (u64, u64),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let channel = match CONFIG.read().unwrap().news_channel {
+        Some(x) => x,
+        None => return Ok(()),
     };
+    let attachment = match emsg.messages[0].attachments.first() {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+    let file = file_of(emsg, attachment)?;
+
+    let content = templates::render(
+        "news",
+        "New on the server: **{name}** from {author}",
+        &result_vars(emsg, tally, "accepted"),
+    );
+    let posted = rest::post("Sending news", || {
+        channel.send_message(http, |m| {
+            m.content(&content);
+            m.add_files(vec![(&*file, &*attachment.filename)])
+        })
+    })?;
+    if let Channel::Guild(c) = rest::call("Fetching channel", || channel.to_channel(http))? {
+        if c.read().kind == ChannelType::News {
+            crosspost::publish(http, channel, posted.id)?;
+        }
+    }
 
     Ok(())
 }
 
-#[command]
-#[only_in(guilds)]
-#[example("123456789")]
-#[allowed_roles("Moderator", "admin")]
-fn remove(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
-    let http = ctx.http.clone();
-    let mut messages = MESSAGES.write().unwrap();
+/// Placeholders of the result posts, `archive`, `hall_of_fame` and `news`.
+fn result_vars(
+    emsg: &EmoteMessage,
+    (pos, neg): (u64, u64),
+    verdict: &str,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("name", emsg.emote.name.clone()),
+        ("author", emsg.emote.author.clone()),
+        ("up", pos.to_string()),
+        ("down", neg.to_string()),
+        ("score", (pos as i64 - neg as i64).to_string()),
+        ("verdict", verdict.to_string()),
+    ]
+}
 
-    println!("{}   Args for stats: {}", msg.author.name, &args.message());
-    let parsed = args
-        .single::<u64>()
-        .map(|id| MessageId(id))
-        .map_err(|_| "Missing id.")
-        .and_then(|id| {
-            messages
-                .get(&id)
-                .map(|m| (id, m))
-                .ok_or("ID is not in messages.")
+/// The file of a suggestion, from `images` when it's there. Only suggestions from before the
+/// store need `attachment` on the Discord CDN.
+fn file_of(emsg: &EmoteMessage, attachment: &Attachment) -> serenity::Result<Vec<u8>> {
+    match emsg.emote.image.and_then(images::get) {
+        Some(file) => Ok(file),
+        None => attachment.download(),
+    }
+}
+
+/// Sends the file of a suggestion with `content` to another channel.
+fn repost(
+    http: &Http,
+    channel: ChannelId,
+    emsg: &EmoteMessage,
+    content: String,
+) -> serenity::Result<()> {
+    let attachment = match emsg.messages[0].attachments.first() {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+    let file = file_of(emsg, attachment)?;
+
+    rest::post("Sending message", || {
+        channel.send_message(http, |m| {
+            m.content(&content);
+            m.add_files(vec![(&*file, &*attachment.filename)])
         })
-        .and_then(|(id, m)| {
-            match m
-                .messages
+    })?;
+
+    Ok(())
+}
+
+/// Like `repost`, but through the `outbox`. Only the download can fail here.
+fn repost_later(
+    http: &Arc<Http>,
+    channel: ChannelId,
+    emsg: &EmoteMessage,
+    content: &str,
+) -> serenity::Result<()> {
+    let attachment = match emsg.messages[0].attachments.first() {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+    let file = file_of(emsg, attachment)?;
+
+    outbox::push_file(http.clone(), channel, content, file, &attachment.filename);
+    Ok(())
+}
+
+/// Gives the winner role to the authors of this round, with `winner_role_until_next_round` the
+/// winners of the last round lose it again when `new_round` starts.
+fn crown(http: &Http, role: RoleId, authors: HashSet<UserId>, new_round: bool) {
+    let mut crowned = CROWNED.write().unwrap();
+
+    if new_round && CONFIG.read().unwrap().winner_role_until_next_round {
+        for user in crowned.difference(&authors) {
+            if let Err(why) = rest::call("Removing role", || {
+                http.remove_member_role(GUILD.0, user.0, role.0)
+            }) {
+                log!("Could not remove the winner role from {}: {:?}", user, why);
+            }
+        }
+        crowned.clear();
+    }
+
+    for user in authors {
+        if crowned.contains(&user) {
+            continue;
+        }
+        match rest::call("Adding role", || {
+            http.add_member_role(GUILD.0, user.0, role.0)
+        }) {
+            Ok(_) => {
+                crowned.insert(user);
+            }
+            Err(why) => log!("Could not give the winner role to {}: {:?}", user, why),
+        }
+    }
+}
+
+/// Records the verdict of a suggestion, for rejected ones this starts the resubmission cooldown.
+fn finish(id: MessageId, emote: Emote, votes: (u64, u64), accepted: bool) {
+    let mut history = HISTORY.write().unwrap();
+    let finished = Finished {
+        id,
+        name: emote.name,
+        author: emote.author,
+        author_id: emote.author_id,
+        hash: emote.hash,
+        legacy: emote.legacy,
+        votes,
+        accepted,
+        at: Utc::now(),
+    };
+    store::log(Op::Finished(finished.clone()));
+    history.push(finished);
+}
+
+/// Puts a suggestion into the voting, the change is logged first.
+fn add_suggestion(messages: &mut HashMap<MessageId, EmoteMessage>, emsg: EmoteMessage) {
+    store::log(Op::Suggestion(Box::new(emsg.clone())));
+    let link = format!(
+        "https://discord.com/channels/{}/{}/{}",
+        GUILD, emsg.messages[1].channel_id, emsg.messages[1].id
+    );
+    let text = templates::render(
+        "new_suggestion",
+        "New suggestion **{name}** from {author}: {link}",
+        &[
+            ("name", emsg.emote.name.clone()),
+            ("author", emsg.emote.author.clone()),
+            ("link", link.clone()),
+        ],
+    );
+    let media = emsg.messages[0]
+        .attachments
+        .first()
+        .filter(|_| emsg.emote.kind != Kind::Sound)
+        .map(|a| telegram::Media {
+            url: a.url.clone(),
+            animated: emsg.emote.animated,
+        });
+    telegram::post(text.clone(), media);
+    webhooks::fire(
+        Event::Submission,
+        text,
+        json!({
+            "id": emsg.messages[1].id,
+            "name": emsg.emote.name,
+            "author": emsg.emote.author,
+            "kind": emsg.emote.kind,
+            "category": emsg.emote.category,
+            "link": link,
+        }),
+    );
+    messages.insert(emsg.messages[1].id, emsg);
+    STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+}
+
+/// DMs the author the ID, a preview, the deadline and the link of a suggestion that just got
+/// into the voting.
+fn receipt(http: &Http, emsg: &EmoteMessage) {
+    let id = short_id(emsg.messages[1].id);
+    let prefix = GUILDS
+        .read()
+        .unwrap()
+        .get(&GUILD)
+        .map_or_else(|| GuildConfig::default().prefix, |g| g.prefix.clone());
+    let end = match voting_open() {
+        true => format!("The voting ends {}.", local_time(deadline(emsg))),
+        false => match ROUND.read().unwrap().voting_at {
+            Some(at) => format!("The voting starts {}.", local_time(at)),
+            None => String::from("The voting starts when the mods open it."),
+        },
+    };
+    let content = format!(
+        "Your suggestion **{}** is in the voting, its ID is `{}`. {}\n\
+         https://discord.com/channels/{}/{}/{}\n\
+         Check on it with `{}status {}` or withdraw it with `{}withdraw {}`.",
+        emsg.emote.name,
+        id,
+        end,
+        GUILD,
+        emsg.messages[1].channel_id,
+        emsg.messages[1].id,
+        prefix,
+        id,
+        prefix,
+        id
+    );
+    let preview = emsg.messages[0]
+        .attachments
+        .first()
+        .filter(|_| emsg.emote.kind != Kind::Sound)
+        .map(|a| a.url.clone());
+
+    let sent = rest::post("Sending receipt", || {
+        emsg.emote
+            .author_id
+            .create_dm_channel(http)?
+            .send_message(http, |m| {
+                m.content(&content);
+                if let Some(url) = &preview {
+                    m.embed(|e| e.image(url));
+                }
+                m
+            })
+    });
+    if let Err(why) = sent {
+        log!(
+            "Could not send the receipt to {}: {:?}",
+            emsg.emote.author_id,
+            why
+        );
+    }
+}
+
+/// Short form of a vote message id for receipts, the snowflake in base 36.
+fn short_id(id: MessageId) -> String {
+    let mut n = id.0;
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((n % 36) as u32, 36).unwrap_or('0'));
+        n /= 36;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().collect()
+}
+
+/// A message id as snowflake or `short_id`, snowflakes of messages have at least 17 digits.
+fn parse_id(arg: &str) -> Option<MessageId> {
+    match arg.len() >= 17 && arg.chars().all(|c| c.is_ascii_digit()) {
+        true => arg.parse().ok(),
+        false => u64::from_str_radix(&arg.to_lowercase(), 36).ok(),
+    }
+    .map(MessageId)
+}
+
+/// Reacts to a new boost tier: fewer slots get the mods a list of the emotes the bot uploaded in
+/// a pool that is now over its limit, more slots get the winners waiting for `accept` uploaded.
+fn tier_changed(http: &Arc<Http>, old: PremiumTier, guild: &PartialGuild) {
+    log!(
+        "Boost tier changed from {:?} to {:?}",
+        old,
+        guild.premium_tier
+    );
+    let (before, now) = (
+        slots::max_emojis(old),
+        slots::max_emojis(guild.premium_tier),
+    );
+
+    if now < before {
+        let uploaded = HISTORY
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|r| r.accepted)
+            .map(|r| names::fold(&r.name))
+            .collect::<HashSet<_>>();
+
+        let mut content = format!(
+            "The boost tier dropped, there are only {} slots for static and animated emotes each now.",
+            now
+        );
+        for animated in [false, true].iter() {
+            let mut pool = guild
+                .emojis
+                .values()
+                .filter(|e| e.animated == *animated)
+                .collect::<Vec<_>>();
+            if pool.len() <= now {
+                continue;
+            }
+            // the newest ones are the likeliest to go
+            pool.sort_by_key(|e| std::cmp::Reverse(e.id));
+            let at_risk = pool
                 .iter()
-                .map(|m| m.delete(http.clone()))
-                .all(|r| r.is_ok())
-            {
-                true => (Ok(id)),
-                false => Err("Internal error, pls try again later."),
+                .filter(|e| uploaded.contains(&names::fold(&e.name)))
+                .map(|e| e.name.clone())
+                .collect::<Vec<_>>();
+            content += &format!(
+                "\n{} {} emotes are {} too many, voted in by us (newest first): {}",
+                pool.len(),
+                if *animated { "animated" } else { "static" },
+                pool.len() - now,
+                match at_risk.is_empty() {
+                    true => String::from("none"),
+                    false => at_risk.join(", "),
+                }
+            );
+        }
+        let channel = CONFIG.read().unwrap().mod_channel.unwrap_or(CHANNEL);
+        send(http.clone(), channel, &content);
+        return;
+    }
+
+    let mut messages = MESSAGES.write().unwrap();
+    let mut winners = messages
+        .values()
+        .filter(|m| m.won)
+        .map(|m| (m.since, m.messages[1].id))
+        .collect::<Vec<_>>();
+    winners.sort();
+    let mut uploaded = Vec::new();
+    for (_, id) in winners {
+        let name = messages[&id].emote.name.clone();
+        match accept_suggestion(http, &mut messages, id) {
+            Ok(()) => uploaded.push(name),
+            Err(why) => log!("Could not upload the winner {}: {}", name, why),
+        }
+    }
+    drop(messages);
+
+    if !uploaded.is_empty() {
+        send(
+            http.clone(),
+            CHANNEL,
+            &format!(
+                "The boost tier rose, the winners {} got uploaded into the new slots.",
+                uploaded.join(", ")
+            ),
+        );
+        store::save(snapshot);
+    }
+}
+
+/// Uploads `winners` into the `rotation_slots` right away, `replace` deletes the emotes of the
+/// last round there first. Returns the announcement.
+fn rotate(
+    http: &Http,
+    messages: &mut HashMap<MessageId, EmoteMessage>,
+    winners: &[MessageId],
+    replace: bool,
+) -> String {
+    let mut rotation = ROTATION.read().unwrap().clone();
+    let mut removed = Vec::new();
+    if replace {
+        for old in rotation.drain(..) {
+            match http.remove_emoji(GUILD, old.emoji) {
+                Ok(()) => removed.push(old.name),
+                Err(why) => log!("Could not remove the rotated emote {}: {:?}", old.name, why),
             }
+        }
+    }
+
+    let mut installed = Vec::new();
+    for id in winners {
+        let name = match messages.get(id) {
+            Some(m) => m.emote.name.clone(),
+            None => continue,
+        };
+        if let Err(why) = accept_suggestion(http, messages, *id) {
+            log!("Could not upload the rotation winner {}: {}", name, why);
+            continue;
+        }
+        // the upload only shows its id in the emotes of the guild
+        let uploaded = rest::call("Fetching guild", || GUILD.to_partial_guild(http)).map(|g| {
+            g.emojis
+                .values()
+                .filter(|e| e.name == name)
+                .map(|e| e.id)
+                .max()
         });
+        match uploaded {
+            // the next round replaces it anyway
+            Ok(Some(emoji)) => {
+                end_trial(emoji);
+                rotation.push(Rotated {
+                    emoji,
+                    name: name.clone(),
+                    since: Utc::now(),
+                });
+            }
+            other => log!("Could not find the rotated emote {}: {:?}", name, other),
+        }
+        installed.push(name);
+    }
 
-    match parsed {
-        Ok(id) => messages.remove(&id),
-        Err(mess) => return dm_user_err(http, msg, mess),
+    let mut stored = ROTATION.write().unwrap();
+    store::log(Op::Rotation(rotation.clone()));
+    *stored = rotation;
+
+    match (removed.is_empty(), installed.is_empty()) {
+        (_, true) => {
+            String::from("Could not upload the winners into the rotation, they wait for accept.")
+        }
+        (true, false) => format!("Uploaded into the rotation: {}.", installed.join(", ")),
+        (false, false) => format!(
+            "Rotation: {} made room for {}.",
+            removed.join(", "),
+            installed.join(", ")
+        ),
+    }
+}
+
+/// Uploads an emote winner into every guild that opted in with `federate`, a taken name gets the
+/// first free variation or skips the guild by its `collisions`. Returns how each guild went.
+fn federate(http: &Http, name: &str, image: &str, animated: bool) -> Vec<String> {
+    let peers = GUILDS
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(id, g)| **id != GUILD && g.federate)
+        .map(|(id, g)| (*id, g.collisions))
+        .collect::<Vec<_>>();
+
+    let mut report = Vec::new();
+    for (id, collisions) in peers {
+        let guild = match rest::call("Fetching guild", || id.to_partial_guild(http)) {
+            Ok(x) => x,
+            Err(why) => {
+                log!("Could not reach the partner guild {}: {:?}", id, why);
+                report.push(format!("{} is unreachable", id));
+                continue;
+            }
+        };
+        let used = guild
+            .emojis
+            .values()
+            .filter(|e| e.animated == animated)
+            .count();
+        if used >= slots::max_emojis(guild.premium_tier) {
+            report.push(format!("{} has no free slot", guild.name));
+            continue;
+        }
+
+        let taken = guild
+            .emojis
+            .values()
+            .map(|e| names::fold(&e.name))
+            .collect::<HashSet<_>>();
+        let upload_as = match (taken.contains(&names::fold(name)), collisions) {
+            (false, _) => name.to_string(),
+            (true, Collision::Skip) => {
+                report.push(format!("{} has a {} already", guild.name, name));
+                continue;
+            }
+            (true, Collision::Rename) => {
+                match name_suggestions(name, Kind::Emoji, &taken)
+                    .into_iter()
+                    .next()
+                {
+                    Some(x) => x,
+                    None => {
+                        report.push(format!("{} has no free variation of the name", guild.name));
+                        continue;
+                    }
+                }
+            }
+        };
+
+        match http.upload_emoji(id, &upload_as, image) {
+            Ok(e) => {
+                log!("Created emote {} ({}) in {}", e.name, e.id, guild.name);
+                report.push(match upload_as == name {
+                    true => guild.name,
+                    false => format!("{} as {}", guild.name, upload_as),
+                });
+            }
+            Err(why) => {
+                log!("Could not federate {} to {}: {:?}", name, guild.name, why);
+                report.push(format!("{} failed", guild.name));
+            }
+        }
+    }
+    report
+}
+
+/// Keeps a slot for the winner `id` until it's accepted, see `check_pool`.
+fn mark_won(messages: &mut HashMap<MessageId, EmoteMessage>, id: MessageId) {
+    if let Some(emsg) = messages.get_mut(&id) {
+        emsg.won = true;
+        store::log(Op::Suggestion(Box::new(emsg.clone())));
+    }
+}
+
+/// Refuses `adding` new emotes when the pool of static or animated emojis can't take them, the
+/// winners waiting for `accept` have their slots already. `static_quota` and `animated_quota`
+/// limit the suggestions of each pool in the voting.
+fn check_pool(http: Arc<Http>, msg: &Message, animated: bool, adding: usize) -> CommandResult {
+    let (pool, quota) = {
+        let config = CONFIG.read().unwrap();
+        match animated {
+            true => ("animated", config.animated_quota),
+            false => ("static", config.static_quota),
+        }
     };
+    let same_pool = |m: &&EmoteMessage| m.emote.kind == Kind::Emoji && m.emote.animated == animated;
+
+    let (pending, waiting) = {
+        let queue = QUEUE.read().unwrap();
+        let messages = MESSAGES.read().unwrap();
+        let pending = queue
+            .values()
+            .chain(messages.values())
+            .filter(same_pool)
+            .count();
+        let waiting = messages
+            .values()
+            .filter(same_pool)
+            .filter(|m| m.won)
+            .count();
+        (pending, waiting)
+    };
+
+    if let Some(quota) = quota.filter(|q| pending + adding > *q) {
+        return dm_user_err(
+            http,
+            msg,
+            &format!(
+                "Only {} {} suggestions can be in the voting at once, {} are.",
+                quota, pool, pending
+            ),
+        );
+    }
+
+    let free = match slots::free_emojis(&http, GUILD, animated) {
+        Ok(x) => x,
+        Err(why) => {
+            dm_user(http, msg, "Discord error, pls try again later.");
+            return Err(CommandError(format!("Counting emoji slots: {:?}", why)));
+        }
+    };
+    if free < waiting + adding {
+        return dm_user_err(
+            http,
+            msg,
+            &format!(
+                "There are no free {} emote slots: {} are left and {} winners waiting for accept \
+                 take them.",
+                pool, free, waiting
+            ),
+        );
+    }
 
-    dm_user(http, msg, "Done");
     Ok(())
 }
 
-pub fn send(http: Arc<Http>, target: ChannelId, content: &str) {
-    if let Err(why) = target.say(http, content) {
-        println!("Could not send message: {:?}", why);
+/// Takes a suggestion out of the voting, the change is logged first.
+fn take_suggestion(
+    messages: &mut HashMap<MessageId, EmoteMessage>,
+    id: MessageId,
+) -> Option<EmoteMessage> {
+    store::log(Op::Removed(id));
+    STANDINGS_CHANGED.store(true, AtomicOrdering::Relaxed);
+    messages.remove(&id)
+}
+
+/// End of the voting for a suggestion, a revote starts the days again.
+fn deadline(emsg: &EmoteMessage) -> DateTime<Utc> {
+    emsg.since + Duration::days(CONFIG.read().unwrap().voting_days)
+}
+
+/// Same roles as the mod commands need without a grant, see `permissions`.
+fn is_mod(ctx: &Context, msg: &Message) -> bool {
+    msg.member(&ctx.cache)
+        .is_some_and(|m| permissions::is_mod(&m.roles))
+}
+
+/// Identifies resubmissions of the same file, the name alone is easy to change. It's FNV-1a
+/// (64 bit), which gives the same value for the same bytes in every build, unlike the std hasher.
+fn file_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// The std hasher that made the hashes stored before version 2, only to compare new files with
+/// them. They are past `resubmit_cooldown_days` at some point and this can go then.
+fn legacy_hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Refuses a suggestion whose name or file got removed within the last
+/// `resubmit_cooldown_days`.
+fn check_cooldown(http: Arc<Http>, msg: &Message, name: &str, data: &[u8]) -> CommandResult {
+    let days = CONFIG.read().unwrap().resubmit_cooldown_days;
+    let until = {
+        let history = HISTORY.read().unwrap();
+        let hash = file_hash(data);
+        let legacy = history.iter().any(|r| r.legacy).then(|| legacy_hash(data));
+        history
+            .iter()
+            .filter(|r| !r.accepted)
+            .filter(|r| match r.legacy {
+                true => Some(r.hash) == legacy,
+                false => r.hash == hash,
+            } || names::same(&r.name, name))
+            .map(|r| r.at + Duration::days(days))
+            .max()
+    };
+
+    match until {
+        Some(until) if until > Utc::now() => dm_user_err(
+            http,
+            msg,
+            &format!(
+                "{} was rejected recently, you can suggest it again on {}.",
+                name,
+                until.format("%Y-%m-%d")
+            ),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Suggestions `user` can still post this round, `max_suggestions` minus the used ones.
+fn slots_left(user: &User) -> u64 {
+    CONFIG
+        .read()
+        .unwrap()
+        .max_suggestions
+        .saturating_sub(user.counter)
+}
+
+/// Refuses a new suggestion of `user` without a slot left.
+fn check_slots(http: Arc<Http>, msg: &Message, user: &User) -> CommandResult {
+    match slots_left(user) {
+        0 => dm_user_err(
+            http,
+            msg,
+            &format!(
+                "You can only post {} suggestions.",
+                CONFIG.read().unwrap().max_suggestions
+            ),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Smallest width and height of an emote submission.
+fn min_image_size() -> u64 {
+    CONFIG.read().unwrap().min_image_size
+}
+
+/// The checks of `download_image` that only need the metadata of the attachment. The limits
+/// besides `min_size` come from the config.
+fn check_attachment(
+    http: Arc<Http>,
+    msg: &Message,
+    attachment: &Attachment,
+    min_size: u64,
+) -> CommandResult {
+    let (max_bytes, max_size, max_ratio) = {
+        let config = CONFIG.read().unwrap();
+        (
+            config.max_image_bytes,
+            config.max_image_size,
+            config.max_aspect_ratio,
+        )
+    };
+
+    if attachment.size > max_bytes {
+        return dm_user_err(
+            http,
+            msg,
+            &format!(
+                "{}MB is the size limit for images.",
+                max_bytes as f64 / 1_000_000.0
+            ),
+        );
+    }
+
+    // vector files have no pixel size, they get rasterized at the right one
+    if is_svg(attachment) {
+        return Ok(());
+    }
+
+    // check if the attachment is an image and its size fits
+    let (width, height) = match attachment.dimensions() {
+        Some(x) => x,
+        None => return dm_user_err(http, msg, "Attachment is not an image."),
+    };
+
+    // the pipeline scales up as well, `max_upscale` decides how far
+    let max_upscale = CONFIG.read().unwrap().max_upscale.max(1.0);
+    if (width.min(height) as f64 * max_upscale) < min_size as f64 {
+        return dm_user_err(
+            http,
+            msg,
+            &format!(
+                "Image must be at least {0}x{0}px.",
+                (min_size as f64 / max_upscale).ceil()
+            ),
+        );
+    }
+
+    if width > max_size || height > max_size {
+        return dm_user_err(
+            http,
+            msg,
+            &format!("Image can be at most {0}x{0}px.", max_size),
+        );
+    }
+
+    let ratio = width.max(height) as f64 / width.min(height) as f64;
+    if ratio > max_ratio {
+        return dm_user_err(
+            http,
+            msg,
+            &format!(
+                "Image is {:.1} times as wide as high (or the other way round), at most {} is allowed.",
+                ratio, max_ratio
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+/// Lets the author know that an image below `min_size` has to be scaled up and may look blurry.
+fn warn_upscale(http: Arc<Http>, msg: &Message, attachment: &Attachment, min_size: u64) {
+    if let Some((width, height)) = attachment.dimensions() {
+        if width.min(height) < min_size {
+            dm_user(
+                http,
+                msg,
+                &format!(
+                    "Heads-up, {} is smaller than {}x{}px and gets scaled up, it might look blurry.",
+                    attachment.filename, min_size, min_size
+                ),
+            );
+        }
+    }
+}
+
+/// Validates an image attachment of `msg` and downloads it. Returns the data with the format
+/// sniffed from its content. SVGs get rasterized for `output`, the side of the finished square.
+fn download_image(
+    http: Arc<Http>,
+    msg: &Message,
+    attachment: &Attachment,
+    min_size: u64,
+    output: u32,
+) -> Result<(Vec<u8>, ImageFormat), CommandError> {
+    check_attachment(http.clone(), msg, attachment, min_size)?;
+
+    // get the attachment
+    let img = fetch(http.clone(), msg, attachment)?;
+
+    if is_svg(attachment) {
+        let max = CONFIG.read().unwrap().max_image_size as u32;
+        return match pool::run(move || convert::svg(&img, output, max)) {
+            Ok(Ok(png)) => Ok((png, ImageFormat::Png)),
+            Ok(Err(why)) => {
+                dm_user(http, msg, "Error reading the SVG.");
+                Err(CommandError(format!("Rasterizing svg: {}", why)))
+            }
+            Err(why) => pool_err(http, msg, why),
+        };
+    }
+
+    // AVIF and the WebPs the image decoder can't read, as phones and 7TV hand them out
+    if convert::needed(&img) {
+        return match pool::run(move || convert::decode(&img)) {
+            Ok(Ok(x)) => Ok(x),
+            Ok(Err(why)) => {
+                dm_user(http, msg, "Error converting the image, try a PNG or GIF.");
+                Err(CommandError(format!("Converting image: {}", why)))
+            }
+            Err(why) => pool_err(http, msg, why),
+        };
+    }
+
+    // check the real image type, the filename can't be trusted
+    match image::guess_format(&img) {
+        Ok(format @ ImageFormat::Png)
+        | Ok(format @ ImageFormat::Jpeg)
+        | Ok(format @ ImageFormat::Gif)
+        | Ok(format @ ImageFormat::WebP) => Ok((img, format)),
+        Ok(format) => dm_user_err(
+            http,
+            msg,
+            &format!(
+                "Attachment is a {:?}, only JPG, PNG, GIF, WebP, AVIF or SVG are allowed.",
+                format
+            ),
+        ),
+        Err(_) => dm_user_err(http, msg, "Attachment is not an image."),
+    }
+}
+
+/// Downloads a submitted attachment within the download limits.
+fn fetch(http: Arc<Http>, msg: &Message, attachment: &Attachment) -> Result<Vec<u8>, CommandError> {
+    match download::fetch(attachment) {
+        Ok(x) => Ok(x),
+        Err(download::Error::Busy) => dm_user_err(
+            http,
+            msg,
+            "A lot of files are being downloaded right now, try again in a few minutes.",
+        ),
+        Err(why) => {
+            dm_user(http, msg, "Attachment download failed, try again later.");
+            Err(CommandError(format!("Attachment download: {}", why)))
+        }
     }
 }
 
+/// Deletes the message of the submission, only call this after every download finished!
+fn delete_original(http: Arc<Http>, msg: &Message) -> CommandResult {
+    discord(&http, msg, "Deleting org. msg", || msg.delete(&http))
+}
+
+fn is_svg(attachment: &Attachment) -> bool {
+    extension(&attachment.filename).map(str::to_lowercase) == Some(String::from("svg"))
+}
+
+fn extension(filename: &str) -> Option<&str> {
+    Path::new(filename).extension().and_then(OsStr::to_str)
+}
+
+/// Sends through the `outbox`, errors only get printed.
+pub fn send(http: Arc<Http>, target: ChannelId, content: &str) {
+    outbox::push(http, target, content);
+}
+
+/// `rest::call` for commands, a failure gets the usual DM and becomes the error of the command.
+fn discord<T>(
+    http: &Arc<Http>,
+    msg: &Message,
+    name: &'static str,
+    f: impl FnMut() -> serenity::Result<T>,
+) -> Result<T, CommandError> {
+    discord_err(http, msg, name, rest::call(name, f))
+}
+
+/// `discord` for a call that creates something, see `rest::post`.
+fn discord_post<T>(
+    http: &Arc<Http>,
+    msg: &Message,
+    name: &'static str,
+    f: impl FnMut() -> serenity::Result<T>,
+) -> Result<T, CommandError> {
+    discord_err(http, msg, name, rest::post(name, f))
+}
+
+fn discord_err<T>(
+    http: &Arc<Http>,
+    msg: &Message,
+    name: &'static str,
+    result: serenity::Result<T>,
+) -> Result<T, CommandError> {
+    result.map_err(|why| {
+        dm_user(http.clone(), msg, "Discord error, pls try again later.");
+        CommandError(format!("{}: {:?}", name, why))
+    })
+}
+
 fn dm_user(http: Arc<Http>, msg: &Message, content: &str) {
-    if let Err(why) = msg.author.dm(http.clone(), |m| m.content(content)) {
-        println!("Could not send message to {}: {:?}", msg.author, why);
+    if let Err(why) = rest::post("Sending DM", || {
+        msg.author.dm(&http, |m| m.content(content))
+    }) {
+        log!("Could not send message to {}: {:?}", msg.author, why);
         send(http, msg.channel_id, content)
     }
 }
 
-fn dm_user_err(http: Arc<Http>, msg: &Message, content: &str) -> CommandResult {
-    if let Err(why) = msg.author.dm(http.clone(), |m| m.content(&content)) {
-        println!("Could not send message to {}: {:?}", msg.author, why);
+fn dm_user_err<T>(http: Arc<Http>, msg: &Message, content: &str) -> Result<T, CommandError> {
+    if let Err(why) = rest::post("Sending DM", || {
+        msg.author.dm(&http, |m| m.content(content))
+    }) {
+        log!("Could not send message to {}: {:?}", msg.author, why);
         send(http, msg.channel_id, content)
     }
 
-    return Err(CommandError(content.to_string()));
+    Err(CommandError(content.to_string()))
 }
 
 fn main() {
@@ -344,28 +7562,122 @@ fn main() {
 
     // Configure the client with your Discord bot token in the environment.
     let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
+    let mode = CONFIG.read().unwrap().mode;
+    {
+        let config = CONFIG.read().unwrap();
+        if let Some(dir) = &config.log_dir {
+            logfile::start(dir, config.log_max_bytes, config.log_keep);
+        }
+
+        if config.mod_roles.as_ref().is_some_and(|r| r.is_empty()) {
+            log!("No mod_roles, only the permissions and grants allow the mod commands");
+        }
+
+        // a broken rule only shows up when it runs otherwise
+        let rules = config.veto_rules.iter().map(|r| &r.when);
+        for source in rules.chain(config.score_script.as_ref()) {
+            if let Err(why) = script::check(source) {
+                log!("Rule {} doesn't parse: {}", source, why);
+            }
+        }
+    }
+    for unknown in templates::unknown() {
+        log!("Unknown {}, it stays as it is", unknown);
+    }
+    let commands = mod_commands().iter().map(|n| n[0]).collect::<Vec<_>>();
+    for unknown in permissions::unknown(&commands) {
+        log!("Unknown {}, it's ignored", unknown);
+    }
+
+    // a worker needs no gateway, only the REST api to post the suggestions
+    if mode == Mode::Worker {
+        jobs::work(&rest::connect(&token), run_job);
+    }
+
+    restore(store::load());
+
     let mut client = Client::new(&token, Handler).expect("Err creating client");
+    let http = rest::connect(&token);
+    // the owner of the application may use `botstats`
+    let owners = match rest::call("Fetching application", || {
+        http.get_current_application_info()
+    }) {
+        Ok(info) => std::iter::once(info.owner.id).collect(),
+        Err(why) => {
+            log!("Could not fetch the application owner: {:?}", why);
+            HashSet::new()
+        }
+    };
 
     client.with_framework(
         StandardFramework::new()
-            .configure(|c| c.with_whitespace(true).prefix(">>").delimiters(vec![" "]))
-            .after(|_, _, command_name, error| match error {
-                Ok(()) => {}
-                Err(why) => println!("Command {} returned error {:?}", command_name, why),
+            .configure(|c| {
+                c.with_whitespace(true)
+                    .owners(owners)
+                    .dynamic_prefix(|_, msg| {
+                        let guilds = GUILDS.read().unwrap();
+                        let prefix = msg
+                            .guild_id
+                            .and_then(|g| guilds.get(&g))
+                            .map(|g| g.prefix.clone());
+                        Some(prefix.unwrap_or_else(|| GuildConfig::default().prefix))
+                    })
+                    .delimiters(vec![" "])
             })
-            .on_dispatch_error(|ctx, msg, error| {
-                if let DispatchError::Ratelimited(seconds) = error {
-                    let _ = msg.channel_id.say(
-                        &ctx.http,
-                        &format!("Try this again in {} seconds.", seconds),
-                    );
+            .before(|ctx, msg, _| {
+                rest::timed(ctx);
+                usage::start(msg.id);
+                true
+            })
+            .after(|_, msg, command_name, error| match error {
+                Ok(()) => {
+                    usage::finish(msg.id, command_name, false);
+                    store::save(snapshot);
+                }
+                Err(why) => {
+                    usage::finish(msg.id, command_name, true);
+                    log!("Command {} returned error {:?}", command_name, why);
+                    let mut errors = ERRORS.write().unwrap();
+                    errors.push_back(Utc::now());
+                    while errors
+                        .front()
+                        .is_some_and(|t| *t < Utc::now() - Duration::days(1))
+                    {
+                        errors.pop_front();
+                    }
+                }
+            })
+            .on_dispatch_error(|ctx, msg, error| match error {
+                DispatchError::Ratelimited(seconds) => {
+                    rest::timed(ctx);
+                    let content = format!("Try this again in {} seconds.", seconds);
+                    let _ = rest::post("Sending msg", || msg.channel_id.say(&ctx.http, &content));
                 }
+                DispatchError::CheckFailed(_, Reason::Log(why)) => log!("{}", why),
+                _ => {}
             })
             .help(&MY_HELP)
             .group(&GENERAL_GROUP),
     );
 
-    if let Err(why) = client.start() {
-        println!("Client error: {:?}", why);
+    client
+        .data
+        .write()
+        .insert::<ShardManagerContainer>(client.shard_manager.clone());
+    if mode == Mode::All {
+        jobs::start(http.clone(), run_job);
+    }
+    schedule(http.clone(), client.cache_and_http.cache.clone());
+    if let Some(address) = CONFIG.read().unwrap().http_address.clone() {
+        server::start(address);
+    }
+    if let Some(minutes) = CONFIG.read().unwrap().pairwise_minutes {
+        compare_pairs(http, minutes);
+    }
+    jobs::collect(finish_job);
+
+    // Discord recommends the shard count, one is enough until the bot is in a lot of guilds
+    if let Err(why) = client.start_autosharded() {
+        log!("Client error: {:?}", why);
     }
 }
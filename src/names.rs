@@ -0,0 +1,61 @@
+//! Comparable form of names, so `PepeLaugh`, `pepelaugh` and `ＰｅｐｅＬａｕｇｈ` count as the same.
+//! `fold` is NFKC with the full Unicode casefolding, which also covers the styled letters
+//! (mathematical, circled, letterlike), ligatures, super- and subscripts and accents written as
+//! a combining mark.
+use caseless::default_case_fold_str;
+use unicode_normalization::UnicodeNormalization;
+
+/// `name` in the form all comparisons of names use.
+pub fn fold(name: &str) -> String {
+    // the casefolding can undo the normalization (like with ΐ), so it's normalized once more
+    default_case_fold_str(&name.nfkc().collect::<String>())
+        .nfkc()
+        .collect()
+}
+
+pub fn same(a: &str, b: &str) -> bool {
+    fold(a) == fold(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_and_width() {
+        assert_eq!(fold("PepeLaugh"), "pepelaugh");
+        assert_eq!(fold("ＰｅｐｅＬａｕｇｈ"), "pepelaugh");
+        assert!(same("PepeLaugh", "pepelaugh"));
+    }
+
+    #[test]
+    fn styled_letters() {
+        assert_eq!(fold("𝐏𝐞𝐩𝐞"), "pepe");
+        assert_eq!(fold("𝓟𝓮𝓹𝓮"), "pepe");
+        assert_eq!(fold("Ⓟⓔⓟⓔ①"), "pepe1");
+        assert_eq!(fold("𝟙𝟚𝟛"), "123");
+        assert_eq!(fold("ℍ𝕒𝕡𝕡𝕪"), "happy");
+        assert_eq!(fold("ℎℬℯ"), "hbe");
+        assert_eq!(fold("⑩⑳"), "1020");
+        assert_eq!(fold("ｶﾞ"), fold("ガ"));
+    }
+
+    #[test]
+    fn decompositions() {
+        assert_eq!(fold("ﬁre²"), "fire2");
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("ΣΟΦΟΣ"), fold("σοφος"));
+    }
+
+    #[test]
+    fn different_names() {
+        assert!(!same("pepe", "pepe2"));
+        assert!(!same("cafe", "café"));
+    }
+
+    #[test]
+    fn composed_accents() {
+        assert!(same("café", "cafe\u{301}"));
+        assert!(same("CAFÉ", "cafe\u{301}"));
+    }
+}
@@ -0,0 +1,47 @@
+//! Optional NSFW check of new images by an HTTP classifier, a local model server or an
+//! external API. The processed file is posted as the request body, the JSON answer has to
+//! contain a score from 0 to 1.
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Nsfw {
+    pub url: String,
+    /// JSON pointer to the score in the answer
+    pub score: String,
+    /// images at or above this score go to the approval queue
+    pub threshold: f64,
+}
+
+impl Default for Nsfw {
+    fn default() -> Self {
+        Nsfw {
+            url: String::new(),
+            score: String::from("/nsfw"),
+            threshold: 0.8,
+        }
+    }
+}
+
+impl Nsfw {
+    /// Score of `file`, the error is meant for the log.
+    pub fn check(&self, file: &[u8]) -> Result<f64, String> {
+        let answer = Client::new()
+            .post(&self.url)
+            .header("Content-Type", "application/octet-stream")
+            .timeout(Duration::from_secs(30))
+            .body(file.to_vec())
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.json::<Value>())
+            .map_err(|why| why.to_string())?;
+
+        answer
+            .pointer(&self.score)
+            .and_then(Value::as_f64)
+            .ok_or_else(|| format!("no score at {} in {}", self.score, answer))
+    }
+}
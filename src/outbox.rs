@@ -0,0 +1,110 @@
+//! Announcements go out through here one at a time, `outbox_interval_ms` apart, so a bulk
+//! operation like `close` doesn't stall its thread in the rate limit. Texts for the same channel
+//! that wait next to each other get merged into one message.
+use crate::{config::CONFIG, rest};
+use lazy_static::lazy_static;
+use serenity::{http::Http, model::id::ChannelId};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, Once},
+    thread, time,
+};
+
+/// Longest message Discord takes.
+const MAX_LENGTH: usize = 2000;
+/// A longer outbox gets its progress printed.
+const PROGRESS_EVERY: usize = 10;
+
+struct Outgoing {
+    channel: ChannelId,
+    content: String,
+    file: Option<(Vec<u8>, String)>,
+}
+
+lazy_static! {
+    static ref OUTBOX: Mutex<VecDeque<Outgoing>> = Mutex::new(VecDeque::new());
+    static ref WAITING: Condvar = Condvar::new();
+}
+static START: Once = Once::new();
+
+/// Queues `content` for `channel`, the first call starts the sending thread.
+pub fn push(http: Arc<Http>, channel: ChannelId, content: &str) {
+    enqueue(
+        http,
+        Outgoing {
+            channel,
+            content: content.to_string(),
+            file: None,
+        },
+    );
+}
+
+/// Like `push` with a file below the text, these never get merged.
+pub fn push_file(http: Arc<Http>, channel: ChannelId, content: &str, file: Vec<u8>, name: &str) {
+    enqueue(
+        http,
+        Outgoing {
+            channel,
+            content: content.to_string(),
+            file: Some((file, name.to_string())),
+        },
+    );
+}
+
+/// Messages that still have to go out, for `diag`.
+pub fn len() -> usize {
+    OUTBOX.lock().unwrap().len()
+}
+
+fn enqueue(http: Arc<Http>, outgoing: Outgoing) {
+    START.call_once(|| {
+        thread::spawn(move || run(http));
+    });
+    OUTBOX.lock().unwrap().push_back(outgoing);
+    WAITING.notify_one();
+}
+
+fn run(http: Arc<Http>) {
+    loop {
+        let mut outbox = OUTBOX.lock().unwrap();
+        while outbox.is_empty() {
+            outbox = WAITING.wait(outbox).unwrap();
+        }
+        let mut next = match outbox.pop_front() {
+            Some(x) => x,
+            None => continue,
+        };
+        while next.file.is_none() {
+            match outbox.front() {
+                Some(o)
+                    if o.channel == next.channel
+                        && o.file.is_none()
+                        && next.content.len() + 1 + o.content.len() <= MAX_LENGTH => {}
+                _ => break,
+            }
+            if let Some(o) = outbox.pop_front() {
+                next.content += "\n";
+                next.content += &o.content;
+            }
+        }
+        let left = outbox.len();
+        drop(outbox);
+
+        let sent = rest::post("Sending outbox", || match &next.file {
+            Some((file, name)) => next.channel.send_message(&http, |m| {
+                m.content(&next.content);
+                m.add_file((&file[..], &name[..]))
+            }),
+            None => next.channel.say(&http, &next.content),
+        });
+        if let Err(why) = sent {
+            log!("Could not send message: {:?}", why);
+        }
+        if left > 0 && left.is_multiple_of(PROGRESS_EVERY) {
+            log!("Outbox: {} messages left", left);
+        }
+
+        let interval = CONFIG.read().unwrap().outbox_interval_ms;
+        thread::sleep(time::Duration::from_millis(interval));
+    }
+}
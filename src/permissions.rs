@@ -0,0 +1,165 @@
+//! Who may run the mod commands. The mods (`mod_roles` of the config, unset the roles named
+//! Moderator and admin) may run all of them, unless `permissions` gives a command to other
+//! `role_sets`, like `remove` only to the admins. `perm grant` gives a single command to more
+//! roles or users on top. The grants are part of the guild settings, so they apply right away and
+//! survive restarts.
+use crate::{
+    config::{CONFIG, GUILDS},
+    rest, GUILD,
+};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    framework::standard::{macros::check, Args, CheckResult, CommandOptions},
+    http::Http,
+    model::{
+        channel::Message,
+        id::{RoleId, UserId},
+    },
+    prelude::Context,
+    utils,
+};
+use std::collections::BTreeSet;
+
+/// What mods do without a command, checked like one: ✅ and ❌ in the approval queue.
+pub const ACTIONS: [&str; 1] = ["approve"];
+
+/// Everyone a command got granted to, besides the mods.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Grant {
+    pub roles: BTreeSet<RoleId>,
+    pub users: BTreeSet<UserId>,
+}
+
+impl Grant {
+    pub fn is_empty(&self) -> bool {
+        self.roles.is_empty() && self.users.is_empty()
+    }
+}
+
+/// Whether one of `roles` is in the `mod_roles` of the config.
+pub fn is_mod(roles: &[RoleId]) -> bool {
+    let config = CONFIG.read().unwrap();
+    roles
+        .iter()
+        .any(|r| config.mod_roles.iter().flatten().any(|m| m == r))
+}
+
+/// Fills an unset `mod_roles` with the roles of the guild named Moderator or admin, they had the
+/// mod commands before it could be set.
+pub fn seed(http: &Http) {
+    if CONFIG.read().unwrap().mod_roles.is_some() {
+        return;
+    }
+
+    match rest::call("Fetching roles", || http.get_guild_roles(GUILD.0)) {
+        Ok(roles) => {
+            let mods = roles
+                .into_iter()
+                .filter(|r| r.name == "Moderator" || r.name == "admin")
+                .map(|r| r.id)
+                .collect::<Vec<_>>();
+            log!(
+                "No mod_roles, using the roles named Moderator and admin: {:?}",
+                mods
+            );
+            CONFIG.write().unwrap().mod_roles = Some(mods);
+        }
+        Err(why) => log!("Could not get the roles for the mod_roles: {:?}", why),
+    }
+}
+
+/// Whether `user` with `roles` may run `command`, or do one of the `ACTIONS`.
+pub fn allowed(command: &str, user: UserId, roles: &[RoleId]) -> bool {
+    let sets = CONFIG.read().unwrap().permissions.get(command).cloned();
+    let permitted = match sets {
+        Some(sets) => {
+            let config = CONFIG.read().unwrap();
+            sets.iter()
+                .filter_map(|s| config.role_sets.get(s))
+                .flatten()
+                .any(|r| roles.contains(r))
+        }
+        None => is_mod(roles),
+    };
+    if permitted {
+        return true;
+    }
+
+    let guilds = GUILDS.read().unwrap();
+    guilds
+        .get(&GUILD)
+        .and_then(|g| g.permissions.get(command))
+        .is_some_and(|g| g.users.contains(&user) || roles.iter().any(|r| g.roles.contains(r)))
+}
+
+// replaces `allowed_roles` on the mod commands, a command is checked under its first name
+#[check]
+#[name = "Permitted"]
+pub fn permitted(
+    ctx: &mut Context,
+    msg: &Message,
+    _: &mut Args,
+    options: &CommandOptions,
+) -> CheckResult {
+    let roles = msg.member(&ctx.cache).map(|m| m.roles).unwrap_or_default();
+    let command = options.names.first().copied().unwrap_or_default();
+
+    match allowed(command, msg.author.id, &roles) {
+        true => CheckResult::Success,
+        false => CheckResult::new_log(format!("{} may not run {}", msg.author.name, command)),
+    }
+}
+
+/// Who a grant is for, see `target`.
+pub enum Target {
+    Role(RoleId),
+    User(UserId),
+}
+
+impl Target {
+    pub fn mention(&self) -> String {
+        match self {
+            Target::Role(id) => format!("<@&{}>", id),
+            Target::User(id) => format!("<@{}>", id),
+        }
+    }
+}
+
+/// A role or user mention, an id or the name of a role.
+pub fn target(ctx: &Context, arg: &str) -> Option<Target> {
+    if let Some(id) = utils::parse_role(arg) {
+        return Some(Target::Role(RoleId(id)));
+    }
+    if let Some(id) = utils::parse_username(arg) {
+        return Some(Target::User(UserId(id)));
+    }
+
+    let guild = ctx.cache.read().guild(GUILD)?;
+    let guild = guild.read();
+    let role = guild.roles.values().find(|r| match arg.parse::<u64>() {
+        Ok(id) => r.id.0 == id,
+        Err(_) => r.name.eq_ignore_ascii_case(arg),
+    });
+    match (role, arg.parse::<u64>()) {
+        (Some(role), _) => Some(Target::Role(role.id)),
+        (None, Ok(id)) => Some(Target::User(UserId(id))),
+        (None, Err(_)) => None,
+    }
+}
+
+/// Commands and role sets in `permissions` that don't exist, for the startup. `commands` are the
+/// first names of the mod commands.
+pub fn unknown(commands: &[&str]) -> Vec<String> {
+    let config = CONFIG.read().unwrap();
+    let mut unknown = Vec::new();
+    for (command, sets) in config.permissions.iter() {
+        if !commands.contains(&command.as_str()) && !ACTIONS.contains(&command.as_str()) {
+            unknown.push(format!("command {} in permissions", command));
+        }
+        for set in sets.iter().filter(|s| !config.role_sets.contains_key(*s)) {
+            unknown.push(format!("role set {} in permissions of {}", set, command));
+        }
+    }
+    unknown
+}
@@ -0,0 +1,390 @@
+//! Store on top of PostgreSQL, several bots can share one database and it can be queried. Users,
+//! pending suggestions with their voters, the history, the round and the emoji uses have tables of
+//! their own, with the columns worth querying next to the whole entry as JSONB. Everything else is
+//! one JSONB row per namespace. Like the files only rows that changed since the last save get
+//! written, and the counters are changed in place like in Redis.
+use crate::{
+    deadline,
+    store::{Counter, State, Store},
+};
+use postgres::{Client, NoTls, Transaction};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    sync::Mutex,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS users (
+    id BIGINT PRIMARY KEY,
+    name TEXT NOT NULL,
+    counter BIGINT NOT NULL,
+    data JSONB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS suggestions (
+    id BIGINT PRIMARY KEY,
+    name TEXT NOT NULL,
+    author_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    since TIMESTAMPTZ NOT NULL,
+    deadline TIMESTAMPTZ NOT NULL,
+    data JSONB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS votes (
+    suggestion BIGINT NOT NULL REFERENCES suggestions ON DELETE CASCADE,
+    voter BIGINT NOT NULL,
+    at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (suggestion, voter)
+);
+CREATE TABLE IF NOT EXISTS history (
+    position BIGINT PRIMARY KEY,
+    id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    author_id BIGINT NOT NULL,
+    up BIGINT NOT NULL,
+    down BIGINT NOT NULL,
+    accepted BOOLEAN NOT NULL,
+    at TIMESTAMPTZ NOT NULL,
+    data JSONB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS rounds (
+    current BOOLEAN PRIMARY KEY CHECK (current),
+    phase TEXT NOT NULL,
+    voting_at TIMESTAMPTZ,
+    closes_at TIMESTAMPTZ,
+    data JSONB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS emoji_uses (
+    id BIGINT PRIMARY KEY,
+    uses BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS namespaces (
+    name TEXT PRIMARY KEY,
+    value JSONB NOT NULL
+);
+";
+
+/// The namespaces without a table of their own.
+const NAMESPACES: [&str; 12] = [
+    "queue",
+    "trash",
+    "variants",
+    "runoffs",
+    "replacements",
+    "pair",
+    "crowned",
+    "guilds",
+    "rotation",
+    "trials",
+    "modlog",
+    "version",
+];
+
+/// What the database holds as far as this bot knows, the JSON of every row by table and key.
+/// Namespaces and the round are under the key 0 of their name.
+type Written = HashMap<&'static str, HashMap<i64, String>>;
+
+pub struct PgStore {
+    url: String,
+    client: Mutex<Option<Client>>,
+    written: Mutex<Written>,
+}
+
+impl PgStore {
+    pub fn new(url: &str) -> PgStore {
+        PgStore {
+            url: url.to_string(),
+            client: Mutex::new(None),
+            written: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Runs `f` with the connection, it's opened (and the schema created) on the first call and
+    /// again after an error.
+    fn with<T>(
+        &self,
+        f: impl FnOnce(&mut Client) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        let mut client = self.client.lock().unwrap();
+        if client.as_ref().is_none_or(Client::is_closed) {
+            let mut connected = Client::connect(&self.url, NoTls)?;
+            connected.batch_execute(SCHEMA)?;
+            *client = Some(connected);
+        }
+
+        let result = f(client.as_mut().unwrap());
+        if result.is_err() {
+            *client = None;
+        }
+
+        result
+    }
+}
+
+/// Whether the single row of `name` changes to `content`, then it counts as written.
+fn fresh(written: &mut Written, name: &'static str, content: &str) -> bool {
+    let rows = written.entry(name).or_default();
+    match rows.get(&0).map(String::as_str) == Some(content) {
+        true => false,
+        false => {
+            rows.insert(0, content.to_string());
+            true
+        }
+    }
+}
+
+/// Writes the entries of `table` that changed with `write` and deletes the ones that are gone.
+fn sync<'a, T: Serialize + 'a>(
+    tx: &mut Transaction,
+    written: &mut Written,
+    table: &'static str,
+    key: &str,
+    entries: impl Iterator<Item = (i64, &'a T)>,
+    mut write: impl FnMut(&mut Transaction, i64, &T, Value) -> Result<(), postgres::Error>,
+) -> Result<(), Box<dyn Error>> {
+    let rows = written.entry(table).or_default();
+    let mut current = HashSet::new();
+    for (id, entry) in entries {
+        current.insert(id);
+        let content = serde_json::to_string(entry)?;
+        if rows.get(&id) != Some(&content) {
+            write(tx, id, entry, serde_json::from_str(&content)?)?;
+            rows.insert(id, content);
+        }
+    }
+
+    let gone = rows
+        .keys()
+        .filter(|id| !current.contains(id))
+        .copied()
+        .collect::<Vec<_>>();
+    for id in gone {
+        let delete = format!("DELETE FROM {} WHERE {} = $1", table, key);
+        tx.execute(delete.as_str(), &[&id])?;
+        rows.remove(&id);
+    }
+
+    Ok(())
+}
+
+impl Store for PgStore {
+    fn load(&self) -> Result<Map<String, Value>, Box<dyn Error>> {
+        let mut written = Written::new();
+        let mut state = Map::new();
+
+        self.with(|client| {
+            let mut keyed = |table: &'static str, query: &str| {
+                let mut namespace = Map::new();
+                for row in client.query(query, &[])? {
+                    let (id, value): (i64, Value) = (row.get(0), row.get(1));
+                    written
+                        .entry(table)
+                        .or_default()
+                        .insert(id, value.to_string());
+                    namespace.insert(id.to_string(), value);
+                }
+                Ok::<_, postgres::Error>(namespace)
+            };
+
+            // the counter column is the one `add` changes
+            let users = keyed(
+                "users",
+                "SELECT id, jsonb_set(data, '{counter}', to_jsonb(counter)) FROM users",
+            )?;
+            let suggestions = keyed("suggestions", "SELECT id, data FROM suggestions")?;
+            let history = keyed("history", "SELECT position, data FROM history")?;
+            let uses = keyed("emoji_uses", "SELECT id, to_jsonb(uses) FROM emoji_uses")?;
+            let round = keyed("rounds", "SELECT 0::BIGINT, data FROM rounds")?;
+            state.insert(String::from("users"), Value::Object(users));
+            state.insert(String::from("suggestions"), Value::Object(suggestions));
+            state.insert(String::from("emoji_uses"), Value::Object(uses));
+            if let Some(round) = round.get("0") {
+                state.insert(String::from("round"), round.clone());
+            }
+
+            let mut history = history
+                .into_iter()
+                .map(|(position, value)| Ok((position.parse::<i64>()?, value)))
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+            history.sort_by_key(|(position, _)| *position);
+            let history = history.into_iter().map(|(_, value)| value).collect();
+            state.insert(String::from("history"), Value::Array(history));
+
+            for row in client.query("SELECT name, value FROM namespaces", &[])? {
+                let (name, value): (String, Value) = (row.get(0), row.get(1));
+                if let Some(namespace) = NAMESPACES.iter().find(|n| **n == name) {
+                    written
+                        .entry(*namespace)
+                        .or_default()
+                        .insert(0, value.to_string());
+                }
+                state.insert(name, value);
+            }
+
+            Ok(())
+        })?;
+
+        *self.written.lock().unwrap() = written;
+        Ok(state)
+    }
+
+    /// Writes the changed rows in one transaction, a crash in between leaves the old state.
+    fn save(&self, state: &State) -> Result<(), Box<dyn Error>> {
+        let mut written = self.written.lock().unwrap();
+        // only what got committed counts as written
+        let mut next = written.clone();
+
+        self.with(|client| {
+            let mut tx = client.transaction()?;
+
+            sync(
+                &mut tx,
+                &mut next,
+                "users",
+                "id",
+                state.users.iter().map(|(id, u)| (id.0 as i64, u)),
+                |tx, id, user, data| {
+                    tx.execute(
+                        "INSERT INTO users (id, name, counter, data) VALUES ($1, $2, $3, $4)
+                         ON CONFLICT (id) DO UPDATE SET name = $2, data = $4",
+                        &[&id, &user.name, &(user.counter as i64), &data],
+                    )
+                    .map(|_| ())
+                },
+            )?;
+
+            sync(
+                &mut tx,
+                &mut next,
+                "suggestions",
+                "id",
+                state.suggestions.iter().map(|(id, e)| (id.0 as i64, e)),
+                |tx, id, emsg, data| {
+                    tx.execute(
+                        "INSERT INTO suggestions (id, name, author_id, kind, since, deadline, data)
+                         VALUES ($1, $2, $3, $4, $5, $6, $7)
+                         ON CONFLICT (id) DO UPDATE SET name = $2, author_id = $3, kind = $4,
+                         since = $5, deadline = $6, data = $7",
+                        &[
+                            &id,
+                            &emsg.emote.name,
+                            &(emsg.emote.author_id.0 as i64),
+                            &format!("{:?}", emsg.emote.kind),
+                            &emsg.since,
+                            &deadline(emsg),
+                            &data,
+                        ],
+                    )?;
+                    tx.execute("DELETE FROM votes WHERE suggestion = $1", &[&id])?;
+                    for (voter, at) in emsg.voted.iter() {
+                        tx.execute(
+                            "INSERT INTO votes (suggestion, voter, at) VALUES ($1, $2, $3)",
+                            &[&id, &(voter.0 as i64), at],
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+
+            sync(
+                &mut tx,
+                &mut next,
+                "history",
+                "position",
+                state.history.iter().enumerate().map(|(n, f)| (n as i64, f)),
+                |tx, position, finished, data| {
+                    tx.execute(
+                        "INSERT INTO history (position, id, name, author_id, up, down, accepted, at,
+                         data) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                         ON CONFLICT (position) DO UPDATE SET id = $2, name = $3, author_id = $4,
+                         up = $5, down = $6, accepted = $7, at = $8, data = $9",
+                        &[
+                            &position,
+                            &(finished.id.0 as i64),
+                            &finished.name,
+                            &(finished.author_id.0 as i64),
+                            &(finished.votes.0 as i64),
+                            &(finished.votes.1 as i64),
+                            &finished.accepted,
+                            &finished.at,
+                            &data,
+                        ],
+                    )
+                    .map(|_| ())
+                },
+            )?;
+
+            // the uses themselves only change with `add`, new emojis start at the count so far
+            sync(
+                &mut tx,
+                &mut next,
+                "emoji_uses",
+                "id",
+                state.emoji_uses.keys().map(|id| (id.0 as i64, &())),
+                |tx, id, _, _| {
+                    let uses = state.emoji_uses.get(&(id as u64).into()).copied();
+                    tx.execute(
+                        "INSERT INTO emoji_uses (id, uses) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+                        &[&id, &(uses.unwrap_or(0) as i64)],
+                    )
+                    .map(|_| ())
+                },
+            )?;
+
+            let round = &state.round;
+            let content = serde_json::to_string(round)?;
+            if fresh(&mut next, "rounds", &content) {
+                tx.execute(
+                    "INSERT INTO rounds (current, phase, voting_at, closes_at, data)
+                     VALUES (TRUE, $1, $2, $3, $4)
+                     ON CONFLICT (current) DO UPDATE SET phase = $1, voting_at = $2,
+                     closes_at = $3, data = $4",
+                    &[
+                        &format!("{:?}", round.phase),
+                        &round.voting_at,
+                        &round.closes_at,
+                        &serde_json::from_str::<Value>(&content)?,
+                    ],
+                )?;
+            }
+
+            let namespaces = serde_json::to_value(state)?;
+            for name in NAMESPACES.iter() {
+                let value = namespaces.get(*name).unwrap_or(&Value::Null);
+                if fresh(&mut next, name, &value.to_string()) {
+                    tx.execute(
+                        "INSERT INTO namespaces (name, value) VALUES ($1, $2)
+                         ON CONFLICT (name) DO UPDATE SET value = $2",
+                        &[name, value],
+                    )?;
+                }
+            }
+
+            tx.commit()?;
+            Ok(())
+        })?;
+
+        *written = next;
+        Ok(())
+    }
+
+    fn add(&self, counter: Counter, by: i64) -> Result<(), Box<dyn Error>> {
+        self.with(|client| {
+            match counter {
+                // a user that isn't saved yet gets the counter from memory with the next save
+                Counter::Slots(id) => client.execute(
+                    "UPDATE users SET counter = counter + $2 WHERE id = $1",
+                    &[&(id.0 as i64), &by],
+                )?,
+                Counter::EmojiUses(id) => client.execute(
+                    "INSERT INTO emoji_uses (id, uses) VALUES ($1, $2)
+                     ON CONFLICT (id) DO UPDATE SET uses = emoji_uses.uses + $2",
+                    &[&(id.0 as i64), &by],
+                )?,
+            };
+            Ok(())
+        })
+    }
+}
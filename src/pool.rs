@@ -0,0 +1,82 @@
+//! Thread pool for the image processing, the command and job threads only wait for the result
+//! so a huge file can't stall everything else.
+use crate::config::CONFIG;
+use lazy_static::lazy_static;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Mutex,
+    },
+    time::Duration,
+};
+use threadpool::ThreadPool;
+
+#[derive(Debug)]
+pub enum Error {
+    /// `processing_queue` images are already waiting or running
+    Busy,
+    Timeout,
+    Panicked,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Busy => write!(f, "processing queue is full"),
+            Error::Timeout => write!(f, "processing timed out"),
+            Error::Panicked => write!(f, "processing panicked"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+lazy_static! {
+    static ref POOL: Mutex<ThreadPool> = Mutex::new(ThreadPool::with_name(
+        String::from("processing"),
+        CONFIG.read().unwrap().processing_threads.max(1),
+    ));
+    static ref QUEUED: AtomicUsize = AtomicUsize::new(0);
+}
+
+/// Gives the queue slot back once the work is done, even if the caller stopped waiting or the
+/// work panicked.
+struct Slot;
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        QUEUED.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Runs `work` on the pool and waits at most `processing_timeout_secs` for it. A timed out job
+/// can't be killed, it keeps its slot until it finishes on its own.
+pub fn run<T, F>(work: F) -> Result<T, Error>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (limit, timeout) = {
+        let config = CONFIG.read().unwrap();
+        (config.processing_queue, config.processing_timeout_secs)
+    };
+
+    if QUEUED.fetch_add(1, Ordering::SeqCst) >= limit {
+        QUEUED.fetch_sub(1, Ordering::SeqCst);
+        return Err(Error::Busy);
+    }
+
+    let (tx, rx) = mpsc::channel();
+    POOL.lock().unwrap().execute(move || {
+        let _slot = Slot;
+        let _ = tx.send(work());
+    });
+
+    match rx.recv_timeout(Duration::from_secs(timeout)) {
+        Ok(x) => Ok(x),
+        Err(RecvTimeoutError::Timeout) => Err(Error::Timeout),
+        Err(RecvTimeoutError::Disconnected) => Err(Error::Panicked),
+    }
+}
@@ -0,0 +1,521 @@
+//! Store on top of Redis, spoken over the plain RESP protocol so it needs no client library.
+//! Users, the approval queue, removed suggestions, variant polls, runoffs, replacement polls,
+//! emote trials and guild settings are hashes, pending suggestions single keys that expire some
+//! days after their deadline with a sorted set of the deadlines next to it. The history and the
+//! mod actions are lists. The emoji uses and the used suggestions of the users are counters in
+//! their own hashes, changed with HINCRBY as they happen and never overwritten by a save, so
+//! several bots on one Redis don't lose each other's counts.
+use crate::store::{Counter, State, Store};
+use chrono::Duration;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    str,
+    sync::Mutex,
+};
+
+const PREFIX: &str = "touhyou";
+
+#[derive(Debug)]
+enum Reply {
+    /// status and integer replies, their values aren't needed
+    Ok,
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<Reply>>),
+}
+
+impl Reply {
+    fn into_bulks(self) -> Vec<Vec<u8>> {
+        match self {
+            Reply::Array(Some(items)) => items
+                .into_iter()
+                .filter_map(|item| match item {
+                    Reply::Bulk(Some(bulk)) => Some(bulk),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RedisError(String);
+
+impl fmt::Display for RedisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "redis: {}", self.0)
+    }
+}
+
+impl Error for RedisError {}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn open(url: &Url) -> Result<Connection, Box<dyn Error>> {
+        let writer = TcpStream::connect(&url.address)?;
+        let mut connection = Connection {
+            reader: BufReader::new(writer.try_clone()?),
+            writer,
+        };
+
+        if let Some(password) = &url.password {
+            connection.run(vec![cmd(&["AUTH", password])])?;
+        }
+        connection.run(vec![cmd(&["SELECT", &url.db.to_string()])])?;
+
+        Ok(connection)
+    }
+
+    /// Sends all commands at once and returns their replies in order.
+    fn run(&mut self, commands: Vec<Vec<Vec<u8>>>) -> Result<Vec<Reply>, Box<dyn Error>> {
+        let mut buf = Vec::new();
+        for args in commands.iter() {
+            buf.extend(format!("*{}\r\n", args.len()).into_bytes());
+            for arg in args {
+                buf.extend(format!("${}\r\n", arg.len()).into_bytes());
+                buf.extend(arg);
+                buf.extend(b"\r\n");
+            }
+        }
+        self.writer.write_all(&buf)?;
+
+        commands.iter().map(|_| self.reply()).collect()
+    }
+
+    fn line(&mut self) -> Result<String, Box<dyn Error>> {
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        if !line.ends_with("\r\n") {
+            return Err(Box::new(RedisError(String::from("connection closed"))));
+        }
+        line.truncate(line.len() - 2);
+
+        Ok(line)
+    }
+
+    fn reply(&mut self) -> Result<Reply, Box<dyn Error>> {
+        let line = self.line()?;
+        let (kind, rest) = line.split_at(1.min(line.len()));
+
+        match kind {
+            "+" | ":" => Ok(Reply::Ok),
+            "-" => Err(Box::new(RedisError(rest.to_string()))),
+            "$" => match rest.parse::<i64>()? {
+                -1 => Ok(Reply::Bulk(None)),
+                len => {
+                    let mut bulk = vec![0; len as usize + 2];
+                    self.reader.read_exact(&mut bulk)?;
+                    bulk.truncate(len as usize);
+                    Ok(Reply::Bulk(Some(bulk)))
+                }
+            },
+            "*" => match rest.parse::<i64>()? {
+                -1 => Ok(Reply::Array(None)),
+                len => Ok(Reply::Array(Some(
+                    (0..len).map(|_| self.reply()).collect::<Result<_, _>>()?,
+                ))),
+            },
+            _ => Err(Box::new(RedisError(format!("unexpected reply {}", line)))),
+        }
+    }
+}
+
+fn cmd(args: &[&str]) -> Vec<Vec<u8>> {
+    args.iter().map(|a| a.as_bytes().to_vec()).collect()
+}
+
+fn key(name: &str) -> String {
+    format!("{}:{}", PREFIX, name)
+}
+
+fn suggestion_key(id: &str) -> String {
+    key(&format!("suggestion:{}", id))
+}
+
+fn decode<T: DeserializeOwned>(bulk: &[u8]) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_slice(bulk)?)
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(serde_json::to_vec(value)?)
+}
+
+/// `redis://[:password@]host[:port][/db]`
+struct Url {
+    address: String,
+    password: Option<String>,
+    db: u32,
+}
+
+impl Url {
+    fn parse(url: &str) -> Url {
+        let rest = url.trim_start_matches("redis://");
+        let (password, rest) = match rest.rsplit_once('@') {
+            Some((auth, rest)) => (Some(auth.trim_start_matches(':').to_string()), rest),
+            None => (None, rest),
+        };
+        let (host, db) = match rest.split_once('/') {
+            Some((host, db)) => (host, db.parse().unwrap_or(0)),
+            None => (rest, 0),
+        };
+        let address = match host.contains(':') {
+            true => host.to_string(),
+            false => format!("{}:6379", host),
+        };
+
+        Url {
+            address,
+            password,
+            db,
+        }
+    }
+}
+
+type Pairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Queues the commands that replace the hash `name` with `entries`.
+fn replace_hash<'a, T: Serialize + 'a>(
+    commands: &mut Vec<Vec<Vec<u8>>>,
+    name: &str,
+    entries: impl Iterator<Item = (u64, &'a T)>,
+) -> Result<(), Box<dyn Error>> {
+    commands.push(cmd(&["DEL", &key(name)]));
+
+    let mut hset = cmd(&["HSET", &key(name)]);
+    for (id, value) in entries {
+        hset.push(id.to_string().into_bytes());
+        hset.push(encode(value)?);
+    }
+    if hset.len() > 2 {
+        commands.push(hset);
+    }
+
+    Ok(())
+}
+
+pub struct RedisStore {
+    url: Url,
+    /// pending suggestions are kept this long after their deadline
+    expire: Duration,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl RedisStore {
+    pub fn new(url: &str, expire_days: i64) -> RedisStore {
+        RedisStore {
+            url: Url::parse(url),
+            expire: Duration::days(expire_days),
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Runs the commands, a broken connection is opened again on the next call.
+    fn run(&self, commands: Vec<Vec<Vec<u8>>>) -> Result<Vec<Reply>, Box<dyn Error>> {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            *connection = Some(Connection::open(&self.url)?);
+        }
+
+        let replies = connection.as_mut().unwrap().run(commands);
+        if replies.is_err() {
+            *connection = None;
+        }
+
+        replies
+    }
+
+    /// Pairs of field and value of a hash.
+    fn hash(&self, name: &str) -> Result<Pairs, Box<dyn Error>> {
+        let mut replies = self.run(vec![cmd(&["HGETALL", &key(name)])])?;
+        let mut bulks = replies.remove(0).into_bulks().into_iter();
+
+        let mut pairs = Vec::new();
+        while let (Some(field), Some(value)) = (bulks.next(), bulks.next()) {
+            pairs.push((field, value));
+        }
+
+        Ok(pairs)
+    }
+}
+
+impl Store for RedisStore {
+    fn load(&self) -> Result<Map<String, Value>, Box<dyn Error>> {
+        let mut state = Map::new();
+
+        for name in [
+            "users",
+            "queue",
+            "trash",
+            "guilds",
+            "variants",
+            "runoffs",
+            "replacements",
+            "trials",
+            "emoji_uses",
+        ]
+        .iter()
+        {
+            let mut namespace = Map::new();
+            for (field, value) in self.hash(name)? {
+                namespace.insert(String::from_utf8(field)?, decode(&value)?);
+            }
+            state.insert(name.to_string(), Value::Object(namespace));
+        }
+
+        // users from before the counters start theirs at the stored value
+        if let Some(Value::Object(users)) = state.get_mut("users") {
+            let seeds = users
+                .iter()
+                .filter_map(|(id, user)| Some((id, user.get("counter")?.as_u64()?)))
+                .map(|(id, counter)| cmd(&["HSETNX", &key("slots"), id, &counter.to_string()]))
+                .collect::<Vec<_>>();
+            if !seeds.is_empty() {
+                self.run(seeds)?;
+            }
+
+            for (field, value) in self.hash("slots")? {
+                let counter: u64 = decode(&value)?;
+                if let Some(Value::Object(user)) = users.get_mut(str::from_utf8(&field)?) {
+                    user.insert(String::from("counter"), Value::from(counter));
+                }
+            }
+        }
+
+        let mut replies = self.run(vec![
+            cmd(&["ZRANGE", &key("suggestions"), "0", "-1"]),
+            cmd(&["LRANGE", &key("history"), "0", "-1"]),
+            cmd(&["LRANGE", &key("modlog"), "0", "-1"]),
+            cmd(&["SMEMBERS", &key("crowned")]),
+            cmd(&["GET", &key("version")]),
+            cmd(&["GET", &key("pair")]),
+            cmd(&["GET", &key("round")]),
+            cmd(&["GET", &key("rotation")]),
+        ])?;
+        let ids = replies.remove(0).into_bulks();
+        let history = replies
+            .remove(0)
+            .into_bulks()
+            .iter()
+            .map(|finished| decode(finished))
+            .collect::<Result<Vec<Value>, _>>()?;
+        state.insert(String::from("history"), Value::Array(history));
+        let modlog = replies
+            .remove(0)
+            .into_bulks()
+            .iter()
+            .map(|action| decode(action))
+            .collect::<Result<Vec<Value>, _>>()?;
+        state.insert(String::from("modlog"), Value::Array(modlog));
+        let crowned = replies
+            .remove(0)
+            .into_bulks()
+            .iter()
+            .map(|id| Ok(Value::from(str::from_utf8(id)?.parse::<u64>()?)))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        state.insert(String::from("crowned"), Value::Array(crowned));
+        if let Reply::Bulk(Some(version)) = replies.remove(0) {
+            state.insert(String::from("version"), decode(&version)?);
+        }
+        if let Reply::Bulk(Some(pair)) = replies.remove(0) {
+            state.insert(String::from("pair"), decode(&pair)?);
+        }
+        if let Reply::Bulk(Some(round)) = replies.remove(0) {
+            state.insert(String::from("round"), decode(&round)?);
+        }
+        if let Reply::Bulk(Some(rotation)) = replies.remove(0) {
+            state.insert(String::from("rotation"), decode(&rotation)?);
+        }
+
+        // the keys of expired suggestions are gone, the next save drops their ids too
+        let gets = ids
+            .iter()
+            .map(|id| Ok(cmd(&["GET", &suggestion_key(str::from_utf8(id)?)])))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        let mut suggestions = Map::new();
+        for (id, reply) in ids.into_iter().zip(self.run(gets)?) {
+            if let Reply::Bulk(Some(bulk)) = reply {
+                suggestions.insert(String::from_utf8(id)?, decode(&bulk)?);
+            }
+        }
+        state.insert(String::from("suggestions"), Value::Object(suggestions));
+
+        Ok(state)
+    }
+
+    /// Replaces everything but the counters in one transaction, a crash in between leaves the old
+    /// state. Counters are only added when missing and deleted with their emoji.
+    fn save(&self, state: &State) -> Result<(), Box<dyn Error>> {
+        let mut replies = self.run(vec![
+            cmd(&["ZRANGE", &key("suggestions"), "0", "-1"]),
+            cmd(&["HKEYS", &key("emoji_uses")]),
+        ])?;
+        let ids = |reply: Reply| {
+            reply
+                .into_bulks()
+                .into_iter()
+                .filter_map(|id| String::from_utf8(id).ok())
+                .collect::<HashSet<_>>()
+        };
+        let stored = ids(replies.remove(0));
+        let counted = ids(replies.remove(0));
+
+        let mut commands = vec![cmd(&["MULTI"])];
+
+        replace_hash(
+            &mut commands,
+            "users",
+            state.users.iter().map(|(id, u)| (id.0, u)),
+        )?;
+        replace_hash(
+            &mut commands,
+            "queue",
+            state.queue.iter().map(|(id, e)| (id.0, e)),
+        )?;
+        replace_hash(
+            &mut commands,
+            "trash",
+            state.trash.iter().map(|(id, t)| (id.0, t)),
+        )?;
+        replace_hash(
+            &mut commands,
+            "variants",
+            state.variants.iter().map(|(id, p)| (id.0, p)),
+        )?;
+        replace_hash(
+            &mut commands,
+            "runoffs",
+            state.runoffs.iter().map(|(id, r)| (id.0, r)),
+        )?;
+        replace_hash(
+            &mut commands,
+            "replacements",
+            state.replacements.iter().map(|(id, r)| (id.0, r)),
+        )?;
+        replace_hash(
+            &mut commands,
+            "trials",
+            state.trials.iter().map(|(id, t)| (id.0, t)),
+        )?;
+        let used = state
+            .emoji_uses
+            .keys()
+            .map(|id| id.0.to_string())
+            .collect::<HashSet<_>>();
+        for id in counted.difference(&used) {
+            commands.push(cmd(&["HDEL", &key("emoji_uses"), id]));
+        }
+        for (id, uses) in state.emoji_uses.iter() {
+            let id = id.0.to_string();
+            commands.push(cmd(&["HSETNX", &key("emoji_uses"), &id, &uses.to_string()]));
+        }
+        for (id, user) in state.users.iter() {
+            let (id, counter) = (id.0.to_string(), user.counter.to_string());
+            commands.push(cmd(&["HSETNX", &key("slots"), &id, &counter]));
+        }
+        replace_hash(
+            &mut commands,
+            "guilds",
+            state.guilds.iter().map(|(id, g)| (id.0, g)),
+        )?;
+
+        let pending = state
+            .suggestions
+            .keys()
+            .map(|id| id.0.to_string())
+            .collect::<HashSet<_>>();
+        for id in stored.difference(&pending) {
+            commands.push(cmd(&["DEL", &suggestion_key(id)]));
+            commands.push(cmd(&["ZREM", &key("suggestions"), id]));
+        }
+        for (id, emsg) in state.suggestions.iter() {
+            let id = id.0.to_string();
+            let deadline = crate::deadline(emsg).timestamp().to_string();
+            let expire = (crate::deadline(emsg) + self.expire)
+                .timestamp()
+                .to_string();
+
+            let mut set = cmd(&["SET", &suggestion_key(&id)]);
+            set.push(encode(emsg)?);
+            commands.push(set);
+            commands.push(cmd(&["EXPIREAT", &suggestion_key(&id), &expire]));
+            commands.push(cmd(&["ZADD", &key("suggestions"), &deadline, &id]));
+        }
+
+        commands.push(cmd(&["DEL", &key("history")]));
+        if !state.history.is_empty() {
+            let mut rpush = cmd(&["RPUSH", &key("history")]);
+            for finished in state.history.iter() {
+                rpush.push(encode(finished)?);
+            }
+            commands.push(rpush);
+        }
+
+        commands.push(cmd(&["DEL", &key("modlog")]));
+        if !state.modlog.is_empty() {
+            let mut rpush = cmd(&["RPUSH", &key("modlog")]);
+            for action in state.modlog.iter() {
+                rpush.push(encode(action)?);
+            }
+            commands.push(rpush);
+        }
+
+        commands.push(cmd(&["DEL", &key("crowned")]));
+        if !state.crowned.is_empty() {
+            let mut sadd = cmd(&["SADD", &key("crowned")]);
+            for id in state.crowned.iter() {
+                sadd.push(id.0.to_string().into_bytes());
+            }
+            commands.push(sadd);
+        }
+
+        match &state.pair {
+            Some(pair) => {
+                let mut set = cmd(&["SET", &key("pair")]);
+                set.push(encode(pair)?);
+                commands.push(set);
+            }
+            None => commands.push(cmd(&["DEL", &key("pair")])),
+        }
+        let mut set = cmd(&["SET", &key("round")]);
+        set.push(encode(&state.round)?);
+        commands.push(set);
+        let mut set = cmd(&["SET", &key("rotation")]);
+        set.push(encode(&state.rotation)?);
+        commands.push(set);
+        commands.push(cmd(&["SET", &key("version"), &state.version.to_string()]));
+        commands.push(cmd(&["EXEC"]));
+
+        match self.run(commands)?.pop() {
+            Some(Reply::Array(Some(_))) => Ok(()),
+            reply => Err(Box::new(RedisError(format!(
+                "transaction failed: {:?}",
+                reply
+            )))),
+        }
+    }
+
+    fn add(&self, counter: Counter, by: i64) -> Result<(), Box<dyn Error>> {
+        let (name, id) = match counter {
+            Counter::Slots(id) => ("slots", id.0),
+            Counter::EmojiUses(id) => ("emoji_uses", id.0),
+        };
+        self.run(vec![cmd(&[
+            "HINCRBY",
+            &key(name),
+            &id.to_string(),
+            &by.to_string(),
+        ])])?;
+
+        Ok(())
+    }
+}
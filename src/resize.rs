@@ -0,0 +1,247 @@
+//! Downscaling with a selectable filter, pixel art wants nearest-neighbor while photos look best
+//! with Lanczos. Non-square images get cropped, fitted or stretched into the square.
+use image::{imageops, imageops::FilterType, DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    /// fast integer sampling, what `thumbnail_exact` does
+    Thumbnail,
+    Nearest,
+    Lanczos,
+    CatmullRom,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &*s.to_lowercase() {
+            "thumbnail" => Ok(Filter::Thumbnail),
+            "nearest" => Ok(Filter::Nearest),
+            "lanczos" => Ok(Filter::Lanczos),
+            "catmullrom" => Ok(Filter::CatmullRom),
+            _ => Err(format!(
+                "Unknown filter {}, use nearest, lanczos or catmullrom.",
+                s
+            )),
+        }
+    }
+}
+
+/// How a non-square image becomes square.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Aspect {
+    /// cut off the longer side around the center
+    Crop,
+    /// keep the whole image, the rest of the square stays transparent
+    Fit,
+    /// squash the image into the square
+    #[default]
+    Stretch,
+}
+
+/// The square `Aspect::Crop` keeps, the submitter can move it before posting.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    /// center as a fraction of the width
+    pub x: f64,
+    /// center as a fraction of the height
+    pub y: f64,
+    /// 1 keeps all of the shorter side
+    pub zoom: f64,
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Window {
+            x: 0.5,
+            y: 0.5,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Window {
+    /// Left, top and side of the square in a `width`x`height` image, always inside of it.
+    fn bounds(&self, width: u32, height: u32) -> (u32, u32, u32) {
+        let side = ((width.min(height) as f64 / self.zoom.max(1.0)).round() as u32).max(1);
+        let start = |center: f64, len: u32| {
+            (center * len as f64 - side as f64 / 2.0)
+                .round()
+                .clamp(0.0, (len - side) as f64) as u32
+        };
+
+        (start(self.x, width), start(self.y, height), side)
+    }
+}
+
+impl FromStr for Aspect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &*s.to_lowercase() {
+            "crop" => Ok(Aspect::Crop),
+            "fit" => Ok(Aspect::Fit),
+            "stretch" => Ok(Aspect::Stretch),
+            _ => Err(format!("Unknown mode {}, use crop, fit or stretch.", s)),
+        }
+    }
+}
+
+impl Filter {
+    fn filter_type(self) -> Option<FilterType> {
+        match self {
+            Filter::Thumbnail => None,
+            Filter::Nearest => Some(FilterType::Nearest),
+            Filter::Lanczos => Some(FilterType::Lanczos3),
+            Filter::CatmullRom => Some(FilterType::CatmullRom),
+        }
+    }
+}
+
+pub fn resize(img: &DynamicImage, width: u32, height: u32, filter: Filter) -> DynamicImage {
+    match filter.filter_type() {
+        Some(filter) => img.resize_exact(width, height, filter),
+        None => img.thumbnail_exact(width, height),
+    }
+}
+
+pub fn resize_rgba(img: &RgbaImage, width: u32, height: u32, filter: Filter) -> RgbaImage {
+    match filter.filter_type() {
+        Some(filter) => imageops::resize(img, width, height, filter),
+        None => imageops::thumbnail(img, width, height),
+    }
+}
+
+/// Scales `img` into a `size`x`size` square, `aspect` decides what happens to the aspect ratio.
+/// `window` is only used for `Aspect::Crop`.
+pub fn square(
+    img: &DynamicImage,
+    size: u32,
+    filter: Filter,
+    aspect: Aspect,
+    window: Window,
+) -> DynamicImage {
+    match aspect {
+        Aspect::Stretch => resize(img, size, size, filter),
+        _ => DynamicImage::ImageRgba8(square_rgba(&img.to_rgba8(), size, filter, aspect, window)),
+    }
+}
+
+pub fn square_rgba(
+    img: &RgbaImage,
+    size: u32,
+    filter: Filter,
+    aspect: Aspect,
+    window: Window,
+) -> RgbaImage {
+    let (width, height) = img.dimensions();
+
+    match aspect {
+        Aspect::Stretch => resize_rgba(img, size, size, filter),
+        Aspect::Crop => {
+            let (x, y, side) = window.bounds(width, height);
+            let cropped = imageops::crop_imm(img, x, y, side, side).to_image();
+            resize_rgba(&cropped, size, size, filter)
+        }
+        Aspect::Fit => {
+            let scale = size as f64 / width.max(height) as f64;
+            let w = ((width as f64 * scale).round() as u32).clamp(1, size);
+            let h = ((height as f64 * scale).round() as u32).clamp(1, size);
+
+            let mut out = RgbaImage::new(size, size);
+            imageops::overlay(
+                &mut out,
+                &resize_rgba(img, w, h, filter),
+                (size - w) / 2,
+                (size - h) / 2,
+            );
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgba};
+
+    /// Left half red, right half blue.
+    fn halves(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, _| match x < width / 2 {
+            true => Rgba([255, 0, 0, 255]),
+            false => Rgba([0, 0, 255, 255]),
+        })
+    }
+
+    #[test]
+    fn every_filter_makes_the_square() {
+        let img = DynamicImage::ImageRgba8(halves(300, 200));
+        for filter in [
+            Filter::Thumbnail,
+            Filter::Nearest,
+            Filter::Lanczos,
+            Filter::CatmullRom,
+        ] {
+            for aspect in [Aspect::Crop, Aspect::Fit, Aspect::Stretch] {
+                let out = square(&img, 128, filter, aspect, Window::default());
+                assert_eq!(out.dimensions(), (128, 128), "{:?} {:?}", filter, aspect);
+            }
+        }
+    }
+
+    #[test]
+    fn fit_leaves_transparent_bars() {
+        let out = square_rgba(
+            &halves(200, 100),
+            100,
+            Filter::Nearest,
+            Aspect::Fit,
+            Window::default(),
+        );
+        assert_eq!(out.get_pixel(50, 0)[3], 0);
+        assert_eq!(out.get_pixel(50, 99)[3], 0);
+        assert_eq!(*out.get_pixel(10, 50), Rgba([255, 0, 0, 255]));
+        assert_eq!(*out.get_pixel(90, 50), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn crop_follows_the_window() {
+        let img = halves(200, 100);
+        let left = Window {
+            x: 0.0,
+            y: 0.5,
+            zoom: 1.0,
+        };
+        let right = Window { x: 1.0, ..left };
+
+        let out = square_rgba(&img, 50, Filter::Nearest, Aspect::Crop, left);
+        assert!(out.pixels().all(|p| *p == Rgba([255, 0, 0, 255])));
+        let out = square_rgba(&img, 50, Filter::Nearest, Aspect::Crop, right);
+        assert!(out.pixels().all(|p| *p == Rgba([0, 0, 255, 255])));
+    }
+
+    #[test]
+    fn window_stays_inside() {
+        let zoomed = Window {
+            x: 1.0,
+            y: 0.0,
+            zoom: 4.0,
+        };
+        assert_eq!(zoomed.bounds(200, 100), (175, 0, 25));
+        assert_eq!(Window::default().bounds(100, 300), (0, 100, 100));
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!("Lanczos".parse(), Ok(Filter::Lanczos));
+        assert_eq!("catmullrom".parse(), Ok(Filter::CatmullRom));
+        assert!("bicubic".parse::<Filter>().is_err());
+        assert_eq!("FIT".parse(), Ok(Aspect::Fit));
+        assert!("zoom".parse::<Aspect>().is_err());
+    }
+}
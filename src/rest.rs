@@ -0,0 +1,146 @@
+//! Discord calls through serenity go through `call` or `post`: failures that may go away get
+//! retried with a growing pause until `rest_retries` or the `rest_timeout_ms` of the call are used
+//! up, a request that may have arrived only when it's safe to send twice. Every request of the
+//! `Http` from `connect` gives up after `rest_timeout_ms`, and every call is timed and counted
+//! under its name for `diag`. Left out are serenity's own calls like the help, and the endpoints
+//! serenity 0.8 lacks: `sticker`, `sound`, `crosspost` and `slash` send those with a `Client` of
+//! their own, without retries and not counted.
+use crate::config::CONFIG;
+use lazy_static::lazy_static;
+use reqwest::blocking::Client;
+use serenity::{http::Http, http::HttpError, prelude::Context, Error};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Pause before the first retry, it doubles with every further one.
+const BACKOFF_MS: u64 = 250;
+
+#[derive(Default, Clone, Copy)]
+pub struct Metric {
+    pub calls: u64,
+    pub errors: u64,
+    pub retries: u64,
+    /// summed up over all calls, with the retries
+    pub millis: u64,
+}
+
+lazy_static! {
+    static ref METRICS: Mutex<HashMap<&'static str, Metric>> = Mutex::new(HashMap::new());
+    /// the one of `connect`, all calls share its rate limits
+    static ref HTTP: RwLock<Option<Arc<Http>>> = RwLock::new(None);
+}
+
+/// The `Http` of the bot, serenity's own waits 30 seconds for an answer. `timed` hands it to the
+/// events and commands.
+pub fn connect(token: &str) -> Arc<Http> {
+    let token = match token.starts_with("Bot ") {
+        true => token.to_string(),
+        false => format!("Bot {}", token),
+    };
+    let timeout = Duration::from_millis(CONFIG.read().unwrap().rest_timeout_ms);
+
+    let http = match Client::builder().use_rustls_tls().timeout(timeout).build() {
+        Ok(client) => Http::new(Arc::new(client), &token),
+        Err(why) => {
+            log!("Could not set the request timeout: {}", why);
+            Http::new_with_token(&token)
+        }
+    };
+    let http = Arc::new(http);
+    *HTTP.write().unwrap() = Some(http.clone());
+    http
+}
+
+/// Gives `ctx` the `Http` of `connect` instead of the one of the serenity client.
+pub fn timed(ctx: &mut Context) {
+    if let Some(http) = &*HTTP.read().unwrap() {
+        ctx.http = http.clone();
+    }
+}
+
+/// Whether `error` may go away with a retry. Unless the request is `idempotent` that's only when
+/// Discord can't have done it: the connection failed or it answered with a server error.
+fn transient(error: &Error, idempotent: bool) -> bool {
+    match error {
+        Error::Http(http) => match &**http {
+            HttpError::UnsuccessfulRequest(response) => response.status_code.is_server_error(),
+            HttpError::Request(why) => idempotent || why.is_connect(),
+            _ => false,
+        },
+        Error::Io(_) => idempotent,
+        _ => false,
+    }
+}
+
+/// Runs `f` with retries and records it as `name`, a GET, PATCH or DELETE that does the same when
+/// it's sent twice.
+pub fn call<T>(name: &'static str, f: impl FnMut() -> serenity::Result<T>) -> serenity::Result<T> {
+    run(name, true, f)
+}
+
+/// `call` for a request that creates something like a message or an emoji, a lost answer isn't
+/// retried since it would be created twice.
+pub fn post<T>(name: &'static str, f: impl FnMut() -> serenity::Result<T>) -> serenity::Result<T> {
+    run(name, false, f)
+}
+
+fn run<T>(
+    name: &'static str,
+    idempotent: bool,
+    mut f: impl FnMut() -> serenity::Result<T>,
+) -> serenity::Result<T> {
+    let (retries, timeout) = {
+        let config = CONFIG.read().unwrap();
+        (
+            config.rest_retries,
+            Duration::from_millis(config.rest_timeout_ms),
+        )
+    };
+    let start = Instant::now();
+
+    let mut tries = 0;
+    let result = loop {
+        let result = f();
+        let pause = Duration::from_millis(BACKOFF_MS << tries);
+        match &result {
+            Err(why)
+                if transient(why, idempotent)
+                    && tries < retries
+                    && start.elapsed() + pause < timeout =>
+            {
+                log!("{} failed, retrying: {:?}", name, why);
+                thread::sleep(pause);
+                tries += 1;
+            }
+            _ => break result,
+        }
+    };
+
+    let mut metrics = METRICS.lock().unwrap();
+    let metric = metrics.entry(name).or_default();
+    metric.calls += 1;
+    metric.retries += tries as u64;
+    metric.millis += start.elapsed().as_millis() as u64;
+    if result.is_err() {
+        metric.errors += 1;
+    }
+
+    result
+}
+
+/// Every name with its numbers, the most called first.
+pub fn metrics() -> Vec<(&'static str, Metric)> {
+    let mut metrics = METRICS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, metric)| (*name, *metric))
+        .collect::<Vec<_>>();
+    metrics.sort_by_key(|(_, m)| Reverse(m.calls));
+    metrics
+}
@@ -0,0 +1,436 @@
+//! Small expression language for rules of the community, set in the config without touching the
+//! bot: `veto_rules` refuse submissions, `score_script` replaces 👍 minus 👎 when a round is
+//! decided. There are no loops or side effects, a rule can only read the variables it gets.
+//!
+//! There are numbers, "strings", true/false, `+ - * / %`, comparisons, `&& || !`, `contains`,
+//! `starts_with`, `ends_with`, `lower(...)` and `cond ? a : b`, like
+//! `kind == "sticker" ? pos - 2 * neg : pos`. The string tests and `==` mind the case,
+//! `lower(name) contains "laugh"` doesn't.
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+/// A submission for which `when` is true gets refused with `reason`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Veto {
+    pub when: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Num(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Num(n)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Self {
+        Value::Num(n as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+/// What a script can read, by name.
+pub type Vars = HashMap<&'static str, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+/// Longer operators first, so `<=` isn't read as `<`.
+const OPS: [&str; 18] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", "?", ":",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text = chars[start..i].iter().collect::<String>();
+            let n = text.parse().map_err(|_| format!("bad number {}", text))?;
+            tokens.push(Token::Num(n));
+        } else if c == '"' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    Some('"') => break,
+                    Some('\\') if chars.get(i + 1).is_some() => {
+                        text.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(c) => {
+                        text.push(*c);
+                        i += 1;
+                    }
+                    None => return Err(String::from("unclosed string")),
+                }
+            }
+            i += 1;
+            tokens.push(Token::Str(text));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let rest = chars[i..].iter().take(2).collect::<String>();
+            let op = OPS
+                .iter()
+                .find(|op| rest.starts_with(*op))
+                .ok_or_else(|| format!("unexpected {}", c))?;
+            tokens.push(Token::Op(op));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Lit(Value),
+    Var(String),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Lower(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+/// Binding strength of the binary operators, the words are string tests.
+fn precedence(token: &Token) -> Option<(&'static str, u8)> {
+    let op = match token {
+        Token::Op(op) => *op,
+        Token::Ident(word) => match word.as_str() {
+            "contains" => "contains",
+            "starts_with" => "starts_with",
+            "ends_with" => "ends_with",
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let strength = match op {
+        "||" => 1,
+        "&&" => 2,
+        "==" | "!=" => 3,
+        "<" | "<=" | ">" | ">=" | "contains" | "starts_with" | "ends_with" => 4,
+        "+" | "-" => 5,
+        "*" | "/" | "%" => 6,
+        _ => return None,
+    };
+    Some((op, strength))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.at += 1;
+        self.tokens.get(self.at - 1).cloned()
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Op(o)) if o == op => Ok(()),
+            other => Err(format!("expected {} instead of {:?}", op, other)),
+        }
+    }
+
+    fn conditional(&mut self) -> Result<Expr, String> {
+        let cond = self.binary(0)?;
+        if self.peek() != Some(&Token::Op("?")) {
+            return Ok(cond);
+        }
+        self.next();
+        let then = self.conditional()?;
+        self.expect(":")?;
+        let otherwise = self.conditional()?;
+        Ok(Expr::If(
+            Box::new(cond),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn binary(&mut self, min: u8) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some((op, strength)) = self.peek().and_then(precedence) {
+            if strength <= min {
+                break;
+            }
+            self.next();
+            let right = self.binary(strength)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Op("!")) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Op("-")) => Ok(Expr::Neg(Box::new(self.unary()?))),
+            Some(Token::Op("(")) => {
+                let inner = self.conditional()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Some(Token::Num(n)) => Ok(Expr::Lit(Value::Num(n))),
+            Some(Token::Str(s)) => Ok(Expr::Lit(Value::Str(s))),
+            // the only function, a variable can't be followed by (
+            Some(Token::Ident(word)) if word == "lower" && self.peek() == Some(&Token::Op("(")) => {
+                self.next();
+                let inner = self.conditional()?;
+                self.expect(")")?;
+                Ok(Expr::Lower(Box::new(inner)))
+            }
+            Some(Token::Ident(word)) => Ok(match word.as_str() {
+                "true" => Expr::Lit(Value::Bool(true)),
+                "false" => Expr::Lit(Value::Bool(false)),
+                _ => Expr::Var(word),
+            }),
+            other => Err(format!("unexpected {:?}", other)),
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        at: 0,
+    };
+    let expr = parser.conditional()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {:?}", token)),
+    }
+}
+
+fn eval(expr: &Expr, vars: &Vars) -> Result<Value, String> {
+    use Value::*;
+    Ok(match expr {
+        Expr::Lit(value) => value.clone(),
+        Expr::Var(name) => vars
+            .get(name.as_str())
+            .cloned()
+            .ok_or_else(|| format!("unknown variable {}", name))?,
+        Expr::Not(inner) => match eval(inner, vars)? {
+            Bool(b) => Bool(!b),
+            other => return Err(format!("! needs true or false, not {}", other)),
+        },
+        Expr::Neg(inner) => match eval(inner, vars)? {
+            Num(n) => Num(-n),
+            other => return Err(format!("- needs a number, not {}", other)),
+        },
+        Expr::Lower(inner) => match eval(inner, vars)? {
+            Str(s) => Str(s.to_lowercase()),
+            other => return Err(format!("lower needs a string, not {}", other)),
+        },
+        Expr::If(cond, then, otherwise) => match eval(cond, vars)? {
+            Bool(true) => eval(then, vars)?,
+            Bool(false) => eval(otherwise, vars)?,
+            other => return Err(format!("? needs true or false, not {}", other)),
+        },
+        // both sides of && and || are only read when needed
+        Expr::Binary("&&", left, right) => match eval(left, vars)? {
+            Bool(false) => Bool(false),
+            Bool(true) => eval(right, vars)?,
+            other => return Err(format!("&& needs true or false, not {}", other)),
+        },
+        Expr::Binary("||", left, right) => match eval(left, vars)? {
+            Bool(true) => Bool(true),
+            Bool(false) => eval(right, vars)?,
+            other => return Err(format!("|| needs true or false, not {}", other)),
+        },
+        Expr::Binary(op, left, right) => match (*op, eval(left, vars)?, eval(right, vars)?) {
+            ("==", a, b) => Bool(a == b),
+            ("!=", a, b) => Bool(a != b),
+            ("+", Num(a), Num(b)) => Num(a + b),
+            ("+", Str(a), Str(b)) => Str(a + &b),
+            ("-", Num(a), Num(b)) => Num(a - b),
+            ("*", Num(a), Num(b)) => Num(a * b),
+            ("/", Num(a), Num(b)) => Num(a / b),
+            ("%", Num(a), Num(b)) => Num(a % b),
+            ("<", Num(a), Num(b)) => Bool(a < b),
+            ("<=", Num(a), Num(b)) => Bool(a <= b),
+            (">", Num(a), Num(b)) => Bool(a > b),
+            (">=", Num(a), Num(b)) => Bool(a >= b),
+            ("contains", Str(a), Str(b)) => Bool(a.contains(&b)),
+            ("starts_with", Str(a), Str(b)) => Bool(a.starts_with(&b)),
+            ("ends_with", Str(a), Str(b)) => Bool(a.ends_with(&b)),
+            (op, a, b) => return Err(format!("{} {} {} doesn't work", a, op, b)),
+        },
+    })
+}
+
+/// Runs `source` with `vars`, parsing is cheap enough to do it every time.
+pub fn run(source: &str, vars: &Vars) -> Result<Value, String> {
+    eval(&parse(source)?, vars)
+}
+
+/// Whether `source` reads the variable `name` anywhere, false when it doesn't parse.
+pub fn reads(source: &str, name: &str) -> bool {
+    fn walk(expr: &Expr, name: &str) -> bool {
+        match expr {
+            Expr::Lit(_) => false,
+            Expr::Var(var) => var == name,
+            Expr::Not(inner) | Expr::Neg(inner) | Expr::Lower(inner) => walk(inner, name),
+            Expr::Binary(_, left, right) => walk(left, name) || walk(right, name),
+            Expr::If(cond, then, otherwise) => {
+                walk(cond, name) || walk(then, name) || walk(otherwise, name)
+            }
+        }
+    }
+
+    parse(source).is_ok_and(|expr| walk(&expr, name))
+}
+
+/// Parse errors of `source`, for the startup.
+pub fn check(source: &str) -> Result<(), String> {
+    parse(source).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> Vars {
+        let mut vars = Vars::new();
+        vars.insert("pos", 10u64.into());
+        vars.insert("neg", 3u64.into());
+        vars.insert("kind", "sticker".into());
+        vars.insert("name", "PepeLaugh".into());
+        vars.insert("animated", false.into());
+        vars
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(run("pos - 2 * neg", &vars()), Ok(Value::Num(4.0)));
+        assert_eq!(run("(pos - neg) * 2", &vars()), Ok(Value::Num(14.0)));
+        assert_eq!(run("-neg + pos % 4", &vars()), Ok(Value::Num(-1.0)));
+        assert_eq!(run("1.5 / 3", &vars()), Ok(Value::Num(0.5)));
+    }
+
+    #[test]
+    fn conditions() {
+        assert_eq!(
+            run(r#"kind == "sticker" ? pos - 2 * neg : pos"#, &vars()),
+            Ok(Value::Num(4.0))
+        );
+        assert_eq!(
+            run("pos > neg && !animated || neg >= 100", &vars()),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(run("pos != 10", &vars()), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            run(r#"name contains "Laugh""#, &vars()),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            run(r#"name contains "laugh""#, &vars()),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            run(r#"lower(name) contains "laugh""#, &vars()),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            run(r#"lower(kind + "S") == "stickers""#, &vars()),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            run(r#"name starts_with "pepe""#, &vars()),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            run(r#"name ends_with "Laugh""#, &vars()),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            run(r#"kind + "s""#, &vars()),
+            Ok(Value::Str(String::from("stickers")))
+        );
+    }
+
+    #[test]
+    fn short_circuit() {
+        // the unknown variable on the right is never read
+        assert_eq!(run("animated && missing", &vars()), Ok(Value::Bool(false)));
+        assert_eq!(run("!animated || missing", &vars()), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(run("missing + 1", &vars()).is_err());
+        assert!(run("pos + kind", &vars()).is_err());
+        assert!(run("pos ? 1 : 2", &vars()).is_err());
+        assert!(check("pos +").is_err());
+        assert!(check("(pos").is_err());
+        assert!(check(r#""open"#).is_err());
+        assert!(check("pos neg").is_err());
+        assert!(check("pos > 1 ? neg : 0").is_ok());
+        assert!(run("lower(pos)", &vars()).is_err());
+        assert!(check("lower(name").is_err());
+    }
+
+    #[test]
+    fn read_variables() {
+        assert!(reads("kind == \"emoji\" && !(width < 64)", "width"));
+        assert!(reads("pos > 1 ? neg : height", "height"));
+        assert!(!reads("name contains \"width\"", "width"));
+        assert!(reads("lower(name) == \"x\"", "name"));
+        assert!(!reads("width <", "width"));
+    }
+}
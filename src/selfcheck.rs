@@ -0,0 +1,96 @@
+//! Checks the permissions of the bot at startup, a missing one would otherwise only show up as
+//! an opaque error in the middle of a submission.
+use crate::rest;
+use serenity::{
+    http::Http,
+    model::{
+        channel::PermissionOverwriteType,
+        id::{ChannelId, GuildId, RoleId, UserId},
+        Permissions,
+    },
+};
+use std::error::Error;
+
+/// Permissions in the voting channel, overwrites of the channel apply.
+const CHANNEL: [(Permissions, &str); 5] = [
+    (Permissions::SEND_MESSAGES, "Send Messages"),
+    (Permissions::ADD_REACTIONS, "Add Reactions"),
+    (Permissions::MANAGE_MESSAGES, "Manage Messages"),
+    (Permissions::ATTACH_FILES, "Attach Files"),
+    (Permissions::READ_MESSAGE_HISTORY, "Read Message History"),
+];
+
+/// Names of the permissions `user` lacks for running the voting in `channel`.
+pub fn missing(
+    http: &Http,
+    guild: GuildId,
+    channel: ChannelId,
+    user: UserId,
+) -> Result<Vec<&'static str>, Box<dyn Error>> {
+    let partial = rest::call("Fetching guild", || guild.to_partial_guild(http))?;
+    let member = rest::call("Fetching member", || guild.member(http, user))?;
+    let channel = rest::call("Fetching channel", || channel.to_channel(http))?
+        .guild()
+        .ok_or("voting channel is not in a guild")?;
+    let channel = channel.read();
+
+    if partial.owner_id == user {
+        return Ok(Vec::new());
+    }
+
+    // the @everyone role has the id of the guild
+    let everyone = RoleId(guild.0);
+    let role = |id: &RoleId| {
+        partial
+            .roles
+            .get(id)
+            .map_or(Permissions::empty(), |r| r.permissions)
+    };
+    let base = member
+        .roles
+        .iter()
+        .fold(role(&everyone), |acc, r| acc | role(r));
+
+    if base.contains(Permissions::ADMINISTRATOR) {
+        return Ok(Vec::new());
+    }
+
+    let mut missing = Vec::new();
+    if !base.contains(Permissions::MANAGE_EMOJIS) {
+        missing.push("Manage Emojis");
+    }
+
+    // overwrites apply @everyone first, then all roles together and the member last
+    let mut perms = base;
+    for overwrite in channel.permission_overwrites.iter() {
+        if overwrite.kind == PermissionOverwriteType::Role(everyone) {
+            perms = (perms & !overwrite.deny) | overwrite.allow;
+        }
+    }
+    let (deny, allow) = channel
+        .permission_overwrites
+        .iter()
+        .filter(|o| match o.kind {
+            PermissionOverwriteType::Role(r) => r != everyone && member.roles.contains(&r),
+            _ => false,
+        })
+        .fold(
+            (Permissions::empty(), Permissions::empty()),
+            |(deny, allow), o| (deny | o.deny, allow | o.allow),
+        );
+    perms = (perms & !deny) | allow;
+    for overwrite in channel.permission_overwrites.iter() {
+        if overwrite.kind == PermissionOverwriteType::Member(user) {
+            perms = (perms & !overwrite.deny) | overwrite.allow;
+        }
+    }
+
+    missing.extend(
+        CHANNEL
+            .iter()
+            .filter(|(p, _)| !perms.contains(*p))
+            .map(|(_, name)| *name),
+    );
+
+    Ok(missing)
+}
@@ -0,0 +1,190 @@
+//! Optional HTTP server on `http_address`, a plain `TcpListener` since it only answers a few GET
+//! requests. `/feed.xml` is an RSS feed of the accepted suggestions, `/metrics` the command
+//! and Discord call numbers for Prometheus.
+use crate::{rest, usage, Finished, HISTORY, MESSAGES};
+use chrono::Utc;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+/// Accepted suggestions in the feed, newest first.
+const FEED_SIZE: usize = 50;
+/// How long a client may take to send its request or read the answer, requests are answered
+/// one at a time.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest request line read, the paths are short.
+const MAX_REQUEST: u64 = 8192;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn item(finished: &Finished) -> String {
+    format!(
+        "<item><title>{}</title><description>{}</description><pubDate>{}</pubDate>\
+         <guid isPermaLink=\"false\">{}-{}</guid></item>",
+        escape(&finished.name),
+        escape(&format!(
+            "{} from {}, 👍 {} 👎 {}",
+            finished.name, finished.author, finished.votes.0, finished.votes.1
+        )),
+        finished.at.to_rfc2822(),
+        finished.id,
+        finished.at.timestamp()
+    )
+}
+
+fn feed() -> String {
+    let history = HISTORY.read().unwrap();
+    let items = history
+        .iter()
+        .rev()
+        .filter(|f| f.accepted)
+        .take(FEED_SIZE)
+        .map(item)
+        .collect::<String>();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\"><channel>\
+         <title>Emote voting results</title><link>https://discord.com</link>\
+         <description>Suggestions that won the voting and were added</description>\
+         <lastBuildDate>{}</lastBuildDate>{}</channel></rss>\n",
+        Utc::now().to_rfc2822(),
+        items
+    )
+}
+
+/// Prometheus text format, the counters start at 0 with every start of the bot.
+fn metrics() -> String {
+    let mut lines = vec![
+        String::from("# TYPE touhyou_pending_suggestions gauge"),
+        format!(
+            "touhyou_pending_suggestions {}",
+            MESSAGES.read().unwrap().len()
+        ),
+    ];
+
+    let commands = usage::all();
+    lines.push(String::from("# TYPE touhyou_command_calls_total counter"));
+    lines.extend(commands.iter().map(|(command, u)| {
+        format!(
+            "touhyou_command_calls_total{{command=\"{}\"}} {}",
+            command, u.calls
+        )
+    }));
+    lines.push(String::from(
+        "# TYPE touhyou_command_failures_total counter",
+    ));
+    lines.extend(commands.iter().map(|(command, u)| {
+        format!(
+            "touhyou_command_failures_total{{command=\"{}\"}} {}",
+            command, u.failures
+        )
+    }));
+    lines.push(String::from("# TYPE touhyou_command_seconds_total counter"));
+    lines.extend(commands.iter().map(|(command, u)| {
+        format!(
+            "touhyou_command_seconds_total{{command=\"{}\"}} {:.3}",
+            command,
+            u.total.as_secs_f64()
+        )
+    }));
+
+    let calls = rest::metrics();
+    lines.push(String::from("# TYPE touhyou_discord_calls_total counter"));
+    lines.extend(calls.iter().map(|(call, m)| {
+        format!(
+            "touhyou_discord_calls_total{{call=\"{}\"}} {}",
+            call, m.calls
+        )
+    }));
+    lines.push(String::from("# TYPE touhyou_discord_errors_total counter"));
+    lines.extend(calls.iter().map(|(call, m)| {
+        format!(
+            "touhyou_discord_errors_total{{call=\"{}\"}} {}",
+            call, m.errors
+        )
+    }));
+    lines.push(String::from("# TYPE touhyou_discord_retries_total counter"));
+    lines.extend(calls.iter().map(|(call, m)| {
+        format!(
+            "touhyou_discord_retries_total{{call=\"{}\"}} {}",
+            call, m.retries
+        )
+    }));
+    lines.push(String::from("# TYPE touhyou_discord_seconds_total counter"));
+    lines.extend(calls.iter().map(|(call, m)| {
+        format!(
+            "touhyou_discord_seconds_total{{call=\"{}\"}} {:.3}",
+            call,
+            m.millis as f64 / 1000.0
+        )
+    }));
+
+    lines.join("\n") + "\n"
+}
+
+/// Content type and body for `path`, `None` is a 404.
+fn route(path: &str) -> Option<(&'static str, String)> {
+    match path {
+        "/feed.xml" | "/rss" => Some(("application/rss+xml; charset=utf-8", feed())),
+        "/metrics" => Some(("text/plain; version=0.0.4", metrics())),
+        _ => None,
+    }
+}
+
+fn answer(stream: TcpStream) -> std::io::Result<()> {
+    // a client that connects and sends nothing would hold up everyone after it
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST));
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+
+    // the path without a query, anything but GET is refused
+    let mut parts = request.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => route(target.split('?').next().unwrap_or(target)),
+        _ => None,
+    };
+
+    let (status, content_type, body) = match response {
+        Some((content_type, body)) => ("200 OK", content_type, body),
+        None => ("404 Not Found", "text/plain", String::from("Not found\n")),
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Serves requests on `address` in the background, one at a time.
+pub fn start(address: String) {
+    let listener = match TcpListener::bind(&address) {
+        Ok(x) => x,
+        Err(why) => {
+            log!("Could not listen on {}: {}", address, why);
+            return;
+        }
+    };
+    log!("Serving HTTP on {}", address);
+
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(Result::ok) {
+            if let Err(why) = answer(stream) {
+                log!("Could not answer HTTP request: {}", why);
+            }
+        }
+    });
+}
@@ -0,0 +1,179 @@
+//! Slash commands, serenity 0.8 predates interactions so they arrive as unknown gateway events
+//! and get answered through the REST api directly.
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use serenity::{
+    http::Http,
+    model::id::{GuildId, RoleId, UserId},
+};
+use std::error::Error;
+
+const API: &str = "https://discord.com/api/v9";
+
+pub const COMMAND: u8 = 2;
+pub const AUTOCOMPLETE: u8 = 4;
+
+/// Discord shows at most 25 autocomplete choices.
+pub const MAX_CHOICES: usize = 25;
+
+#[derive(Deserialize, Debug)]
+pub struct Interaction {
+    pub id: String,
+    pub application_id: String,
+    pub token: String,
+    #[serde(rename = "type")]
+    pub kind: u8,
+    pub guild_id: Option<GuildId>,
+    pub member: Option<Member>,
+    pub data: Option<Data>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Member {
+    pub user: User,
+    pub roles: Vec<RoleId>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct User {
+    pub id: UserId,
+    pub username: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Data {
+    pub name: String,
+    #[serde(default)]
+    pub options: Vec<DataOption>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DataOption {
+    pub name: String,
+    pub value: Value,
+    #[serde(default)]
+    pub focused: bool,
+}
+
+impl Interaction {
+    pub fn name(&self) -> Option<&str> {
+        self.data.as_ref().map(|d| d.name.as_str())
+    }
+
+    /// Value of the option the user is typing in right now.
+    pub fn focused(&self) -> Option<&str> {
+        self.data
+            .as_ref()?
+            .options
+            .iter()
+            .find(|o| o.focused)
+            .and_then(|o| o.value.as_str())
+    }
+
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.data
+            .as_ref()?
+            .options
+            .iter()
+            .find(|o| o.name == name)
+            .and_then(|o| o.value.as_str())
+    }
+}
+
+/// Replaces the guild commands with `remove` and `accept`, both take the vote message ID with
+/// autocomplete.
+pub fn register(http: &Http, app: UserId, guild: GuildId) -> Result<(), Box<dyn Error>> {
+    let id_option = json!({
+        "type": 3,
+        "name": "id",
+        "description": "Suggestion, type to search by name",
+        "required": true,
+        "autocomplete": true,
+    });
+    // manage expressions, the role check happens again when the command comes in
+    let commands = json!([
+        {
+            "name": "remove",
+            "description": "Removes the suggestion from the voting",
+            "options": [id_option],
+            "default_member_permissions": "1073741824",
+        },
+        {
+            "name": "accept",
+            "description": "Uploads the suggestion and removes it from the voting",
+            "options": [id_option],
+            "default_member_permissions": "1073741824",
+        },
+    ]);
+
+    Client::new()
+        .put(&format!(
+            "{}/applications/{}/guilds/{}/commands",
+            API, app.0, guild.0
+        ))
+        .header("Authorization", &http.token)
+        .json(&commands)
+        .send()?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// Answers an autocomplete request, `choices` are pairs of shown name and value.
+pub fn autocomplete(
+    interaction: &Interaction,
+    choices: Vec<(String, String)>,
+) -> Result<(), Box<dyn Error>> {
+    let choices = choices
+        .into_iter()
+        .take(MAX_CHOICES)
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect::<Vec<_>>();
+
+    callback(
+        interaction,
+        json!({ "type": 8, "data": { "choices": choices } }),
+    )
+}
+
+/// Acknowledges a command with a loading state only the caller sees, uploads easily take longer
+/// than the 3 seconds Discord waits for an answer.
+pub fn defer(interaction: &Interaction) -> Result<(), Box<dyn Error>> {
+    callback(interaction, json!({ "type": 5, "data": { "flags": 64 } }))
+}
+
+/// Replaces the loading state of a deferred command.
+pub fn edit_response(interaction: &Interaction, content: &str) -> Result<(), Box<dyn Error>> {
+    Client::new()
+        .patch(&format!(
+            "{}/webhooks/{}/{}/messages/@original",
+            API, interaction.application_id, interaction.token
+        ))
+        .json(&json!({ "content": content }))
+        .send()?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// Answers a command right away with a message only the caller sees.
+pub fn reply(interaction: &Interaction, content: &str) -> Result<(), Box<dyn Error>> {
+    callback(
+        interaction,
+        json!({ "type": 4, "data": { "content": content, "flags": 64 } }),
+    )
+}
+
+fn callback(interaction: &Interaction, body: Value) -> Result<(), Box<dyn Error>> {
+    Client::new()
+        .post(&format!(
+            "{}/interactions/{}/{}/callback",
+            API, interaction.id, interaction.token
+        ))
+        .json(&body)
+        .send()?
+        .error_for_status()?;
+
+    Ok(())
+}
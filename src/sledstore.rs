@@ -0,0 +1,146 @@
+//! Embedded store in `store.sled/` (or the directory in `EMOTE_SLED`), needs no service next to
+//! the bot. Every namespace is a tree: maps like users and suggestions keyed by their id, lists
+//! like the history by position and single values under `value`. A save only writes the entries
+//! that changed, all of them in one transaction.
+use crate::{
+    filestore::{FileStore, NAMESPACES},
+    store::{State, Store},
+};
+use serde_json::{Map, Value};
+use sled::{transaction::ConflictableTransactionError, Db, Transactional, Tree};
+use std::{collections::HashSet, env, error::Error, path::PathBuf};
+
+/// Namespaces that are JSON arrays, their order is kept.
+pub const LISTS: [&str; 4] = ["history", "crowned", "rotation", "modlog"];
+/// Namespaces that are a single value.
+pub const VALUES: [&str; 3] = ["pair", "round", "version"];
+
+pub struct SledStore {
+    db: Db,
+    /// one per namespace, in the order of `NAMESPACES`
+    trees: Vec<Tree>,
+}
+
+impl SledStore {
+    pub fn open(path: PathBuf) -> Result<SledStore, sled::Error> {
+        let db = sled::open(path)?;
+        let trees = NAMESPACES
+            .iter()
+            .map(|namespace| db.open_tree(namespace))
+            .collect::<Result<_, _>>()?;
+
+        Ok(SledStore { db, trees })
+    }
+}
+
+/// A key in a tree and its JSON.
+type Entry = (Vec<u8>, Vec<u8>);
+
+/// The entries of `namespace`.
+fn entries(namespace: &str, value: &Value) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let entries = match value {
+        Value::Object(map) if !VALUES.contains(&namespace) => map
+            .iter()
+            .map(|(key, value)| Ok((key.as_bytes().to_vec(), serde_json::to_vec(value)?)))
+            .collect::<Result<_, serde_json::Error>>()?,
+        Value::Array(list) if LISTS.contains(&namespace) => list
+            .iter()
+            .enumerate()
+            .map(|(n, value)| {
+                Ok((
+                    (n as u64).to_be_bytes().to_vec(),
+                    serde_json::to_vec(value)?,
+                ))
+            })
+            .collect::<Result<_, serde_json::Error>>()?,
+        value => vec![(b"value".to_vec(), serde_json::to_vec(value)?)],
+    };
+
+    Ok(entries)
+}
+
+impl Store for SledStore {
+    /// Takes over the JSON files of the `files` store (in `data/` or `EMOTE_DATA`) while the
+    /// database is still empty.
+    fn load(&self) -> Result<Map<String, Value>, Box<dyn Error>> {
+        if self.trees.iter().all(Tree::is_empty) {
+            let dir = env::var("EMOTE_DATA").unwrap_or_else(|_| String::from("data"));
+            let files = FileStore::new(PathBuf::from(&dir)).load()?;
+            if !files.is_empty() {
+                log!("Taking over the stored state in {}", dir);
+            }
+            return Ok(files);
+        }
+
+        let mut state = Map::new();
+        for (namespace, tree) in NAMESPACES.iter().zip(self.trees.iter()) {
+            let mut values = Vec::new();
+            for entry in tree.iter() {
+                let (key, value) = entry?;
+                values.push((key, serde_json::from_slice::<Value>(&value)?));
+            }
+
+            let value = match () {
+                _ if values.is_empty() => continue,
+                _ if LISTS.contains(namespace) => {
+                    Value::Array(values.into_iter().map(|(_, value)| value).collect())
+                }
+                _ if VALUES.contains(namespace) => values.remove(0).1,
+                _ => Value::Object(
+                    values
+                        .into_iter()
+                        .map(|(key, value)| Ok((String::from_utf8(key.to_vec())?, value)))
+                        .collect::<Result<_, std::string::FromUtf8Error>>()?,
+                ),
+            };
+            state.insert(namespace.to_string(), value);
+        }
+
+        Ok(state)
+    }
+
+    fn save(&self, state: &State) -> Result<(), Box<dyn Error>> {
+        let state = serde_json::to_value(state)?;
+
+        // (tree, key, new value or `None` to remove it)
+        let mut changes = Vec::new();
+        for (n, (namespace, tree)) in NAMESPACES.iter().zip(self.trees.iter()).enumerate() {
+            let entries = entries(namespace, state.get(*namespace).unwrap_or(&Value::Null))?;
+            let keys = entries
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect::<HashSet<_>>();
+
+            for key in tree.iter().keys() {
+                let key = key?;
+                if !keys.contains(&*key) {
+                    changes.push((n, key.to_vec(), None));
+                }
+            }
+            for (key, value) in entries {
+                if tree.get(&key)?.as_deref() != Some(&value[..]) {
+                    changes.push((n, key, Some(value)));
+                }
+            }
+        }
+
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        self.trees[..]
+            .transaction(|trees| {
+                for (n, key, value) in changes.iter() {
+                    match value {
+                        Some(value) => trees[*n].insert(&key[..], &value[..])?,
+                        None => trees[*n].remove(&key[..])?,
+                    };
+                }
+                Ok::<_, ConflictableTransactionError<()>>(())
+            })
+            .map_err(|why| format!("sled transaction: {:?}", why))?;
+        self.db.flush()?;
+
+        Ok(())
+    }
+}
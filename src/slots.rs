@@ -0,0 +1,61 @@
+//! Free emoji, sticker and soundboard slots, all of them depend on the boost tier of the guild.
+use crate::{rest, sound, sticker};
+use serenity::{
+    http::Http,
+    model::{guild::PremiumTier, id::GuildId},
+};
+use std::error::Error;
+
+/// Slots of each of the static and animated emoji pools.
+pub fn max_emojis(tier: PremiumTier) -> usize {
+    match tier {
+        PremiumTier::Tier1 => 100,
+        PremiumTier::Tier2 => 150,
+        PremiumTier::Tier3 => 250,
+        _ => 50,
+    }
+}
+
+fn max_stickers(tier: PremiumTier) -> usize {
+    match tier {
+        PremiumTier::Tier1 => 15,
+        PremiumTier::Tier2 => 30,
+        PremiumTier::Tier3 => 60,
+        _ => 5,
+    }
+}
+
+fn max_sounds(tier: PremiumTier) -> usize {
+    match tier {
+        PremiumTier::Tier1 => 24,
+        PremiumTier::Tier2 => 36,
+        PremiumTier::Tier3 => 48,
+        _ => 8,
+    }
+}
+
+/// Free slots for static or animated emojis, both have a pool of the same size.
+pub fn free_emojis(http: &Http, guild: GuildId, animated: bool) -> Result<usize, Box<dyn Error>> {
+    let guild = rest::call("Fetching guild", || guild.to_partial_guild(http))?;
+    let used = guild
+        .emojis
+        .values()
+        .filter(|e| e.animated == animated)
+        .count();
+
+    Ok(max_emojis(guild.premium_tier).saturating_sub(used))
+}
+
+pub fn free_stickers(http: &Http, guild: GuildId) -> Result<usize, Box<dyn Error>> {
+    let tier = rest::call("Fetching guild", || guild.to_partial_guild(http))?.premium_tier;
+    let used = sticker::list(http, guild)?.len();
+
+    Ok(max_stickers(tier).saturating_sub(used))
+}
+
+pub fn free_sounds(http: &Http, guild: GuildId) -> Result<usize, Box<dyn Error>> {
+    let tier = rest::call("Fetching guild", || guild.to_partial_guild(http))?.premium_tier;
+    let used = sound::list(http, guild)?.len();
+
+    Ok(max_sounds(tier).saturating_sub(used))
+}
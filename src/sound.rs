@@ -0,0 +1,278 @@
+//! Soundboard sounds, like stickers they aren't covered by serenity 0.8.
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::json;
+use serenity::{http::Http, model::id::GuildId};
+use std::error::Error;
+
+/// Discord rejects soundboard files above 512KB.
+pub const MAX_BYTES: u64 = 512_000;
+/// Longest sound the soundboard accepts, in seconds.
+pub const MAX_DURATION: f64 = 5.2;
+
+const API: &str = "https://discord.com/api/v9";
+
+#[derive(Deserialize, Debug)]
+pub struct Sound {
+    pub sound_id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct SoundList {
+    items: Vec<Sound>,
+}
+
+pub fn list(http: &Http, guild: GuildId) -> Result<Vec<Sound>, Box<dyn Error>> {
+    let sounds: SoundList = Client::new()
+        .get(&format!("{}/guilds/{}/soundboard-sounds", API, guild.0))
+        .header("Authorization", &http.token)
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(sounds.items)
+}
+
+pub fn create(
+    http: &Http,
+    guild: GuildId,
+    name: &str,
+    filetype: &str,
+    data: &[u8],
+) -> Result<Sound, Box<dyn Error>> {
+    let mime = match filetype {
+        "ogg" => "audio/ogg",
+        _ => "audio/mpeg",
+    };
+
+    let sound = Client::new()
+        .post(&format!("{}/guilds/{}/soundboard-sounds", API, guild.0))
+        .header("Authorization", &http.token)
+        .json(&json!({
+            "name": name,
+            "sound": format!("data:{};base64,{}", mime, base64::encode(data)),
+        }))
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(sound)
+}
+
+/// Length of an MP3 or OGG (Vorbis/Opus) file in seconds, `None` if it can't be parsed.
+pub fn duration(filetype: &str, data: &[u8]) -> Option<f64> {
+    match filetype {
+        "mp3" => mp3_duration(data),
+        "ogg" => ogg_duration(data),
+        _ => None,
+    }
+}
+
+const MP3_BITRATES: [[u32; 15]; 5] = [
+    // MPEG1 layer I, II, III
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+    // MPEG2/2.5 layer I, II & III
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+const MP3_SAMPLERATES: [[u32; 3]; 3] = [
+    [44100, 48000, 32000],
+    [22050, 24000, 16000],
+    [11025, 12000, 8000],
+];
+
+fn mp3_duration(data: &[u8]) -> Option<f64> {
+    let mut pos = 0;
+
+    // skip the ID3v2 tag, its size is stored as a synchsafe integer
+    if data.len() > 10 && &data[..3] == b"ID3" {
+        let size = data[6..10]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 7) | (*b as usize & 0x7f));
+        let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+        pos = 10 + size + footer;
+    }
+
+    let mut seconds = 0.0;
+    let mut frames = 0;
+    while pos + 4 <= data.len() {
+        let h = &data[pos..pos + 4];
+        if h[0] != 0xff || h[1] & 0xe0 != 0xe0 {
+            pos += 1;
+            continue;
+        }
+
+        let version = (h[1] >> 3) & 3; // 0 = 2.5, 2 = 2, 3 = 1
+        let layer = (h[1] >> 1) & 3; // 1 = III, 2 = II, 3 = I
+        let bitrate = (h[2] >> 4) as usize;
+        let samplerate = ((h[2] >> 2) & 3) as usize;
+        let padding = ((h[2] >> 1) & 1) as u32;
+
+        if version == 1 || layer == 0 || bitrate == 0 || bitrate == 15 || samplerate == 3 {
+            pos += 1;
+            continue;
+        }
+
+        let mpeg1 = version == 3;
+        let table = match (mpeg1, layer) {
+            (true, 3) => 0,
+            (true, 2) => 1,
+            (true, _) => 2,
+            (false, 3) => 3,
+            (false, _) => 4,
+        };
+        let rate = match version {
+            3 => MP3_SAMPLERATES[0][samplerate],
+            2 => MP3_SAMPLERATES[1][samplerate],
+            _ => MP3_SAMPLERATES[2][samplerate],
+        };
+        let bitrate = MP3_BITRATES[table][bitrate] * 1000;
+
+        let (samples, length) = match layer {
+            3 => (384, (12 * bitrate / rate + padding) * 4),
+            2 => (1152, 144 * bitrate / rate + padding),
+            _ if mpeg1 => (1152, 144 * bitrate / rate + padding),
+            _ => (576, 72 * bitrate / rate + padding),
+        };
+
+        seconds += samples as f64 / rate as f64;
+        frames += 1;
+        pos += length.max(1) as usize;
+    }
+
+    if frames == 0 {
+        None
+    } else {
+        Some(seconds)
+    }
+}
+
+fn ogg_duration(data: &[u8]) -> Option<f64> {
+    let mut pos = 0;
+    let mut rate = None;
+    let mut pre_skip = 0;
+    let mut granule = None;
+
+    while pos + 27 <= data.len() && &data[pos..pos + 4] == b"OggS" {
+        let segments = data[pos + 26] as usize;
+        let table = data.get(pos + 27..pos + 27 + segments)?;
+        let body = pos + 27 + segments;
+        let size: usize = table.iter().map(|s| *s as usize).sum();
+        let body = data.get(body..body + size)?;
+
+        // the first page carries the codec header
+        if rate.is_none() {
+            if body.starts_with(b"OpusHead") && body.len() >= 12 {
+                rate = Some(48000);
+                pre_skip = u16::from_le_bytes([body[10], body[11]]) as u64;
+            } else if body.starts_with(b"\x01vorbis") && body.len() >= 16 {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(&body[12..16]);
+                rate = Some(u32::from_le_bytes(bytes));
+            } else {
+                return None;
+            }
+        }
+
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&data[pos + 6..pos + 14]);
+        let pos_granule = i64::from_le_bytes(bytes);
+        if pos_granule >= 0 {
+            granule = Some(pos_granule as u64);
+        }
+
+        pos += 27 + segments + size;
+    }
+
+    match (rate, granule) {
+        (Some(rate), Some(granule)) if rate > 0 => {
+            Some(granule.saturating_sub(pre_skip) as f64 / rate as f64)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` MPEG1 layer III frames of 128 kbit/s at 44.1 kHz, 417 bytes each.
+    fn mp3(count: usize) -> Vec<u8> {
+        let mut frame = vec![0; 417];
+        frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+        frame.repeat(count)
+    }
+
+    /// An Ogg page with `body`, `granule` and one segment per 255 bytes.
+    fn page(granule: i64, body: &[u8]) -> Vec<u8> {
+        let mut segments = vec![255; body.len() / 255];
+        segments.push((body.len() % 255) as u8);
+
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&[0; 12]);
+        page.push(segments.len() as u8);
+        page.extend_from_slice(&segments);
+        page.extend_from_slice(body);
+        page
+    }
+
+    #[test]
+    fn mp3_frames() {
+        let seconds = duration("mp3", &mp3(100)).unwrap();
+        assert!((seconds - 100.0 * 1152.0 / 44100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mp3_after_id3_tag() {
+        // a tag of 200 bytes, its size is synchsafe
+        let mut data = b"ID3\x04\0\0\0\0\x01\x48".to_vec();
+        data.extend_from_slice(&[0xff; 200]);
+        data.extend_from_slice(&mp3(10));
+
+        let seconds = duration("mp3", &data).unwrap();
+        assert!((seconds - 10.0 * 1152.0 / 44100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn opus_minus_pre_skip() {
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        head.extend_from_slice(&312u16.to_le_bytes());
+        head.extend_from_slice(&48000u32.to_le_bytes());
+
+        let mut data = page(0, &head);
+        data.extend(page(-1, &[0; 300]));
+        data.extend(page(96312, &[0; 10]));
+        assert_eq!(duration("ogg", &data), Some(2.0));
+    }
+
+    #[test]
+    fn vorbis_rate() {
+        let mut head = b"\x01vorbis\0\0\0\0\x01".to_vec();
+        head.extend_from_slice(&22050u32.to_le_bytes());
+        head.extend_from_slice(&[0; 14]);
+
+        let mut data = page(0, &head);
+        data.extend(page(44100, &[0; 10]));
+        assert_eq!(duration("ogg", &data), Some(2.0));
+    }
+
+    #[test]
+    fn unknown() {
+        assert_eq!(duration("wav", &mp3(10)), None);
+        assert_eq!(duration("mp3", &[0; 100]), None);
+        assert_eq!(duration("ogg", &page(10, b"something else")), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! Store in the SQLite database `store.db` (or the file in `EMOTE_SQLITE`), a single file that
+//! can be queried with `json_extract`. Every namespace gets its rows in `entries`: maps like users
+//! and suggestions one per id, lists like the history one per position and single values under
+//! `value`. A save only writes the rows that changed, all of them in one transaction.
+use crate::{
+    filestore::NAMESPACES,
+    sledstore::{LISTS, VALUES},
+    store::{State, Store},
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{Map, Value};
+use std::{collections::HashSet, error::Error, path::PathBuf, sync::Mutex};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS entries (
+    namespace TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (namespace, key)
+);
+";
+
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(path: PathBuf) -> Result<SqliteStore, rusqlite::Error> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(SqliteStore {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+/// The rows of `namespace` as keys and JSON, list positions are padded so they sort.
+fn entries(namespace: &str, value: &Value) -> Result<Vec<(String, String)>, serde_json::Error> {
+    match value {
+        Value::Object(map) if !VALUES.contains(&namespace) => map
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), serde_json::to_string(value)?)))
+            .collect(),
+        Value::Array(list) if LISTS.contains(&namespace) => list
+            .iter()
+            .enumerate()
+            .map(|(n, value)| Ok((format!("{:020}", n), serde_json::to_string(value)?)))
+            .collect(),
+        value => Ok(vec![(String::from("value"), serde_json::to_string(value)?)]),
+    }
+}
+
+impl Store for SqliteStore {
+    fn load(&self) -> Result<Map<String, Value>, Box<dyn Error>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT key, value FROM entries WHERE namespace = ?1 ORDER BY key")?;
+
+        let mut state = Map::new();
+        for namespace in NAMESPACES.iter() {
+            let rows = statement
+                .query_map(params![namespace], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut values = Vec::new();
+            for (key, value) in rows {
+                values.push((key, serde_json::from_str::<Value>(&value)?));
+            }
+
+            let value = match () {
+                _ if values.is_empty() => continue,
+                _ if LISTS.contains(namespace) => {
+                    Value::Array(values.into_iter().map(|(_, value)| value).collect())
+                }
+                _ if VALUES.contains(namespace) => values.remove(0).1,
+                _ => Value::Object(values.into_iter().collect()),
+            };
+            state.insert(namespace.to_string(), value);
+        }
+
+        Ok(state)
+    }
+
+    fn save(&self, state: &State) -> Result<(), Box<dyn Error>> {
+        let state = serde_json::to_value(state)?;
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        {
+            let mut keys = transaction.prepare("SELECT key FROM entries WHERE namespace = ?1")?;
+            let mut stored = transaction
+                .prepare("SELECT value FROM entries WHERE namespace = ?1 AND key = ?2")?;
+            let mut upsert = transaction.prepare(
+                "INSERT INTO entries (namespace, key, value) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (namespace, key) DO UPDATE SET value = excluded.value",
+            )?;
+            let mut delete =
+                transaction.prepare("DELETE FROM entries WHERE namespace = ?1 AND key = ?2")?;
+
+            for namespace in NAMESPACES.iter() {
+                let entries = entries(namespace, state.get(*namespace).unwrap_or(&Value::Null))?;
+                let current = entries
+                    .iter()
+                    .map(|(key, _)| key.as_str())
+                    .collect::<HashSet<_>>();
+
+                let gone = keys
+                    .query_map(params![namespace], |row| row.get::<_, String>(0))?
+                    .filter(|key| {
+                        key.as_ref()
+                            .map_or(true, |key| !current.contains(key.as_str()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                for key in gone {
+                    delete.execute(params![namespace, key])?;
+                }
+
+                for (key, value) in entries.iter() {
+                    let old = stored
+                        .query_row(params![namespace, key], |row| row.get::<_, String>(0))
+                        .optional()?;
+                    if old.as_ref() != Some(value) {
+                        upsert.execute(params![namespace, key, value])?;
+                    }
+                }
+            }
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+//! Guild sticker endpoints, serenity 0.8 doesn't know about stickers so they are called directly.
+use reqwest::blocking::{
+    multipart::{Form, Part},
+    Client,
+};
+use serde::Deserialize;
+use serenity::{http::Http, model::id::GuildId};
+use std::error::Error;
+
+/// Stickers have to be exactly 320x320px.
+pub const SIZE: u32 = 320;
+/// Discord rejects sticker files above 512KB.
+pub const MAX_BYTES: usize = 512_000;
+
+const API: &str = "https://discord.com/api/v9";
+
+#[derive(Deserialize, Debug)]
+pub struct Sticker {
+    pub id: String,
+    pub name: String,
+}
+
+pub fn list(http: &Http, guild: GuildId) -> Result<Vec<Sticker>, Box<dyn Error>> {
+    let stickers = Client::new()
+        .get(&format!("{}/guilds/{}/stickers", API, guild.0))
+        .header("Authorization", &http.token)
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(stickers)
+}
+
+pub fn create(
+    http: &Http,
+    guild: GuildId,
+    name: &str,
+    description: &str,
+    png: Vec<u8>,
+) -> Result<Sticker, Box<dyn Error>> {
+    let form = Form::new()
+        .text("name", name.to_string())
+        .text("description", description.to_string())
+        // tags are mandatory, the name is the best guess we have
+        .text("tags", name.to_string())
+        .part(
+            "file",
+            Part::bytes(png)
+                .file_name(format!("{}.png", name))
+                .mime_str("image/png")?,
+        );
+
+    let sticker = Client::new()
+        .post(&format!("{}/guilds/{}/stickers", API, guild.0))
+        .header("Authorization", &http.token)
+        .multipart(form)
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(sticker)
+}
@@ -0,0 +1,271 @@
+//! Pending suggestions, quotas, the history and the guild settings survive restarts in the
+//! backend picked with `store` in the config. The whole state gets written after every change,
+//! nothing else in the bot knows which backend it is.
+use crate::{
+    config::{Backend, GuildConfig, CONFIG},
+    filestore::FileStore,
+    fixed_zone,
+    pgstore::PgStore,
+    redis::RedisStore,
+    sledstore::SledStore,
+    sqlitestore::SqliteStore,
+    wal::{self, Op},
+    EmoteMessage, Finished, ModAction, Pair, Replacement, Rotated, Round, Runoff, Trashed, Trial,
+    User, VariantPoll,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serenity::model::id::{EmojiId, GuildId, MessageId, UserId};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    error::Error,
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+/// Version of the state this build writes, raising it needs an entry in `MIGRATIONS`.
+pub const VERSION: u64 = 3;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), Box<dyn Error>>;
+
+/// `MIGRATIONS[n]` upgrades a state of version `n` to `n + 1`.
+const MIGRATIONS: [Migration; VERSION as usize] = [guilds_into_store, stable_hashes, named_zones];
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct State {
+    /// 0 for a new store and everything written before there were versions
+    pub version: u64,
+    pub users: HashMap<UserId, User>,
+    /// suggestions waiting for the mods by the id of their decision message
+    pub queue: HashMap<MessageId, EmoteMessage>,
+    /// pending suggestions by the id of their vote message
+    pub suggestions: HashMap<MessageId, EmoteMessage>,
+    /// removed suggestions that can still be undone
+    pub trash: HashMap<MessageId, Trashed>,
+    pub variants: HashMap<MessageId, VariantPoll>,
+    pub runoffs: HashMap<MessageId, Runoff>,
+    pub replacements: HashMap<MessageId, Replacement>,
+    pub pair: Option<Pair>,
+    pub round: Round,
+    pub history: Vec<Finished>,
+    pub crowned: HashSet<UserId>,
+    pub guilds: HashMap<GuildId, GuildConfig>,
+    pub rotation: Vec<Rotated>,
+    /// uploaded emotes waiting for or in their confirmation vote
+    pub trials: HashMap<EmojiId, Trial>,
+    /// counted without the log, a crash loses the uses since the last save
+    pub emoji_uses: HashMap<EmojiId, u64>,
+    /// what the mods did, oldest first
+    pub modlog: Vec<ModAction>,
+}
+
+/// A number of the state that a backend may change in place, see `Store::add`.
+#[derive(Debug, Clone, Copy)]
+pub enum Counter {
+    /// the used suggestions of a user, `User::counter`
+    Slots(UserId),
+    /// the tally of an emoji of the server in `emoji_uses`
+    EmojiUses(EmojiId),
+}
+
+pub trait Store: Send + Sync {
+    /// The stored state as JSON, one field per namespace. `load` migrates it before it
+    /// becomes a `State`.
+    fn load(&self) -> Result<Map<String, Value>, Box<dyn Error>>;
+    fn save(&self, state: &State) -> Result<(), Box<dyn Error>>;
+    /// Adds `by` to `counter` right away. Backends without counters keep the number from the
+    /// next `save`.
+    fn add(&self, _counter: Counter, _by: i64) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref STORE: Option<Box<dyn Store>> = open();
+    /// one save at a time, otherwise an older snapshot could overwrite a newer one
+    static ref SAVING: Mutex<()> = Mutex::new(());
+    /// when the last save was tried and whether it worked, for `diag`
+    static ref LAST_SAVE: Mutex<Option<(DateTime<Utc>, bool)>> = Mutex::new(None);
+}
+
+fn open() -> Option<Box<dyn Store>> {
+    let config = CONFIG.read().unwrap();
+
+    match config.store {
+        Backend::Memory => None,
+        Backend::Files => Some(Box::new(FileStore::new(PathBuf::from(
+            env::var("EMOTE_DATA").unwrap_or_else(|_| String::from("data")),
+        )))),
+        Backend::Redis => Some(Box::new(RedisStore::new(
+            &config.redis_url,
+            config.redis_expire_days,
+        ))),
+        Backend::Postgres => Some(Box::new(PgStore::new(&config.postgres_url))),
+        Backend::Sled => {
+            let path = env::var("EMOTE_SLED").unwrap_or_else(|_| String::from("store.sled"));
+            match SledStore::open(PathBuf::from(&path)) {
+                Ok(store) => Some(Box::new(store)),
+                Err(why) => panic!("Could not open {}: {}", path, why),
+            }
+        }
+        Backend::Sqlite => {
+            let path = env::var("EMOTE_SQLITE").unwrap_or_else(|_| String::from("store.db"));
+            match SqliteStore::open(PathBuf::from(&path)) {
+                Ok(store) => Some(Box::new(store)),
+                Err(why) => panic!("Could not open {}: {}", path, why),
+            }
+        }
+    }
+}
+
+/// State of the last run with the changes from the log, empty without a store.
+pub fn load() -> State {
+    let stored = match &*STORE {
+        Some(store) => store.load(),
+        None => Ok(Map::new()),
+    };
+
+    let mut state = stored
+        .and_then(migrate)
+        .unwrap_or_else(|why| panic!("Could not load state: {}", why));
+    if STORE.is_some() {
+        wal::replay(&mut state);
+    }
+
+    state
+}
+
+fn migrate(mut stored: Map<String, Value>) -> Result<State, Box<dyn Error>> {
+    let version = stored.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > VERSION {
+        return Err(format!(
+            "stored state has version {}, this build only knows up to {}",
+            version, VERSION
+        )
+        .into());
+    }
+
+    for (n, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        log!("Migrating state to version {}", n + 1);
+        migration(&mut stored)?;
+    }
+    stored.insert(String::from("version"), Value::from(VERSION));
+
+    Ok(serde_json::from_value(Value::Object(stored))?)
+}
+
+/// Version 1, guild settings moved from `guilds.json` (or the file in `EMOTE_GUILDS`) into the
+/// store.
+fn guilds_into_store(stored: &mut Map<String, Value>) -> Result<(), Box<dyn Error>> {
+    let path = env::var("EMOTE_GUILDS").unwrap_or_else(|_| String::from("guilds.json"));
+    if let Ok(content) = fs::read_to_string(&path) {
+        let guilds = serde_json::from_str(&content).map_err(|why| format!("{}: {}", path, why))?;
+        stored.insert(String::from("guilds"), guilds);
+    }
+
+    Ok(())
+}
+
+/// Version 2, `file_hash` became FNV-1a. The stored hashes can't be computed again without the
+/// submitted files, so they are marked as made by the old hasher instead.
+fn stable_hashes(stored: &mut Map<String, Value>) -> Result<(), Box<dyn Error>> {
+    fn mark(value: Option<&mut Value>) {
+        if let Some(Value::Object(object)) = value {
+            object.insert(String::from("legacy"), Value::Bool(true));
+        }
+    }
+
+    if let Some(Value::Array(history)) = stored.get_mut("history") {
+        history.iter_mut().for_each(|f| mark(Some(f)));
+    }
+    for name in ["suggestions", "queue"].iter() {
+        if let Some(Value::Object(map)) = stored.get_mut(*name) {
+            map.values_mut().for_each(|e| mark(e.get_mut("emote")));
+        }
+    }
+    if let Some(Value::Object(trash)) = stored.get_mut("trash") {
+        for trashed in trash.values_mut() {
+            mark(trashed.get_mut("emsg").and_then(|e| e.get_mut("emote")));
+        }
+    }
+    if let Some(Value::Object(variants)) = stored.get_mut("variants") {
+        variants.values_mut().for_each(|v| mark(Some(v)));
+    }
+
+    Ok(())
+}
+
+/// Version 3, the guilds got a time zone instead of a fixed `utc_offset` in minutes. Offsets
+/// without a zone of their own fall back to UTC.
+fn named_zones(stored: &mut Map<String, Value>) -> Result<(), Box<dyn Error>> {
+    if let Some(Value::Object(guilds)) = stored.get_mut("guilds") {
+        for (id, guild) in guilds.iter_mut() {
+            let minutes = match guild.as_object_mut().and_then(|g| g.remove("utc_offset")) {
+                Some(minutes) => minutes.as_i64().unwrap_or(0) as i32,
+                None => continue,
+            };
+            let zone = fixed_zone(minutes).unwrap_or_else(|| {
+                log!(
+                    "Guild {} had a UTC offset of {} minutes, it's UTC now",
+                    id,
+                    minutes
+                );
+                Tz::UTC
+            });
+            guild["timezone"] = Value::from(zone.name());
+        }
+    }
+
+    Ok(())
+}
+
+/// Logs a change before it's applied, see `wal`.
+pub fn log(op: Op) {
+    if STORE.is_some() {
+        wal::append(&op);
+    }
+}
+
+/// Changes a counter in the backend as well, the caller changes it in memory.
+pub fn add(counter: Counter, by: i64) {
+    if let Some(store) = &*STORE {
+        if let Err(why) = store.add(counter, by) {
+            log!("Could not count {:?}: {}", counter, why);
+        }
+    }
+}
+
+/// Saves what `snapshot` returns, nothing is copied without a store. Returns whether it worked.
+pub fn save(snapshot: fn() -> State) -> bool {
+    let store = match &*STORE {
+        Some(store) => store,
+        None => return true,
+    };
+
+    let _saving = SAVING.lock().unwrap();
+    // changes logged before this are in the snapshot, their maps were locked while logging
+    let logged = wal::len();
+    let saved = match store.save(&snapshot()) {
+        Ok(()) => {
+            wal::truncate(logged);
+            true
+        }
+        Err(why) => {
+            log!("Could not save state: {}", why);
+            false
+        }
+    };
+    *LAST_SAVE.lock().unwrap() = Some((Utc::now(), saved));
+    saved
+}
+
+/// When the last `save` was tried and whether it worked, `None` before the first one.
+pub fn last_save() -> Option<(DateTime<Utc>, bool)> {
+    *LAST_SAVE.lock().unwrap()
+}
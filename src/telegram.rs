@@ -0,0 +1,66 @@
+//! Mirror of new suggestions and round results in a Telegram channel, through the Bot API.
+use crate::config::CONFIG;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::thread;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Telegram {
+    /// token of the Telegram bot, it has to be an admin of the channel
+    pub token: String,
+    /// id or `@name` of the channel
+    pub chat: String,
+}
+
+/// File of a suggestion, Telegram fetches it from Discord by itself.
+pub struct Media {
+    pub url: String,
+    pub animated: bool,
+}
+
+/// Sends `text` to the channel, with `media` as photo or animation above it. Runs in the
+/// background like the webhooks, Discord's bold markers are dropped.
+pub fn post(text: String, media: Option<Media>) {
+    let telegram = match &CONFIG.read().unwrap().telegram {
+        Some(x) => x.clone(),
+        None => return,
+    };
+    let text = text.replace("**", "");
+
+    thread::spawn(move || {
+        let (method, body): (&str, Value) = match media {
+            Some(Media {
+                url,
+                animated: true,
+            }) => (
+                "sendAnimation",
+                json!({ "chat_id": telegram.chat, "animation": url, "caption": text }),
+            ),
+            Some(Media { url, .. }) => (
+                "sendPhoto",
+                json!({ "chat_id": telegram.chat, "photo": url, "caption": text }),
+            ),
+            None => (
+                "sendMessage",
+                json!({ "chat_id": telegram.chat, "text": text }),
+            ),
+        };
+
+        let sent = Client::new()
+            .post(&format!(
+                "https://api.telegram.org/bot{}/{}",
+                telegram.token, method
+            ))
+            .json(&body)
+            .send()
+            .and_then(|r| r.error_for_status());
+        if let Err(why) = sent {
+            // the error would print the url and with it the token
+            let reason = why
+                .status()
+                .map_or_else(|| String::from("no response"), |s| s.to_string());
+            log!("Could not post to Telegram: {}", reason);
+        }
+    });
+}
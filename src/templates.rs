@@ -0,0 +1,236 @@
+//! Texts of announcements, DMs and result posts that admins can replace with `templates` in the
+//! config, and the round announcements of `announcements` that can have an embed too.
+//! Placeholders like `{name}` get the values of the message, other braces stay as they are.
+use crate::config::CONFIG;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serenity::{
+    http::Http,
+    model::{channel::Message, id::ChannelId},
+};
+
+/// Every template with the placeholders it gets.
+pub const KEYS: [(&str, &[&str]); 7] = [
+    ("new_suggestion", &["name", "author", "link"]),
+    ("approved", &["name"]),
+    ("rejected", &["name"]),
+    (
+        "archive",
+        &["name", "author", "up", "down", "score", "verdict"],
+    ),
+    (
+        "hall_of_fame",
+        &["name", "author", "up", "down", "score", "verdict"],
+    ),
+    ("runoff_won", &["name", "author", "id"]),
+    (
+        "news",
+        &["name", "author", "up", "down", "score", "verdict"],
+    ),
+];
+
+/// Every round announcement with its default text and placeholders. The optional parts of
+/// `round_closed` start with a line break when they are there and are empty otherwise.
+pub const ANNOUNCEMENTS: [(&str, &str, &[&str]); 3] = [
+    (
+        "round_started",
+        "A new round starts, nominations are open and the voting starts {voting_at}.",
+        &["voting_at"],
+    ),
+    (
+        "voting_opened",
+        "The voting is open, {count} suggestions are in.",
+        &["count"],
+    ),
+    (
+        "round_closed",
+        "Round closed, {losers} suggestions lost. Winners, upload them with accept:{winners}\
+         {per_category}{runoff}{next}{rotation}",
+        &[
+            "losers",
+            "winners",
+            "winner_count",
+            "per_category",
+            "runoff",
+            "next",
+            "rotation",
+            "date",
+        ],
+    ),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Announcement {
+    /// mentions like `<@&role id>` ping as usual
+    pub content: String,
+    pub embed: Option<Embed>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Embed {
+    pub title: String,
+    pub description: String,
+    pub footer: String,
+    pub color: Option<u32>,
+}
+
+impl Announcement {
+    /// The text for the webhooks and Telegram, they get no embed.
+    pub fn text(&self) -> String {
+        match (&self.embed, self.content.is_empty()) {
+            (Some(embed), true) => format!("{}\n{}", embed.title, embed.description)
+                .trim()
+                .to_string(),
+            _ => self.content.clone(),
+        }
+    }
+}
+
+/// `template` with the placeholders of `vars` filled in. The values are inserted in one pass, so
+/// a name with braces stays what it is.
+fn fill(template: &str, vars: &[(&str, String)]) -> String {
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let value = rest[start..].find('}').and_then(|end| {
+            let name = &rest[start + 1..start + end];
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| (v, start + end + 1))
+        });
+        match value {
+            Some((value, next)) => {
+                text.push_str(value);
+                rest = &rest[next..];
+            }
+            None => {
+                text.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    text.push_str(rest);
+
+    text
+}
+
+/// The template of `key` from the config, or `default`, filled in with `vars`.
+pub fn render(key: &str, default: &str, vars: &[(&str, String)]) -> String {
+    let template = CONFIG.read().unwrap().templates.get(key).cloned();
+    fill(template.as_deref().unwrap_or(default), vars)
+}
+
+/// The round announcement `key` of the config, or its default text, filled in with `vars`.
+pub fn announcement(key: &str, vars: &[(&str, String)]) -> Announcement {
+    let configured = CONFIG.read().unwrap().announcements.get(key).cloned();
+    let announcement = configured
+        .filter(|a| !a.content.is_empty() || a.embed.is_some())
+        .unwrap_or_else(|| Announcement {
+            content: ANNOUNCEMENTS
+                .iter()
+                .find(|(k, _, _)| *k == key)
+                .map_or("", |(_, default, _)| default)
+                .to_string(),
+            embed: None,
+        });
+
+    Announcement {
+        content: fill(&announcement.content, vars),
+        embed: announcement.embed.map(|e| Embed {
+            title: fill(&e.title, vars),
+            description: fill(&e.description, vars),
+            footer: fill(&e.footer, vars),
+            color: e.color,
+        }),
+    }
+}
+
+/// Sends `announcement` with an optional file, the embed shows it as its image. `silent` keeps
+/// the mentions from pinging, for previews.
+pub fn post(
+    http: &Http,
+    channel: ChannelId,
+    announcement: &Announcement,
+    file: Option<(&[u8], &str)>,
+    silent: bool,
+) -> serenity::Result<Message> {
+    channel.send_message(http, |m| {
+        if !announcement.content.is_empty() {
+            m.content(&announcement.content);
+        }
+        if let Some(embed) = &announcement.embed {
+            m.embed(|e| {
+                if !embed.title.is_empty() {
+                    e.title(&embed.title);
+                }
+                if !embed.description.is_empty() {
+                    e.description(&embed.description);
+                }
+                if !embed.footer.is_empty() {
+                    e.footer(|f| f.text(&embed.footer));
+                }
+                if let Some(color) = embed.color {
+                    e.color(color);
+                }
+                if let Some((_, name)) = file {
+                    e.attachment(name);
+                }
+                e
+            });
+        }
+        if let Some(file) = file {
+            m.add_file(file);
+        }
+        if silent {
+            m.0.insert("allowed_mentions", json!({ "parse": [] }));
+        }
+        m
+    })
+}
+
+/// Keys and placeholders of the configured templates and announcements that don't exist, for
+/// the startup.
+pub fn unknown() -> Vec<String> {
+    let config = CONFIG.read().unwrap();
+    let mut unknown = Vec::new();
+    let mut check = |kind: &str, key: &str, known: Option<&[&str]>, texts: Vec<&str>| {
+        let vars = match known {
+            Some(x) => x,
+            None => return unknown.push(format!("{} {}", kind, key)),
+        };
+        for part in texts.iter().flat_map(|t| t.split('{').skip(1)) {
+            if let Some(name) = part.split('}').next().filter(|_| part.contains('}')) {
+                if !name.is_empty()
+                    && !vars.contains(&name)
+                    && name.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+                {
+                    unknown.push(format!("{{{}}} in {} {}", name, kind, key));
+                }
+            }
+        }
+    };
+
+    for (key, template) in config.templates.iter() {
+        let known = KEYS.iter().find(|(k, _)| k == key).map(|(_, vars)| *vars);
+        check("template", key, known, vec![template]);
+    }
+    for (key, announcement) in config.announcements.iter() {
+        let known = ANNOUNCEMENTS
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, _, vars)| *vars);
+        let mut texts = vec![announcement.content.as_str()];
+        if let Some(embed) = &announcement.embed {
+            texts.extend(&[
+                embed.title.as_str(),
+                embed.description.as_str(),
+                embed.footer.as_str(),
+            ]);
+        }
+        check("announcement", key, known, texts);
+    }
+    unknown
+}
@@ -0,0 +1,311 @@
+//! The submission pipeline against `api::mock::Mock`, without a bot or a store.
+use super::*;
+use api::mock::{Call, Mock};
+use config::Backend;
+use serde_json::json;
+use std::sync::{Mutex, MutexGuard, Once};
+
+static SETUP: Once = Once::new();
+lazy_static! {
+    /// the tests change the global config
+    static ref LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Keeps the tests away from the store, the images and the log of a real bot.
+fn setup() -> MutexGuard<'static, ()> {
+    SETUP.call_once(|| {
+        let dir = env::temp_dir().join(format!("touhyou-tests-{}", std::process::id()));
+        env::set_var("EMOTE_CONFIG", dir.join("config.json"));
+        env::set_var("EMOTE_IMAGES", dir.join("images"));
+        env::set_var("EMOTE_WAL", dir.join("wal.jsonl"));
+        CONFIG.write().unwrap().store = Backend::Memory;
+    });
+
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut config = CONFIG.write().unwrap();
+    config.approval_queue = false;
+    config.mod_channel = None;
+    config.nsfw = None;
+    config.veto_rules = Vec::new();
+    drop(config);
+    guard
+}
+
+fn command() -> Message {
+    serde_json::from_value(json!({
+        "id": "100",
+        "channel_id": "200",
+        "author": {"id": "300", "username": "author", "discriminator": "0001"},
+        "content": ">>add Test",
+        "attachments": [],
+        "embeds": [],
+        "type": 0,
+        "mention_everyone": false,
+        "mention_roles": [],
+        "mentions": [],
+        "pinned": false,
+        "timestamp": "2020-01-01T00:00:00+00:00",
+        "tts": false,
+    }))
+    .unwrap()
+}
+
+fn emote(name: &str, kind: Kind) -> Emote {
+    Emote {
+        name: name.to_string(),
+        author: String::from("author"),
+        author_id: UserId(300),
+        kind,
+        animated: false,
+        hash: file_hash(name.as_bytes()),
+        legacy: false,
+        category: None,
+        image: None,
+    }
+}
+
+fn posts(calls: &[Call]) -> Vec<(String, Vec<String>, Vec<String>)> {
+    calls
+        .iter()
+        .filter_map(|call| match call {
+            Call::Post {
+                content,
+                files,
+                reactions,
+                ..
+            } => Some((content.clone(), files.clone(), reactions.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn emoji_goes_into_the_voting() {
+    let _lock = setup();
+    let mock = Mock::new();
+
+    let submitted = submit(
+        &mock,
+        &command(),
+        emote("Emoji", Kind::Emoji),
+        b"png",
+        "Emoji.png",
+    );
+    let emsg = match submitted {
+        Ok(Submitted::Voting(emsg)) => emsg,
+        _ => panic!("not in the voting"),
+    };
+
+    // uploaded for the preview and deleted again
+    let calls = mock.calls();
+    assert!(matches!(&calls[0], Call::UploadEmoji(GUILD, name) if name == "Emoji"));
+    let preview = match calls.last() {
+        Some(Call::RemoveEmoji(GUILD, id)) => *id,
+        _ => panic!("the preview is still there"),
+    };
+    let seeded = seed_votes().iter().map(|r| r.as_data()).collect::<Vec<_>>();
+    assert_eq!(
+        posts(&calls),
+        vec![
+            (
+                String::from("Emoji"),
+                vec![String::from("Emoji.png")],
+                Vec::new()
+            ),
+            (format!("<:Emoji:{}>", preview), Vec::new(), seeded),
+        ]
+    );
+    assert_eq!(emsg.messages[0].channel_id, CHANNEL);
+    assert_eq!(emsg.emote.image, Some(file_hash(b"png")));
+}
+
+#[test]
+fn sound_needs_no_upload() {
+    let _lock = setup();
+    let mock = Mock::new();
+
+    let submitted = submit(
+        &mock,
+        &command(),
+        emote("Sound", Kind::Sound),
+        b"ogg",
+        "Sound.ogg",
+    );
+    assert!(matches!(submitted, Ok(Submitted::Voting(_))));
+
+    let calls = mock.calls();
+    assert!(calls.iter().all(|c| matches!(c, Call::Post { .. })));
+    assert_eq!(posts(&calls)[1].0, "Sound: Sound");
+}
+
+#[test]
+fn approval_queue_waits_for_the_mods() {
+    let _lock = setup();
+    CONFIG.write().unwrap().approval_queue = true;
+    CONFIG.write().unwrap().mod_channel = Some(ChannelId(400));
+    let mock = Mock::new();
+
+    let submitted = submit(
+        &mock,
+        &command(),
+        emote("Queued", Kind::Emoji),
+        b"png",
+        "Queued.png",
+    );
+    let emsg = match submitted {
+        Ok(Submitted::Queued(emsg)) => emsg,
+        _ => panic!("not queued"),
+    };
+
+    let calls = mock.calls();
+    assert!(calls
+        .iter()
+        .all(|c| matches!(c, Call::Post { channel, .. } if *channel == ChannelId(400))));
+    let posts = posts(&calls);
+    assert_eq!(posts[0].1, vec![format!("{}Queued.png", SPOILER)]);
+    assert_eq!(posts[1].2, vec![APPROVE, REJECT]);
+    assert_eq!(emsg.messages[1].channel_id, ChannelId(400));
+}
+
+#[test]
+fn approval_queue_without_mod_channel() {
+    let _lock = setup();
+    CONFIG.write().unwrap().approval_queue = true;
+    let mock = Mock::new();
+
+    let submitted = submit(
+        &mock,
+        &command(),
+        emote("Nowhere", Kind::Emoji),
+        b"png",
+        "a.png",
+    );
+    assert!(submitted.is_err());
+    assert_eq!(
+        mock.calls(),
+        vec![Call::Dm(
+            UserId(300),
+            String::from("This can't be posted, pls ask a mod.")
+        )]
+    );
+}
+
+#[test]
+fn discord_error_tells_the_author() {
+    let _lock = setup();
+    let mock = Mock::new();
+    *mock.fail.lock().unwrap() = true;
+
+    let submitted = submit(
+        &mock,
+        &command(),
+        emote("Failing", Kind::Emoji),
+        b"png",
+        "a.png",
+    );
+    assert!(submitted.is_err());
+    assert_eq!(
+        mock.calls().last(),
+        Some(&Call::Dm(
+            UserId(300),
+            String::from("Discord error, pls try again later.")
+        ))
+    );
+}
+
+#[test]
+fn removed_suggestion_is_rejected() {
+    let _lock = setup();
+    let mock = Mock::new();
+    let emsg = publish(&mock, emote("Removed", Kind::Sticker), b"png", "a.png").unwrap();
+    let id = emsg.messages[1].id;
+    let mut messages = HashMap::new();
+    messages.insert(id, emsg.clone());
+    mock.calls.lock().unwrap().clear();
+
+    assert_eq!(remove_suggestion(&mock, &mut messages, id), Ok(()));
+    assert_eq!(
+        mock.calls(),
+        vec![
+            Call::Fetch(CHANNEL, id),
+            Call::Remove(CHANNEL, emsg.messages[0].id),
+            Call::Remove(CHANNEL, id),
+        ]
+    );
+    assert!(messages.is_empty());
+    assert!(mock.messages.lock().unwrap().is_empty());
+    assert!(HISTORY
+        .read()
+        .unwrap()
+        .iter()
+        .any(|f| f.id == id && f.name == "Removed" && !f.accepted && f.votes == (0, 0)));
+
+    assert!(remove_suggestion(&mock, &mut messages, id).is_err());
+}
+
+#[test]
+fn markdown_table_newest_first() {
+    let finished = |name: &str, votes, accepted, day| Finished {
+        id: MessageId(1),
+        name: name.to_string(),
+        author: String::from("a|b"),
+        author_id: UserId(1),
+        hash: 0,
+        legacy: false,
+        votes,
+        accepted,
+        at: Utc.with_ymd_and_hms(2021, 5, day, 12, 0, 0).unwrap(),
+    };
+    let table = markdown_table(&[
+        finished("Old", (3, 12), false, 1),
+        finished("NewEmote", (120, 4), true, 2),
+    ]);
+
+    assert_eq!(
+        table,
+        "| Name     | Author |   👍 |   👎 | Score | Verdict                |\n\
+         | -------- | ------ | --: | --: | ----: | ---------------------- |\n\
+         | NewEmote | a\\|b   | 120 |   4 |   116 | accepted on 2021-05-02 |\n\
+         | Old      | a\\|b   |   3 |  12 |    -9 | rejected on 2021-05-01 |\n"
+    );
+}
+
+#[test]
+fn vetoes_for_every_kind() {
+    let _lock = setup();
+    let veto = |when: &str| script::Veto {
+        when: when.to_string(),
+        reason: when.to_string(),
+    };
+    CONFIG.write().unwrap().veto_rules =
+        vec![veto("width < 64"), veto("kind == \"sound\" && tally >= 2")];
+    let author = command().author;
+    let sound = |tally| validate::Submission {
+        kind: Kind::Sound,
+        name: "Sound",
+        author: &author,
+        category: None,
+        tally,
+    };
+
+    // the dimensions are left to the image check
+    assert_eq!(validate::submission(&sound(1)), Ok(()));
+    assert_eq!(
+        validate::submission(&sound(2)),
+        Err(String::from("kind == \"sound\" && tally >= 2"))
+    );
+
+    let emoji = validate::Submission {
+        kind: Kind::Emoji,
+        ..sound(2)
+    };
+    let mut png = Vec::new();
+    image::DynamicImage::new_rgba8(32, 32)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+    assert_eq!(validate::submission(&emoji), Ok(()));
+    assert_eq!(
+        validate::image(&emoji, &png),
+        Err(String::from("width < 64"))
+    );
+}
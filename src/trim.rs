@@ -0,0 +1,114 @@
+//! Auto-crop of uniform borders, otherwise emotes with huge margins look tiny at 128px.
+use image::{imageops, DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+/// How far a channel may differ from the border color to still count as border.
+const TOLERANCE: i16 = 16;
+
+fn is_border(pixel: &Rgba<u8>, border: &Rgba<u8>) -> bool {
+    // every transparent pixel is the same, no matter what color it carries
+    if border[3] <= TOLERANCE as u8 {
+        return pixel[3] <= TOLERANCE as u8;
+    }
+
+    pixel
+        .0
+        .iter()
+        .zip(border.0.iter())
+        .all(|(a, b)| (*a as i16 - *b as i16).abs() <= TOLERANCE)
+}
+
+/// Bounding box `(x, y, width, height)` of everything that isn't border, the border color is
+/// taken from the top left pixel. `None` if the whole image is border.
+pub fn bounds(img: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+    let border = *img.get_pixel(0, 0);
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if !is_border(pixel, &border) {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+
+    if min_x > max_x {
+        return None;
+    }
+
+    Some((min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
+
+/// Smallest box containing all the given boxes.
+pub fn union(a: (u32, u32, u32, u32), b: (u32, u32, u32, u32)) -> (u32, u32, u32, u32) {
+    let x = a.0.min(b.0);
+    let y = a.1.min(b.1);
+    let right = (a.0 + a.2).max(b.0 + b.2);
+    let bottom = (a.1 + a.3).max(b.1 + b.3);
+
+    (x, y, right - x, bottom - y)
+}
+
+pub fn trim(img: &DynamicImage) -> DynamicImage {
+    match bounds(&img.to_rgba8()) {
+        Some((x, y, width, height)) if (width, height) != img.dimensions() => {
+            img.crop_imm(x, y, width, height)
+        }
+        _ => img.clone(),
+    }
+}
+
+pub fn crop(img: &RgbaImage, (x, y, width, height): (u32, u32, u32, u32)) -> RgbaImage {
+    imageops::crop_imm(img, x, y, width, height).to_image()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `width`x`height` image of `border` with a `fill` rectangle at `(x, y, w, h)`.
+    fn framed(
+        width: u32,
+        height: u32,
+        border: [u8; 4],
+        fill: [u8; 4],
+        rect: (u32, u32, u32, u32),
+    ) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let inside = x >= rect.0 && x < rect.0 + rect.2 && y >= rect.1 && y < rect.1 + rect.3;
+            Rgba(if inside { fill } else { border })
+        })
+    }
+
+    #[test]
+    fn transparent_border() {
+        let img = framed(64, 48, [255, 0, 0, 0], [10, 20, 30, 255], (10, 5, 20, 30));
+        assert_eq!(bounds(&img), Some((10, 5, 20, 30)));
+
+        let trimmed = trim(&DynamicImage::ImageRgba8(img));
+        assert_eq!(trimmed.dimensions(), (20, 30));
+    }
+
+    #[test]
+    fn border_within_tolerance() {
+        let mut img = framed(32, 32, [255, 255, 255, 255], [0, 0, 0, 255], (8, 8, 4, 4));
+        // a bit of noise in the white border is still border
+        img.put_pixel(30, 30, Rgba([250, 245, 255, 255]));
+        assert_eq!(bounds(&img), Some((8, 8, 4, 4)));
+    }
+
+    #[test]
+    fn nothing_to_trim() {
+        let img = framed(16, 16, [0, 0, 0, 255], [0, 0, 0, 255], (0, 0, 0, 0));
+        assert_eq!(bounds(&img), None);
+        assert_eq!(trim(&DynamicImage::ImageRgba8(img)).dimensions(), (16, 16));
+
+        let img = framed(16, 16, [0, 0, 0, 255], [200, 0, 0, 255], (0, 0, 16, 16));
+        assert_eq!(trim(&DynamicImage::ImageRgba8(img)).dimensions(), (16, 16));
+    }
+
+    #[test]
+    fn union_of_boxes() {
+        assert_eq!(union((2, 3, 4, 5), (0, 6, 3, 10)), (0, 3, 6, 13));
+    }
+}
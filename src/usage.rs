@@ -0,0 +1,55 @@
+//! How often every command ran since the start, how often it failed and how long it took,
+//! recorded by the `before` and `after` hooks of the framework.
+use lazy_static::lazy_static;
+use serenity::model::id::MessageId;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Default, Clone, Copy)]
+pub struct Usage {
+    pub calls: u64,
+    pub failures: u64,
+    pub total: Duration,
+}
+
+lazy_static! {
+    /// commands that are running, by the message that started them
+    static ref RUNNING: Mutex<HashMap<MessageId, Instant>> = Mutex::new(HashMap::new());
+    static ref USAGE: Mutex<HashMap<String, Usage>> = Mutex::new(HashMap::new());
+}
+
+pub fn start(msg: MessageId) {
+    RUNNING.lock().unwrap().insert(msg, Instant::now());
+}
+
+pub fn finish(msg: MessageId, command: &str, failed: bool) {
+    let took = RUNNING
+        .lock()
+        .unwrap()
+        .remove(&msg)
+        .map_or(Duration::default(), |start| start.elapsed());
+
+    let mut usage = USAGE.lock().unwrap();
+    let entry = usage.entry(command.to_string()).or_default();
+    entry.calls += 1;
+    entry.total += took;
+    if failed {
+        entry.failures += 1;
+    }
+}
+
+/// Every command that ran, the most used first.
+pub fn all() -> Vec<(String, Usage)> {
+    let mut all = USAGE
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(command, usage)| (command.clone(), *usage))
+        .collect::<Vec<_>>();
+    all.sort_by_key(|(_, usage)| Reverse(usage.calls));
+    all
+}
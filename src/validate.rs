@@ -0,0 +1,230 @@
+//! Rules every new suggestion has to pass, each one a `SubmissionValidator` in `CHAIN`. A rule of
+//! a single server only needs its own small validator appended there, the commands run the whole
+//! chain and DM the first refusal.
+use crate::{config::CONFIG, script, Kind};
+use chrono::{Duration, Utc};
+use image::{DynamicImage, GenericImageView, Pixel};
+use lazy_static::lazy_static;
+use serenity::model::user::User;
+
+/// How far (per channel, summed up) the border may be from a color of `banned_backgrounds`.
+const BACKGROUND_TOLERANCE: u32 = 60;
+/// Share of the border that has to be opaque to count as background.
+const OPAQUE_BORDER: f64 = 0.5;
+
+/// What the submission and image checks know about a new suggestion.
+pub struct Submission<'a> {
+    pub kind: Kind,
+    pub name: &'a str,
+    pub author: &'a User,
+    pub category: Option<&'a str>,
+    /// suggestions the author already has this round, `User::counter` before this one
+    pub tally: u64,
+}
+
+/// Every check is optional, the default lets everything through.
+pub trait SubmissionValidator: Send + Sync {
+    fn name(&self, _kind: Kind, _name: &str) -> Result<(), String> {
+        Ok(())
+    }
+    /// Checks of everything but the file, they run for every kind.
+    fn submission(&self, _submission: &Submission) -> Result<(), String> {
+        Ok(())
+    }
+    fn image(&self, _submission: &Submission, _image: &DynamicImage) -> Result<(), String> {
+        Ok(())
+    }
+    fn user(&self, _author: &User) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref CHAIN: Vec<Box<dyn SubmissionValidator>> = vec![
+        Box::new(DiscordNames),
+        Box::new(NamePrefix),
+        Box::new(Background),
+        Box::new(AccountAge),
+        Box::new(Vetoes),
+    ];
+}
+
+pub fn name(kind: Kind, name: &str) -> Result<(), String> {
+    CHAIN.iter().try_for_each(|v| v.name(kind, name))
+}
+
+pub fn submission(submission: &Submission) -> Result<(), String> {
+    CHAIN.iter().try_for_each(|v| v.submission(submission))
+}
+
+/// Images that don't decode are left to the processing, it explains the problem better.
+pub fn image(submission: &Submission, file: &[u8]) -> Result<(), String> {
+    match image::load_from_memory(file) {
+        Ok(image) => CHAIN.iter().try_for_each(|v| v.image(submission, &image)),
+        Err(_) => Ok(()),
+    }
+}
+
+pub fn user(author: &User) -> Result<(), String> {
+    CHAIN.iter().try_for_each(|v| v.user(author))
+}
+
+/// The name rules Discord applies when the suggestion gets uploaded.
+struct DiscordNames;
+
+impl SubmissionValidator for DiscordNames {
+    fn name(&self, kind: Kind, name: &str) -> Result<(), String> {
+        let len = name.chars().count();
+        let why = match kind {
+            Kind::Emoji if !(2..=32).contains(&len) => "Emote names must be 2-32 characters long.",
+            Kind::Emoji if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                "Emote names may only contain letters, numbers and underscores."
+            }
+            Kind::Sticker if !(2..=30).contains(&len) => {
+                "Sticker names must be 2-30 characters long."
+            }
+            Kind::Sound if !(2..=32).contains(&len) => "Sound names must be 2-32 characters long.",
+            _ => return Ok(()),
+        };
+        Err(why.to_string())
+    }
+}
+
+/// `name_prefix` of the config, so the emotes of the server stay together in the picker.
+struct NamePrefix;
+
+impl SubmissionValidator for NamePrefix {
+    fn name(&self, _kind: Kind, name: &str) -> Result<(), String> {
+        match &CONFIG.read().unwrap().name_prefix {
+            Some(prefix) if !name.starts_with(prefix.as_str()) => {
+                Err(format!("Names have to start with {}.", prefix))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `banned_backgrounds` of the config, the background is the average of the opaque border.
+struct Background;
+
+impl SubmissionValidator for Background {
+    fn image(&self, _submission: &Submission, image: &DynamicImage) -> Result<(), String> {
+        let banned = CONFIG.read().unwrap().banned_backgrounds.clone();
+        if banned.is_empty() {
+            return Ok(());
+        }
+
+        let (width, height) = image.dimensions();
+        let border = image
+            .pixels()
+            .filter(|(x, y, _)| *x == 0 || *y == 0 || *x == width - 1 || *y == height - 1)
+            .map(|(_, _, p)| p.to_rgba())
+            .collect::<Vec<_>>();
+        let opaque = border.iter().filter(|p| p[3] >= 128).collect::<Vec<_>>();
+        if opaque.is_empty() || (opaque.len() as f64) < border.len() as f64 * OPAQUE_BORDER {
+            return Ok(());
+        }
+
+        let mut sum = [0u64; 3];
+        for p in opaque.iter() {
+            for (s, c) in sum.iter_mut().zip(p.0.iter()) {
+                *s += *c as u64;
+            }
+        }
+        let average = sum.map(|s| (s / opaque.len() as u64) as u8);
+        match banned.iter().find(|color| {
+            color
+                .iter()
+                .zip(average.iter())
+                .map(|(a, b)| (*a as i32 - *b as i32).unsigned_abs())
+                .sum::<u32>()
+                <= BACKGROUND_TOLERANCE
+        }) {
+            Some(color) => Err(format!(
+                "Backgrounds in #{:02x}{:02x}{:02x} aren't allowed, pls remove it.",
+                color[0], color[1], color[2]
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `min_account_age_days` of the config, against throwaway accounts.
+struct AccountAge;
+
+impl SubmissionValidator for AccountAge {
+    fn user(&self, author: &User) -> Result<(), String> {
+        let days = match CONFIG.read().unwrap().min_account_age_days {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        match author.created_at() + Duration::days(days) > Utc::now() {
+            true => Err(format!(
+                "Accounts have to be {} days old before they can suggest something.",
+                days
+            )),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Variables only an image has, rules that read them wait for `validate::image`.
+const DIMENSIONS: [&str; 2] = ["width", "height"];
+
+/// `veto_rules` of the config, the first one that holds refuses the suggestion. Rules without
+/// the dimensions run for every kind, the others only for images.
+struct Vetoes;
+
+impl Vetoes {
+    fn vars(submission: &Submission) -> script::Vars {
+        let kind = match submission.kind {
+            Kind::Emoji => "emoji",
+            Kind::Sticker => "sticker",
+            Kind::Sound => "sound",
+        };
+        let mut vars = script::Vars::new();
+        vars.insert("name", submission.name.into());
+        vars.insert("author", submission.author.name.as_str().into());
+        vars.insert("kind", kind.into());
+        vars.insert("category", submission.category.unwrap_or("").into());
+        vars.insert("tally", submission.tally.into());
+        vars.insert(
+            "account_days",
+            ((Utc::now() - submission.author.created_at().with_timezone(&Utc)).num_days() as u64)
+                .into(),
+        );
+        vars
+    }
+
+    /// Runs the rules for which `image` says whether they need the dimensions.
+    fn check(vars: &script::Vars, image: bool) -> Result<(), String> {
+        let rules = CONFIG.read().unwrap().veto_rules.clone();
+        let rules = rules
+            .into_iter()
+            .filter(|rule| DIMENSIONS.iter().any(|d| script::reads(&rule.when, d)) == image);
+
+        for rule in rules {
+            match script::run(&rule.when, vars) {
+                Ok(script::Value::Bool(true)) => return Err(rule.reason),
+                Ok(script::Value::Bool(false)) => {}
+                // a broken rule is for the admins to fix, it doesn't hold up everyone
+                other => log!("veto rule {} failed: {:?}", rule.when, other),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SubmissionValidator for Vetoes {
+    fn submission(&self, submission: &Submission) -> Result<(), String> {
+        Vetoes::check(&Vetoes::vars(submission), false)
+    }
+
+    fn image(&self, submission: &Submission, image: &DynamicImage) -> Result<(), String> {
+        let (width, height) = image.dimensions();
+        let mut vars = Vetoes::vars(submission);
+        vars.insert("width", (width as u64).into());
+        vars.insert("height", (height as u64).into());
+        Vetoes::check(&vars, true)
+    }
+}
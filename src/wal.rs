@@ -0,0 +1,180 @@
+//! Every change is appended to `wal.jsonl` (or the file in `EMOTE_WAL`) before the next save
+//! of the store. The log gets replayed on top of the store at startup, so a crash between two
+//! saves loses nothing. Votes are reactions on Discord and need no log.
+use crate::{
+    store::State, EmoteMessage, Finished, ModAction, Pair, Replacement, Rotated, Round, Runoff,
+    Trashed, Trial, User, VariantPoll,
+};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{EmojiId, MessageId, UserId};
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    sync::Mutex,
+};
+
+/// A change to the state, logged while the lock of the changed map is held.
+#[derive(Serialize, Deserialize)]
+pub enum Op {
+    User(UserId, User),
+    /// new or changed pending suggestion
+    Suggestion(Box<EmoteMessage>),
+    /// suggestion left the voting, by the id of its vote message
+    Removed(MessageId),
+    /// new suggestion in the approval queue
+    Queued(Box<EmoteMessage>),
+    /// approved or rejected, by the id of its decision message
+    Dequeued(MessageId),
+    /// removed suggestion waiting out its undo window
+    Trash(MessageId, Box<Trashed>),
+    /// undone or deleted for good
+    TrashDone(MessageId),
+    Variant(MessageId, Box<VariantPoll>),
+    VariantDone(MessageId),
+    Runoff(MessageId, Box<Runoff>),
+    RunoffDone(MessageId),
+    Replacement(MessageId, Box<Replacement>),
+    ReplacementDone(MessageId),
+    /// new open pair, or `None` once it's counted
+    Pair(Option<Box<Pair>>),
+    Round(Round),
+    Finished(Finished),
+    /// the emotes of the `rotation_slots` after a closed round
+    Rotation(Vec<Rotated>),
+    /// new or changed trial of an uploaded emote
+    Trial(EmojiId, Box<Trial>),
+    TrialDone(EmojiId),
+    ModAction(ModAction),
+}
+
+lazy_static! {
+    static ref LOG: Mutex<()> = Mutex::new(());
+}
+
+fn path() -> String {
+    env::var("EMOTE_WAL").unwrap_or_else(|_| String::from("wal.jsonl"))
+}
+
+/// Appends `op` and waits until it's on disk.
+pub fn append(op: &Op) {
+    let _log = LOG.lock().unwrap();
+
+    let written = serde_json::to_string(op)
+        .map_err(|why| why.to_string())
+        .and_then(|line| {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path())
+                .map_err(|why| why.to_string())?;
+            writeln!(file, "{}", line).map_err(|why| why.to_string())?;
+            file.sync_data().map_err(|why| why.to_string())
+        });
+
+    if let Err(why) = written {
+        log!("Could not write to the log: {}", why);
+    }
+}
+
+/// Bytes in the log, everything before this is part of the next snapshot.
+pub fn len() -> u64 {
+    let _log = LOG.lock().unwrap();
+    fs::metadata(path()).map_or(0, |m| m.len())
+}
+
+/// Drops the first `len` bytes after they made it into the store.
+pub fn truncate(len: u64) {
+    let _log = LOG.lock().unwrap();
+
+    let content = match fs::read(path()) {
+        Ok(x) => x,
+        Err(_) => return,
+    };
+    let rest = &content[(len as usize).min(content.len())..];
+    let tmp = format!("{}.tmp", path());
+    if let Err(why) = fs::write(&tmp, rest).and_then(|_| fs::rename(&tmp, path())) {
+        log!("Could not truncate the log: {}", why);
+    }
+}
+
+/// Applies the logged changes to `state`, changes that were already saved don't hurt.
+pub fn replay(state: &mut State) {
+    let content = fs::read_to_string(path()).unwrap_or_default();
+
+    // a crash while appending leaves half a line at the end
+    for op in content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Op>(line).ok())
+    {
+        match op {
+            Op::User(id, user) => {
+                state.users.insert(id, user);
+            }
+            Op::Suggestion(emsg) => {
+                state.suggestions.insert(emsg.messages[1].id, *emsg);
+            }
+            Op::Removed(id) => {
+                state.suggestions.remove(&id);
+            }
+            Op::Queued(emsg) => {
+                state.queue.insert(emsg.messages[1].id, *emsg);
+            }
+            Op::Dequeued(id) => {
+                state.queue.remove(&id);
+            }
+            Op::Trash(id, trashed) => {
+                state.trash.insert(id, *trashed);
+            }
+            Op::TrashDone(id) => {
+                state.trash.remove(&id);
+            }
+            Op::Variant(id, poll) => {
+                state.variants.insert(id, *poll);
+            }
+            Op::VariantDone(id) => {
+                state.variants.remove(&id);
+            }
+            Op::Runoff(id, runoff) => {
+                state.runoffs.insert(id, *runoff);
+            }
+            Op::RunoffDone(id) => {
+                state.runoffs.remove(&id);
+            }
+            Op::Replacement(id, replacement) => {
+                state.replacements.insert(id, *replacement);
+            }
+            Op::ReplacementDone(id) => {
+                state.replacements.remove(&id);
+            }
+            Op::Pair(pair) => state.pair = pair.map(|p| *p),
+            Op::Round(round) => state.round = round,
+            Op::Rotation(rotation) => state.rotation = rotation,
+            Op::Trial(id, trial) => {
+                state.trials.insert(id, *trial);
+            }
+            Op::TrialDone(id) => {
+                state.trials.remove(&id);
+            }
+            Op::Finished(finished) => {
+                if !state
+                    .history
+                    .iter()
+                    .any(|f| f.id == finished.id && f.at == finished.at)
+                {
+                    state.history.push(finished);
+                }
+            }
+            Op::ModAction(action) => {
+                if !state
+                    .modlog
+                    .iter()
+                    .any(|a| a.actor_id == action.actor_id && a.at == action.at)
+                {
+                    state.modlog.push(action);
+                }
+            }
+        }
+    }
+}
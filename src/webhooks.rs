@@ -0,0 +1,73 @@
+//! Round events posted to other services (a website, a Twitch bot), as plain JSON or in the
+//! format of Discord webhooks.
+use crate::config::CONFIG;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::thread;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    /// a suggestion went into the voting
+    Submission,
+    /// a round was closed
+    Closed,
+    /// the winners of a round or a runoff were announced
+    Winners,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// the event data with its name in `event`
+    #[default]
+    Json,
+    /// a message with the text of the event
+    Discord,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub format: Format,
+    /// empty gets every event
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+/// Posts `event` to every webhook that wants it. Runs in the background, a slow service can't
+/// hold up the bot and failures only get logged.
+pub fn fire(event: Event, text: String, mut data: Value) {
+    let hooks = CONFIG
+        .read()
+        .unwrap()
+        .webhooks
+        .iter()
+        .filter(|h| h.events.is_empty() || h.events.contains(&event))
+        .cloned()
+        .collect::<Vec<_>>();
+    if hooks.is_empty() {
+        return;
+    }
+    data["event"] = json!(event);
+
+    thread::spawn(move || {
+        let client = Client::new();
+        for hook in hooks {
+            let body = match hook.format {
+                Format::Json => data.clone(),
+                Format::Discord => json!({ "content": text }),
+            };
+            if let Err(why) = client
+                .post(&hook.url)
+                .json(&body)
+                .send()
+                .and_then(|r| r.error_for_status())
+            {
+                log!("Could not call webhook {}: {}", hook.url, why);
+            }
+        }
+    });
+}